@@ -0,0 +1,214 @@
+//! `#[derive(Consume)]` for the [`consume_on_drop`](https://docs.rs/consume_on_drop) crate.
+//! Enable the `derive` feature of `consume_on_drop` and use the derive from there, rather than
+//! depending on this crate directly.
+
+use proc_macro::TokenStream;
+use proc_macro2::{TokenStream as TokenStream2, TokenTree};
+use quote::{format_ident, quote};
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+use syn::{
+    parse_macro_input, parse_quote, Attribute, Data, DeriveInput, Error, Fields, Ident, LitStr,
+    Path, Result, Type, WherePredicate,
+};
+
+/// Derives `Consume` for a struct or an enum.
+///
+/// With `#[consume(with = "path::to::function")]` on the type, the value is consumed by passing
+/// it to the function, which takes it by value. Otherwise, the value is destructured, and its
+/// fields are consumed in order of declaration, with the same guarantees as for a tuple: if
+/// consuming one field panics, the later fields are still consumed while unwinding. Each field is
+/// consumed with `Consume::consume`, unless it is marked with:
+///
+/// - `#[consume(with = "path::to::function")]`, which passes the field to the function instead;
+/// - `#[consume(skip)]`, which drops the field normally instead.
+///
+/// Since destructuring is not allowed for types implementing [`Drop`], such types must use
+/// `#[consume(with = "...")]` on the type.
+///
+/// ```ignore
+/// use consume_on_drop::Consume;
+///
+/// #[derive(Consume)]
+/// #[consume(with = "close_connection")]
+/// struct Connection(RawConnection);
+///
+/// #[derive(Consume)]
+/// struct Session {
+///     connection: Connection,
+///     #[consume(with = "flush_log")]
+///     log: Log,
+///     #[consume(skip)]
+///     name: String,
+/// }
+/// ```
+#[proc_macro_derive(Consume, attributes(consume))]
+pub fn derive_consume(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// How a field is consumed.
+enum FieldMode {
+    Consume,
+    With(Path),
+    Skip,
+}
+
+/// Parses the `#[consume(...)]` attributes in `attrs`. Returns `None` if there are none.
+fn parse_attrs(attrs: &[Attribute], allow_skip: bool) -> Result<Option<FieldMode>> {
+    let mut mode = None;
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("consume")) {
+        attr.parse_nested_meta(|meta| {
+            if mode.is_some() {
+                return Err(meta.error("only one of `with` and `skip` may be given"));
+            }
+            if meta.path.is_ident("with") {
+                let path: LitStr = meta.value()?.parse()?;
+                mode = Some(FieldMode::With(path.parse()?));
+                Ok(())
+            } else if allow_skip && meta.path.is_ident("skip") {
+                mode = Some(FieldMode::Skip);
+                Ok(())
+            } else {
+                Err(meta.error("unsupported `consume` attribute"))
+            }
+        })?;
+    }
+    Ok(mode)
+}
+
+/// Whether `ty` mentions any of `params`.
+fn mentions(ty: &Type, params: &[Ident]) -> bool {
+    fn walk(tokens: TokenStream2, params: &[Ident]) -> bool {
+        tokens.into_iter().any(|token| match token {
+            TokenTree::Ident(ident) => params.contains(&ident),
+            TokenTree::Group(group) => walk(group.stream(), params),
+            _ => false,
+        })
+    }
+    walk(quote!(#ty), params)
+}
+
+/// Destructures `fields` into bindings, and builds the expression consuming them in order.
+/// Adds a bound for every field consumed with `Consume::consume` whose type mentions `params`.
+fn consume_fields(
+    fields: &Fields,
+    params: &[Ident],
+    bounds: &mut Vec<WherePredicate>,
+) -> Result<(TokenStream2, TokenStream2)> {
+    let mut patterns = Vec::new();
+    let mut parts = Vec::new();
+    for (index, field) in fields.iter().enumerate() {
+        let binding = format_ident!("__field{}", index);
+        let member = match &field.ident {
+            Some(ident) => quote!(#ident),
+            None => {
+                let index = syn::Index::from(index);
+                quote!(#index)
+            }
+        };
+        match parse_attrs(&field.attrs, true)?.unwrap_or(FieldMode::Consume) {
+            FieldMode::Consume => {
+                let ty = &field.ty;
+                if mentions(ty, params) {
+                    bounds.push(parse_quote!(#ty: ::consume_on_drop::Consume));
+                }
+                parts.push(quote!(#binding));
+            }
+            FieldMode::With(path) => {
+                parts.push(quote!(::consume_on_drop::Closure(move || #path(#binding))));
+            }
+            // Skipped fields stay behind, and are dropped at the end of `consume`.
+            FieldMode::Skip => continue,
+        }
+        patterns.push(quote!(#member: #binding));
+    }
+    let pattern = quote!({ #(#patterns,)* .. });
+    let consume = match parts.pop() {
+        None => quote!(),
+        Some(last) => {
+            let composed = parts.into_iter().rev().fold(
+                last,
+                |rest, part| quote!(::consume_on_drop::Composed::new(#part, #rest)),
+            );
+            quote!(::consume_on_drop::Consume::consume(#composed))
+        }
+    };
+    Ok((pattern, consume))
+}
+
+fn expand(input: DeriveInput) -> Result<TokenStream2> {
+    let params: Vec<Ident> = input
+        .generics
+        .type_params()
+        .map(|param| param.ident.clone())
+        .collect();
+    let mut bounds = Vec::new();
+    let body = match parse_attrs(&input.attrs, false)? {
+        Some(FieldMode::With(path)) => {
+            let fields: Box<dyn Iterator<Item = &syn::Field>> = match &input.data {
+                Data::Struct(data) => Box::new(data.fields.iter()),
+                Data::Enum(data) => Box::new(data.variants.iter().flat_map(|v| &v.fields)),
+                Data::Union(_) => Box::new(core::iter::empty()),
+            };
+            for field in fields {
+                if let Some(attr) = field.attrs.iter().find(|a| a.path().is_ident("consume")) {
+                    return Err(Error::new(
+                        attr.span(),
+                        "fields cannot have `consume` attributes when the type has one",
+                    ));
+                }
+            }
+            quote!(#path(self))
+        }
+        _ => match &input.data {
+            Data::Struct(data) => {
+                let (pattern, consume) = consume_fields(&data.fields, &params, &mut bounds)?;
+                quote! {
+                    let Self #pattern = self;
+                    #consume
+                }
+            }
+            Data::Enum(data) => {
+                let arms = data
+                    .variants
+                    .iter()
+                    .map(|variant| {
+                        let ident = &variant.ident;
+                        let (pattern, consume) =
+                            consume_fields(&variant.fields, &params, &mut bounds)?;
+                        Ok(quote!(Self::#ident #pattern => { #consume }))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                quote!(match self { #(#arms)* })
+            }
+            Data::Union(data) => {
+                return Err(Error::new(
+                    data.union_token.span,
+                    "unions can only derive `Consume` with `#[consume(with = \"...\")]`",
+                ))
+            }
+        },
+    };
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let mut predicates: Punctuated<WherePredicate, syn::Token![,]> = where_clause
+        .map(|clause| clause.predicates.clone())
+        .unwrap_or_default();
+    predicates.extend(bounds);
+    Ok(quote! {
+        impl #impl_generics ::consume_on_drop::Consume for #name #ty_generics
+        where
+            #predicates
+        {
+            #[inline]
+            fn consume(self) {
+                #body
+            }
+        }
+    })
+}
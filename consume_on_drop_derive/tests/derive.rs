@@ -0,0 +1,56 @@
+use consume_on_drop::{Closure, Consume, ConsumeOnDrop};
+use std::cell::RefCell;
+
+thread_local! {
+    static LOG: RefCell<Vec<&'static str>> = const { RefCell::new(Vec::new()) };
+}
+
+fn log(event: &'static str) {
+    LOG.with(|log| log.borrow_mut().push(event))
+}
+
+fn close(_: Handle) {
+    log("close")
+}
+
+#[derive(Consume)]
+#[consume(with = "close")]
+struct Handle;
+
+#[derive(Consume)]
+struct Session<T> {
+    handle: Handle,
+    #[consume(with = "log")]
+    name: &'static str,
+    #[consume(skip)]
+    _skipped: Handle,
+    extra: T,
+}
+
+#[derive(Consume)]
+enum Either<T> {
+    Left(Handle),
+    Right { value: T },
+    Neither,
+}
+
+#[test]
+fn derived() {
+    drop(ConsumeOnDrop::new(Session {
+        handle: Handle,
+        name: "session",
+        _skipped: Handle,
+        extra: Closure(|| log("extra")),
+    }));
+    drop(ConsumeOnDrop::new(Either::Left::<Handle>(Handle)));
+    drop(ConsumeOnDrop::new(Either::Right {
+        value: Closure(|| log("right")),
+    }));
+    drop(ConsumeOnDrop::new(Either::<Handle>::Neither));
+    LOG.with(|log| {
+        assert_eq!(
+            *log.borrow(),
+            ["close", "session", "extra", "close", "right"]
+        );
+    });
+}
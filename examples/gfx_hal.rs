@@ -1,7 +1,8 @@
+#![allow(clippy::empty_line_after_doc_comments, clippy::drop_non_drop)]
+
 use crate::gfx_simulation::Resource;
-use consume_on_drop::{Consume, ConsumeOnDrop, WithConsumer};
+use consume_on_drop::{consume_wrapper, Closure, ConsumeOnDrop, WithConsumer};
 use std::mem::{size_of, size_of_val};
-use std::ops::{Deref, DerefMut};
 
 /// In `gfx-hal`, resources must be consumed by custom functions which take `self` by value. It would
 /// be quite a bit more convenient to be able to use [`drop`] normally. We can solve this using
@@ -32,39 +33,15 @@ mod gfx_simulation {
     }
 }
 
-/// We can't implement [`Consume`] on the library type [`Resource`], so we need a wrapper.
-#[repr(transparent)]
-pub struct ConsumableResource(Resource);
-
-impl ConsumableResource {
-    pub fn into_inner(self) -> Resource {
-        self.0
-    }
+consume_wrapper! {
+    /// We can't implement [`Consume`](consume_on_drop::Consume) on the library type [`Resource`],
+    /// so we need a wrapper.
+    pub ConsumableResource(Resource) => Resource::destroy_resource;
 }
 
 impl Default for ConsumableResource {
     fn default() -> Self {
-        Self(Resource::create_resource())
-    }
-}
-
-impl Deref for ConsumableResource {
-    type Target = Resource;
-
-    fn deref(&self) -> &Self::Target {
-        &self.0
-    }
-}
-
-impl DerefMut for ConsumableResource {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
-    }
-}
-
-impl Consume for ConsumableResource {
-    fn consume(self) {
-        self.into_inner().destroy_resource()
+        Self::new(Resource::create_resource())
     }
 }
 
@@ -87,8 +64,10 @@ fn main() {
     // use it in monomorphic functions. The benefit is that we get to skip the boilerplate
     // of writing ConsumableResource.
 
-    let mut wrapped_resource =
-        WithConsumer::new(Resource::create_resource(), Resource::destroy_resource);
+    let mut wrapped_resource = WithConsumer::new(
+        Resource::create_resource(),
+        Closure(Resource::destroy_resource),
+    );
     // In this case, wrapped_resource takes up exactly as much space as a resource. This is a zero-cost
     // abstraction.
     assert_eq!(size_of_val(&wrapped_resource), size_of::<Resource>());
@@ -96,7 +75,10 @@ fn main() {
     wrapped_resource.borrow_resource();
     wrapped_resource.borrow_mut_resource();
     // We implicitly drop wrapped_resource here when we reassign the variable to a new value.
-    wrapped_resource = WithConsumer::new(Resource::create_resource(), Resource::destroy_resource);
+    wrapped_resource = WithConsumer::new(
+        Resource::create_resource(),
+        Closure(Resource::destroy_resource),
+    );
     println!("Finished with second resource. We won't destroy the last one.");
     let resource = WithConsumer::into_inner(wrapped_resource);
     drop(resource); // this does nothing
@@ -0,0 +1,162 @@
+use crate::{Consume, ConsumeOnDrop};
+use alloc::alloc::{alloc, alloc_zeroed, dealloc};
+use alloc::boxed::Box;
+use core::alloc::Layout;
+use core::ptr::{self, NonNull};
+
+#[derive(Debug)]
+struct RawAllocation {
+    ptr: NonNull<u8>,
+    layout: Layout,
+}
+
+impl Consume for RawAllocation {
+    #[inline]
+    fn consume(self) {
+        if self.layout.size() != 0 {
+            unsafe {
+                // SAFETY: every constructor of `AllocGuard` ensures that `ptr` was allocated by
+                // the global allocator with `layout`, and we only get here once.
+                dealloc(self.ptr.as_ptr(), self.layout)
+            }
+        }
+    }
+}
+
+/// An owned, possibly uninitialized allocation from the global allocator, which is
+/// deallocated (without running any destructors) when dropped.
+///
+/// Allocations with a size of zero never touch the allocator: they are represented by a
+/// dangling pointer aligned to the layout.
+#[derive(Debug)]
+pub struct AllocGuard {
+    inner: ConsumeOnDrop<RawAllocation>,
+}
+
+// SAFETY: an `AllocGuard` owns untyped memory from the global allocator, which may be
+// deallocated from any thread.
+unsafe impl Send for AllocGuard {}
+// SAFETY: shared references to an `AllocGuard` only expose the pointer and layout.
+unsafe impl Sync for AllocGuard {}
+
+impl AllocGuard {
+    fn allocate(layout: Layout, allocate: unsafe fn(Layout) -> *mut u8) -> Option<Self> {
+        let ptr = if layout.size() == 0 {
+            ptr::without_provenance_mut(layout.align())
+        } else {
+            // SAFETY: `layout` has a nonzero size.
+            unsafe { allocate(layout) }
+        };
+        let ptr = NonNull::new(ptr)?;
+        Some(Self {
+            inner: ConsumeOnDrop::new(RawAllocation { ptr, layout }),
+        })
+    }
+
+    /// Allocates uninitialized memory for `layout`, returning `None` if the allocator fails.
+    #[inline]
+    pub fn new(layout: Layout) -> Option<Self> {
+        Self::allocate(layout, alloc)
+    }
+
+    /// Allocates zeroed memory for `layout`, returning `None` if the allocator fails.
+    #[inline]
+    pub fn new_zeroed(layout: Layout) -> Option<Self> {
+        Self::allocate(layout, alloc_zeroed)
+    }
+
+    /// Takes ownership of an existing allocation.
+    ///
+    /// # Safety
+    ///
+    /// If `layout` has a nonzero size, `ptr` must have been allocated by the global allocator
+    /// with `layout`, and must not be deallocated by anyone else.
+    #[inline]
+    pub const unsafe fn from_raw_parts(ptr: NonNull<u8>, layout: Layout) -> Self {
+        Self {
+            inner: ConsumeOnDrop::new(RawAllocation { ptr, layout }),
+        }
+    }
+
+    /// Releases ownership of the allocation without deallocating it.
+    #[inline]
+    pub fn into_raw_parts(guard: Self) -> (NonNull<u8>, Layout) {
+        let raw = ConsumeOnDrop::into_inner(guard.inner);
+        (raw.ptr, raw.layout)
+    }
+
+    /// A pointer to the start of the allocation.
+    #[inline]
+    pub fn as_ptr(&self) -> NonNull<u8> {
+        self.inner.ptr
+    }
+
+    /// The layout the memory was allocated with.
+    #[inline]
+    pub fn layout(&self) -> Layout {
+        self.inner.layout
+    }
+
+    /// Converts the allocation into a [`Box<T>`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the allocation's layout is not exactly [`Layout::new::<T>()`](Layout::new),
+    /// in which case the memory is deallocated.
+    ///
+    /// # Safety
+    ///
+    /// The memory must hold a valid, initialized `T`.
+    #[inline]
+    pub unsafe fn assume_init<T>(guard: Self) -> Box<T> {
+        assert_eq!(guard.layout(), Layout::new::<T>());
+        let (ptr, _) = Self::into_raw_parts(guard);
+        // SAFETY: the memory was allocated by the global allocator with the layout of `T`, and
+        // the caller guarantees that it holds a valid `T`.
+        unsafe { Box::from_raw(ptr.cast::<T>().as_ptr()) }
+    }
+
+    /// Converts the allocation into a boxed slice of `len` elements.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the allocation's layout is not exactly that of `[T; len]`, in which case the
+    /// memory is deallocated.
+    ///
+    /// # Safety
+    ///
+    /// The memory must hold `len` valid, initialized `T`s.
+    #[inline]
+    pub unsafe fn assume_init_slice<T>(guard: Self, len: usize) -> Box<[T]> {
+        assert_eq!(Ok(guard.layout()), Layout::array::<T>(len));
+        let (ptr, _) = Self::into_raw_parts(guard);
+        let slice = ptr::slice_from_raw_parts_mut(ptr.cast::<T>().as_ptr(), len);
+        // SAFETY: as for `assume_init`, using the layout of `[T; len]`.
+        unsafe { Box::from_raw(slice) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AllocGuard;
+    use core::alloc::Layout;
+
+    #[test]
+    fn allocate_and_initialize() {
+        let guard = AllocGuard::new(Layout::new::<u64>()).unwrap();
+        unsafe { guard.as_ptr().cast::<u64>().write(42) };
+        let boxed = unsafe { AllocGuard::assume_init::<u64>(guard) };
+        assert_eq!(*boxed, 42);
+
+        // Dropping an uninitialized guard just deallocates it.
+        drop(AllocGuard::new(Layout::new::<[u8; 64]>()).unwrap());
+
+        let zeroed = AllocGuard::new_zeroed(Layout::array::<u32>(4).unwrap()).unwrap();
+        let slice = unsafe { AllocGuard::assume_init_slice::<u32>(zeroed, 4) };
+        assert_eq!(*slice, [0; 4]);
+
+        let empty = AllocGuard::new(Layout::new::<()>()).unwrap();
+        assert_eq!(empty.as_ptr().as_ptr() as usize, 1);
+        let () = *unsafe { AllocGuard::assume_init::<()>(empty) };
+    }
+}
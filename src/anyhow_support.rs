@@ -0,0 +1,126 @@
+use crate::{TryConsume, TryConsumer};
+use alloc::vec::Vec;
+use core::fmt;
+
+/// Collects the failures of many fallible cleanups as [`anyhow::Error`]s, so that bulk teardown
+/// can try every cleanup and report all of the failures at the end.
+#[derive(Debug, Default)]
+pub struct CleanupErrors {
+    errors: Vec<anyhow::Error>,
+}
+
+impl CleanupErrors {
+    /// Builds an empty collector.
+    #[inline]
+    pub const fn new() -> Self {
+        Self { errors: Vec::new() }
+    }
+
+    /// Consumes `value`, recording the error if consumption fails.
+    pub fn try_consume<T: TryConsume>(&mut self, value: T)
+    where
+        T::Error: Into<anyhow::Error>,
+    {
+        if let Err(error) = value.try_consume() {
+            self.push(error)
+        }
+    }
+
+    /// Consumes `value` with `consumer`, recording the error if consumption fails.
+    pub fn try_consume_with<T, Q: TryConsumer<T>>(&mut self, value: T, consumer: Q)
+    where
+        Q::Error: Into<anyhow::Error>,
+    {
+        if let Err(error) = consumer.try_consume(value) {
+            self.push(error)
+        }
+    }
+
+    /// Records a failure.
+    #[inline]
+    pub fn push(&mut self, error: impl Into<anyhow::Error>) {
+        self.errors.push(error.into())
+    }
+
+    /// The failures recorded so far.
+    #[inline]
+    pub fn errors(&self) -> &[anyhow::Error] {
+        &self.errors
+    }
+
+    /// Whether no failures have been recorded.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// Reports the recorded failures. A single failure is returned as is, while several are
+    /// combined into a [`MultipleErrors`].
+    pub fn into_result(self) -> anyhow::Result<()> {
+        let mut errors = self.errors;
+        match errors.len() {
+            0 => Ok(()),
+            1 => Err(errors.pop().unwrap()),
+            _ => Err(MultipleErrors { errors }.into()),
+        }
+    }
+}
+
+/// Several cleanup failures reported together by [`CleanupErrors::into_result`].
+#[derive(Debug)]
+pub struct MultipleErrors {
+    errors: Vec<anyhow::Error>,
+}
+
+impl MultipleErrors {
+    /// The individual failures, in the order they were recorded.
+    #[inline]
+    pub fn errors(&self) -> &[anyhow::Error] {
+        &self.errors
+    }
+
+    /// Unwraps the individual failures.
+    #[inline]
+    pub fn into_errors(self) -> Vec<anyhow::Error> {
+        self.errors
+    }
+}
+
+impl fmt::Display for MultipleErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} cleanups failed", self.errors.len())?;
+        for (i, error) in self.errors.iter().enumerate() {
+            let separator = if i == 0 { ": " } else { "; " };
+            write!(f, "{}{:#}", separator, error)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for MultipleErrors {}
+
+#[cfg(test)]
+mod tests {
+    use super::{CleanupErrors, MultipleErrors};
+    use crate::Closure;
+    use alloc::string::ToString;
+
+    #[test]
+    fn collect_cleanup_failures() {
+        let mut errors = CleanupErrors::new();
+        errors.try_consume(Closure(|| Ok::<_, anyhow::Error>(())));
+        assert!(errors.is_empty());
+        errors.try_consume(Closure(|| Err(anyhow::anyhow!("socket reset"))));
+        errors.try_consume_with(7, Closure(|fd| Err(anyhow::anyhow!("bad fd {}", fd))));
+
+        let error = errors.into_result().unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "2 cleanups failed: socket reset; bad fd 7"
+        );
+        assert_eq!(
+            error.downcast::<MultipleErrors>().unwrap().errors().len(),
+            2
+        );
+    }
+}
@@ -0,0 +1,275 @@
+use crate::composite::consume_all;
+use crate::Consume;
+use bumpalo::Bump;
+use core::cell::Cell;
+use core::fmt;
+use core::marker::PhantomData;
+use core::mem::ManuallyDrop;
+use core::ptr::NonNull;
+
+type Link<'a> = Option<NonNull<dyn Finalize<'a> + 'a>>;
+
+trait Finalize<'a> {
+    fn next(&self) -> Link<'a>;
+
+    /// Safety: must be called at most once, after which the node must not be used.
+    unsafe fn run(&mut self);
+}
+
+struct Node<'a, T> {
+    next: Link<'a>,
+    value: ManuallyDrop<T>,
+}
+
+impl<'a, T: Consume> Finalize<'a> for Node<'a, T> {
+    #[inline]
+    fn next(&self) -> Link<'a> {
+        self.next
+    }
+
+    unsafe fn run(&mut self) {
+        // SAFETY: the caller guarantees this is the only time the value is taken.
+        unsafe { ManuallyDrop::take(&mut self.value) }.consume()
+    }
+}
+
+/// A node popped off a [`Chain`], which is finalized when consumed.
+struct Pending<'a>(NonNull<dyn Finalize<'a> + 'a>);
+
+impl Consume for Pending<'_> {
+    #[inline]
+    fn consume(mut self) {
+        // SAFETY: the node was popped off its chain, so it is run exactly once, and the arena
+        // holding it outlives the chain.
+        unsafe { self.0.as_mut().run() }
+    }
+}
+
+/// An intrusive list of values living in an arena, which are consumed in reverse order of
+/// registration when the list is dropped.
+struct Chain<'a> {
+    head: Cell<Link<'a>>,
+    len: Cell<usize>,
+    /// Values are only consumed when the chain is dropped, so `'a` must not shrink.
+    _invariant: PhantomData<fn(&'a ()) -> &'a ()>,
+}
+
+impl<'a> Chain<'a> {
+    const fn new() -> Self {
+        Self {
+            head: Cell::new(None),
+            len: Cell::new(0),
+            _invariant: PhantomData,
+        }
+    }
+
+    // Like `Bump::alloc`, every call returns a fresh allocation.
+    #[allow(clippy::mut_from_ref)]
+    fn push<'s, T: Consume + 'a>(&'s self, bump: &'s Bump, value: T) -> &'s mut T {
+        let node = bump.alloc(Node {
+            next: self.head.get(),
+            value: ManuallyDrop::new(value),
+        });
+        let node = NonNull::from(node);
+        let link: NonNull<dyn Finalize<'a> + 'a> = node;
+        self.head.set(Some(link));
+        self.len.set(self.len.get() + 1);
+        // SAFETY: the node stays in the arena until it is finalized, which only happens once
+        // the chain, and therefore the returned borrow of it, is gone.
+        unsafe { &mut (*node.as_ptr()).value }
+    }
+
+    fn pop(&self) -> Option<Pending<'a>> {
+        let node = self.head.get()?;
+        // SAFETY: nodes on the chain are live.
+        self.head.set(unsafe { node.as_ref() }.next());
+        self.len.set(self.len.get() - 1);
+        Some(Pending(node))
+    }
+}
+
+impl Drop for Chain<'_> {
+    fn drop(&mut self) {
+        consume_all(core::iter::from_fn(|| self.pop()))
+    }
+}
+
+/// A list of finalizers for values allocated in a [`Bump`] arena, which consumes them when the
+/// list is dropped.
+///
+/// Bump arenas never run destructors, so a resource allocated in one is normally leaked. Values
+/// allocated with [`FinalizerList::alloc`] are consumed in reverse order of allocation when the
+/// list is dropped, which must happen before the arena is reset or dropped. If consuming one
+/// panics, the rest are still consumed while unwinding. The values are linked to each other
+/// in the arena, so registering a value never allocates on the heap. See [`FinalizingBump`]
+/// for an arena which owns its list.
+pub struct FinalizerList<'bump> {
+    bump: &'bump Bump,
+    chain: Chain<'bump>,
+}
+
+impl<'bump> FinalizerList<'bump> {
+    /// Builds an empty list allocating in `bump`.
+    #[inline]
+    pub const fn new(bump: &'bump Bump) -> Self {
+        Self {
+            bump,
+            chain: Chain::new(),
+        }
+    }
+
+    /// Allocates `value` in the arena, to be consumed when the list is dropped.
+    #[inline]
+    pub fn alloc<T: Consume + 'bump>(&self, value: T) -> &mut T {
+        self.chain.push(self.bump, value)
+    }
+
+    /// The arena the values are allocated in.
+    #[inline]
+    pub fn bump(&self) -> &'bump Bump {
+        self.bump
+    }
+
+    /// The number of values waiting to be consumed.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.chain.len.get()
+    }
+
+    /// Whether there are no values waiting to be consumed.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Consume for FinalizerList<'_> {
+    #[inline]
+    fn consume(self) {
+        drop(self)
+    }
+}
+
+impl fmt::Debug for FinalizerList<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FinalizerList")
+            .field("len", &self.len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// A [`Bump`] arena with a built-in finalizer list, like a [`FinalizerList`], whose values are
+/// consumed when the arena is reset or dropped, before their memory is freed.
+///
+/// Values allocated with [`FinalizingBump::alloc`] may borrow anything which outlives `'a`.
+/// Plain allocations made through [`FinalizingBump::bump`] are not finalized.
+pub struct FinalizingBump<'a> {
+    // Declared first, so that the values are consumed before the arena is freed.
+    chain: Chain<'a>,
+    bump: Bump,
+}
+
+impl<'a> FinalizingBump<'a> {
+    /// Builds an empty arena.
+    #[inline]
+    pub fn new() -> Self {
+        Self::from_bump(Bump::new())
+    }
+
+    /// Adds finalization to `bump`.
+    #[inline]
+    pub const fn from_bump(bump: Bump) -> Self {
+        Self {
+            chain: Chain::new(),
+            bump,
+        }
+    }
+
+    /// Allocates `value` in the arena, to be consumed when the arena is reset or dropped.
+    #[inline]
+    pub fn alloc<T: Consume + 'a>(&self, value: T) -> &mut T {
+        self.chain.push(&self.bump, value)
+    }
+
+    /// The underlying arena, for allocations which need no finalization.
+    #[inline]
+    pub fn bump(&self) -> &Bump {
+        &self.bump
+    }
+
+    /// The number of values waiting to be consumed.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.chain.len.get()
+    }
+
+    /// Whether there are no values waiting to be consumed.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Consumes every value allocated with [`FinalizingBump::alloc`], then resets the arena,
+    /// keeping its memory for reuse.
+    pub fn reset(&mut self) {
+        drop(core::mem::replace(&mut self.chain, Chain::new()));
+        self.bump.reset()
+    }
+}
+
+impl Default for FinalizingBump<'_> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Consume for FinalizingBump<'_> {
+    #[inline]
+    fn consume(self) {
+        drop(self)
+    }
+}
+
+impl fmt::Debug for FinalizingBump<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FinalizingBump")
+            .field("len", &self.len())
+            .field("allocated_bytes", &self.bump.allocated_bytes())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FinalizerList, FinalizingBump};
+    use crate::Closure;
+    use alloc::vec::Vec;
+    use bumpalo::Bump;
+    use core::cell::RefCell;
+
+    #[test]
+    fn finalizes_arena_values() {
+        let log = RefCell::new(Vec::new());
+        let log = &log;
+        let handle = |id| Closure(move || log.borrow_mut().push(id));
+
+        let bump = Bump::new();
+        let list = FinalizerList::new(&bump);
+        let first = list.alloc(handle(1));
+        list.alloc(handle(2));
+        *first = handle(3);
+        assert_eq!(list.len(), 2);
+        drop(list);
+        assert_eq!(*log.borrow(), [2, 3]);
+
+        let mut arena = FinalizingBump::new();
+        arena.alloc(handle(4));
+        arena.reset();
+        assert_eq!(log.borrow()[2..], [4]);
+        arena.alloc(handle(5));
+        arena.alloc(handle(6));
+        drop(arena);
+        assert_eq!(log.borrow()[3..], [6, 5]);
+    }
+}
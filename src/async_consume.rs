@@ -0,0 +1,189 @@
+use crate::{Closure, Consume, ConsumeOnDrop};
+use core::future::Future;
+use core::ops::{Deref, DerefMut};
+
+/// A trait for types which are consumed asynchronously, such as a connection closed with
+/// `async fn close(self)`.
+///
+/// The future must be [`Send`], so that it can be spawned onto an executor by an
+/// [`AsyncConsumeOnDrop`] guard when the value is dropped.
+pub trait AsyncConsume {
+    /// Consumes `self`, once the returned future completes.
+    fn consume(self) -> impl Future<Output = ()> + Send;
+}
+
+/// Consumes the closure by calling it and awaiting the future it returns.
+impl<F: FnOnce() -> Fut, Fut: Future<Output = ()> + Send> AsyncConsume for Closure<F> {
+    #[inline]
+    fn consume(self) -> impl Future<Output = ()> + Send {
+        (self.0)()
+    }
+}
+
+/// A handle to an executor which runs futures in the background, used by an
+/// [`AsyncConsumeOnDrop`] guard to consume its value when dropped.
+///
+/// Implement this to plug in any executor, such as `smol`, `async-std` or `embassy`. With the
+/// `tokio` feature, it is implemented for `tokio::runtime::Handle`.
+pub trait Spawner {
+    /// Runs `future` to completion in the background.
+    fn spawn(&self, future: impl Future<Output = ()> + Send + 'static);
+}
+
+impl<S: Spawner + ?Sized> Spawner for &S {
+    #[inline]
+    fn spawn(&self, future: impl Future<Output = ()> + Send + 'static) {
+        (**self).spawn(future)
+    }
+}
+
+/// Spawns the future as a detached task on the runtime.
+#[cfg(feature = "tokio")]
+impl Spawner for tokio::runtime::Handle {
+    #[inline]
+    fn spawn(&self, future: impl Future<Output = ()> + Send + 'static) {
+        drop(tokio::runtime::Handle::spawn(self, future))
+    }
+}
+
+#[derive(Debug)]
+struct Spawning<T, S> {
+    value: T,
+    spawner: S,
+}
+
+impl<T: AsyncConsume + 'static, S: Spawner> Consume for Spawning<T, S> {
+    #[inline]
+    fn consume(self) {
+        // Dropping cannot wait, so the consumption runs in the background.
+        self.spawner.spawn(self.value.consume())
+    }
+}
+
+/// A guard which consumes an [`AsyncConsume`] value when dropped, by spawning the future with
+/// the [`Spawner`] `S`.
+///
+/// Since dropping cannot await, the value is only consumed once the executor gets to the
+/// spawned future, and never if the executor has shut down. Await
+/// [`AsyncConsumeOnDrop::consume`] instead to consume it in place.
+#[derive(Debug)]
+pub struct AsyncConsumeOnDrop<T: AsyncConsume + 'static, S: Spawner> {
+    inner: ConsumeOnDrop<Spawning<T, S>>,
+}
+
+#[cfg(feature = "tokio")]
+impl<T: AsyncConsume + 'static> AsyncConsumeOnDrop<T, tokio::runtime::Handle> {
+    /// Guards `value`, to be consumed on the current `tokio` runtime when dropped.
+    ///
+    /// Panics if called outside of a `tokio` runtime.
+    #[inline]
+    pub fn new(value: T) -> Self {
+        Self::with_spawner(value, tokio::runtime::Handle::current())
+    }
+}
+
+impl<T: AsyncConsume + 'static, S: Spawner> AsyncConsumeOnDrop<T, S> {
+    /// Guards `value`, to be consumed by a future spawned with `spawner` when dropped.
+    #[inline]
+    pub const fn with_spawner(value: T, spawner: S) -> Self {
+        Self {
+            inner: ConsumeOnDrop::new(Spawning { value, spawner }),
+        }
+    }
+
+    /// The spawner the value is consumed with when dropped.
+    #[inline]
+    pub fn spawner(x: &Self) -> &S {
+        &x.inner.spawner
+    }
+
+    /// Consumes the value now, completing once it is consumed.
+    #[inline]
+    pub async fn consume(x: Self) {
+        Self::into_inner(x).consume().await
+    }
+
+    /// Unwraps the value without consuming it.
+    #[inline]
+    pub fn into_inner(x: Self) -> T {
+        ConsumeOnDrop::into_inner(x.inner).value
+    }
+}
+
+impl<T: AsyncConsume + 'static, S: Spawner> Deref for AsyncConsumeOnDrop<T, S> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        &self.inner.value
+    }
+}
+
+impl<T: AsyncConsume + 'static, S: Spawner> DerefMut for AsyncConsumeOnDrop<T, S> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.inner.value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AsyncConsumeOnDrop, Spawner};
+    use crate::Closure;
+    use core::future::Future;
+    use core::pin::pin;
+    use core::sync::atomic::{AtomicU32, Ordering};
+    use core::task::{Context, Waker};
+
+    /// Polls spawned futures once, in place.
+    struct PollOnce;
+
+    impl Spawner for PollOnce {
+        fn spawn(&self, future: impl Future<Output = ()> + Send + 'static) {
+            let _ = pin!(future).poll(&mut Context::from_waker(Waker::noop()));
+        }
+    }
+
+    #[test]
+    fn consumes_with_custom_spawner() {
+        static CLOSED: AtomicU32 = AtomicU32::new(0);
+        let close = |n| {
+            Closure(move || async move {
+                CLOSED.fetch_add(n, Ordering::Relaxed);
+            })
+        };
+
+        drop(AsyncConsumeOnDrop::with_spawner(close(1), PollOnce));
+        assert_eq!(CLOSED.load(Ordering::Relaxed), 1);
+        let guard = AsyncConsumeOnDrop::with_spawner(close(10), &PollOnce);
+        let awaited =
+            pin!(AsyncConsumeOnDrop::consume(guard)).poll(&mut Context::from_waker(Waker::noop()));
+        assert!(awaited.is_ready());
+        assert_eq!(CLOSED.load(Ordering::Relaxed), 11);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn consumes_on_the_runtime() {
+        use tokio::sync::oneshot;
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let close = |sender: oneshot::Sender<&'static str>, how| {
+            Closure(move || async move { sender.send(how).unwrap() })
+        };
+
+        let (sender, receiver) = oneshot::channel();
+        let guard = runtime.block_on(async { AsyncConsumeOnDrop::new(close(sender, "dropped")) });
+        drop(guard);
+        assert_eq!(runtime.block_on(receiver), Ok("dropped"));
+
+        runtime.block_on(async {
+            let (sender, mut receiver) = oneshot::channel();
+            let guard = AsyncConsumeOnDrop::new(close(sender, "awaited"));
+            AsyncConsumeOnDrop::consume(guard).await;
+            assert_eq!(receiver.try_recv(), Ok("awaited"));
+        });
+    }
+}
@@ -0,0 +1,326 @@
+use alloc::boxed::Box;
+use alloc::vec::{self, Vec};
+use core::future::{poll_fn, Future};
+use core::mem;
+use core::pin::Pin;
+use core::task::Poll;
+
+type Finalizer<'a> = Pin<Box<dyn Future<Output = ()> + 'a>>;
+
+/// Finalizers taken out of `running` to be polled. Those not polled yet are put back when this
+/// is dropped, even while unwinding, while a finalizer which panicked is dropped instead, since
+/// it must not be polled again.
+struct Polling<'r, 'a> {
+    rest: vec::IntoIter<Finalizer<'a>>,
+    running: &'r mut Vec<Finalizer<'a>>,
+}
+
+impl Drop for Polling<'_, '_> {
+    fn drop(&mut self) {
+        self.running.extend(self.rest.by_ref())
+    }
+}
+
+/// What an [`AsyncDropBag`] does with its remaining finalizers if it is dropped without being
+/// closed, including when [`AsyncDropBag::close`] is cancelled partway.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum UnclosedPolicy {
+    /// Drops the remaining finalizers without polling them. Only their destructors run.
+    #[default]
+    Discard,
+    /// Runs the remaining finalizers to completion on the current thread, one at a time, by
+    /// blocking until each is woken. This must not be used on a thread driving an executor that
+    /// the finalizers depend on, or it will deadlock.
+    #[cfg(feature = "std")]
+    BlockOn,
+}
+
+/// A bag of asynchronous finalizers, which are awaited when the bag is closed.
+///
+/// Finalizers are awaited in reverse registration order, either one at a time by
+/// [`AsyncDropBag::close`], or with bounded concurrency by
+/// [`AsyncDropBag::close_concurrently`]. Since [`Drop`] cannot await, a bag dropped without
+/// being closed falls back to its [`UnclosedPolicy`].
+pub struct AsyncDropBag<'a> {
+    finalizers: Vec<Finalizer<'a>>,
+    /// Finalizers started by [`AsyncDropBag::close_concurrently`] which have not completed.
+    running: Vec<Finalizer<'a>>,
+    policy: UnclosedPolicy,
+}
+
+impl<'a> AsyncDropBag<'a> {
+    /// Builds an empty bag which discards its finalizers if it is dropped without being closed.
+    #[inline]
+    pub const fn new() -> Self {
+        Self::with_policy(UnclosedPolicy::Discard)
+    }
+
+    /// Builds an empty bag which applies `policy` if it is dropped without being closed.
+    #[inline]
+    pub const fn with_policy(policy: UnclosedPolicy) -> Self {
+        Self {
+            finalizers: Vec::new(),
+            running: Vec::new(),
+            policy,
+        }
+    }
+
+    /// Registers `finalizer` to be awaited when the bag is closed.
+    #[inline]
+    pub fn push(&mut self, finalizer: impl Future<Output = ()> + 'a) {
+        self.finalizers.push(Box::pin(finalizer))
+    }
+
+    /// The number of finalizers waiting to run.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.finalizers.len()
+    }
+
+    /// Whether there are no finalizers waiting to run.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.finalizers.is_empty()
+    }
+
+    /// Awaits every finalizer, one at a time, in reverse registration order.
+    pub async fn close(mut self) {
+        // A pending finalizer goes back into the bag, so that the policy applies to it if
+        // closing is cancelled. One which panics is dropped instead.
+        poll_fn(|cx| {
+            while let Some(mut finalizer) = self.finalizers.pop() {
+                if finalizer.as_mut().poll(cx).is_pending() {
+                    self.finalizers.push(finalizer);
+                    return Poll::Pending;
+                }
+            }
+            Poll::Ready(())
+        })
+        .await
+    }
+
+    /// Awaits every finalizer, with at most `limit` of them in progress at once. Finalizers are
+    /// started in reverse registration order. A `limit` of 0 is treated as 1.
+    pub async fn close_concurrently(mut self, limit: usize) {
+        let limit = limit.max(1);
+        self.running.reserve(limit.min(self.len()));
+        poll_fn(|cx| {
+            let Self {
+                finalizers,
+                running,
+                ..
+            } = &mut self;
+            loop {
+                while running.len() < limit {
+                    match finalizers.pop() {
+                        Some(finalizer) => running.push(finalizer),
+                        None => break,
+                    }
+                }
+                let before = running.len();
+                let mut polling = Polling {
+                    rest: mem::take(running).into_iter(),
+                    running,
+                };
+                for mut finalizer in polling.rest.by_ref() {
+                    if finalizer.as_mut().poll(cx).is_pending() {
+                        polling.running.push(finalizer)
+                    }
+                }
+                drop(polling);
+                if running.is_empty() && finalizers.is_empty() {
+                    return Poll::Ready(());
+                }
+                // Only start more finalizers if some finished during this pass.
+                if running.len() == before {
+                    return Poll::Pending;
+                }
+            }
+        })
+        .await
+    }
+}
+
+impl Default for AsyncDropBag<'_> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for AsyncDropBag<'_> {
+    fn drop(&mut self) {
+        match self.policy {
+            UnclosedPolicy::Discard => {}
+            #[cfg(feature = "std")]
+            UnclosedPolicy::BlockOn => {
+                // Finalizers which were already started come first.
+                for finalizer in self.running.drain(..) {
+                    block_on(finalizer)
+                }
+                while let Some(finalizer) = self.finalizers.pop() {
+                    block_on(finalizer)
+                }
+            }
+        }
+    }
+}
+
+/// Runs `future` to completion on the current thread, parking it while the future is pending.
+#[cfg(feature = "std")]
+pub(crate) fn block_on<F: Future>(future: F) -> F::Output {
+    use alloc::sync::Arc;
+    use core::task::{Context, Waker};
+    use std::task::Wake;
+    use std::thread::{self, Thread};
+
+    struct Unpark(Thread);
+
+    impl Wake for Unpark {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark()
+        }
+
+        fn wake_by_ref(self: &Arc<Self>) {
+            self.0.unpark()
+        }
+    }
+
+    let mut future = core::pin::pin!(future);
+    let waker = Waker::from(Arc::new(Unpark(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::{block_on, AsyncDropBag, UnclosedPolicy};
+    use core::cell::RefCell;
+    use core::future::poll_fn;
+    use core::task::Poll;
+    use std::vec::Vec;
+
+    /// Returns `Pending` once before completing, waking itself immediately.
+    async fn yield_now() {
+        let mut yielded = false;
+        poll_fn(|cx| {
+            if yielded {
+                Poll::Ready(())
+            } else {
+                yielded = true;
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        })
+        .await
+    }
+
+    #[test]
+    fn closes_in_reverse_order() {
+        let log = RefCell::new(Vec::new());
+        let log = &log;
+        let mut bag = AsyncDropBag::new();
+        for name in ["a", "b", "c"] {
+            bag.push(async move {
+                log.borrow_mut().push(name);
+                yield_now().await;
+                log.borrow_mut().push(name);
+            });
+        }
+        block_on(bag.close());
+        assert_eq!(*log.borrow(), ["c", "c", "b", "b", "a", "a"]);
+
+        log.borrow_mut().clear();
+        let mut bag = AsyncDropBag::new();
+        for name in ["a", "b", "c"] {
+            bag.push(async move {
+                log.borrow_mut().push(name);
+                yield_now().await;
+                log.borrow_mut().push(name);
+            });
+        }
+        block_on(bag.close_concurrently(2));
+        assert_eq!(*log.borrow(), ["c", "b", "c", "b", "a", "a"]);
+    }
+
+    #[test]
+    fn unclosed_policy() {
+        let log = RefCell::new(Vec::new());
+        let log = &log;
+        let mut discarded = AsyncDropBag::new();
+        discarded.push(async move { log.borrow_mut().push("discarded") });
+        drop(discarded);
+        let mut blocked = AsyncDropBag::with_policy(UnclosedPolicy::BlockOn);
+        blocked.push(async move {
+            yield_now().await;
+            log.borrow_mut().push("blocked")
+        });
+        drop(blocked);
+        assert_eq!(*log.borrow(), ["blocked"]);
+    }
+
+    #[test]
+    fn cancelled_close_applies_policy() {
+        use core::future::Future;
+        use core::pin::pin;
+        use core::task::{Context, Waker};
+
+        let log = RefCell::new(Vec::new());
+        let log = &log;
+        let mut cx = Context::from_waker(Waker::noop());
+        for concurrently in [false, true] {
+            let mut bag = AsyncDropBag::with_policy(UnclosedPolicy::BlockOn);
+            for name in ["a", "b"] {
+                bag.push(async move {
+                    yield_now().await;
+                    log.borrow_mut().push(name)
+                });
+            }
+            {
+                let close = async move {
+                    if concurrently {
+                        bag.close_concurrently(2).await
+                    } else {
+                        bag.close().await
+                    }
+                };
+                assert!(pin!(close).poll(&mut cx).is_pending());
+            }
+            assert_eq!(log.borrow_mut().drain(..).collect::<Vec<_>>(), ["b", "a"]);
+        }
+    }
+
+    #[test]
+    fn panicking_finalizer_under_block_on() {
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+
+        let log = RefCell::new(Vec::new());
+        let log = &log;
+        for concurrently in [false, true] {
+            let mut bag = AsyncDropBag::with_policy(UnclosedPolicy::BlockOn);
+            bag.push(async move {
+                yield_now().await;
+                log.borrow_mut().push("a")
+            });
+            bag.push(async move {
+                yield_now().await;
+                panic!("finalizer failed")
+            });
+            let close = AssertUnwindSafe(|| {
+                if concurrently {
+                    block_on(bag.close_concurrently(2))
+                } else {
+                    block_on(bag.close())
+                }
+            });
+            assert!(catch_unwind(close).is_err());
+            assert_eq!(log.borrow_mut().drain(..).collect::<Vec<_>>(), ["a"]);
+        }
+    }
+}
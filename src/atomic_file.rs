@@ -0,0 +1,162 @@
+use crate::{Consume, ConsumeOnDrop};
+use core::ops::{Deref, DerefMut};
+use core::sync::atomic::{AtomicUsize, Ordering};
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug)]
+struct Pending {
+    file: File,
+    temp: PathBuf,
+    target: PathBuf,
+}
+
+impl Consume for Pending {
+    #[inline]
+    fn consume(self) {
+        drop(self.file);
+        // There is nobody to report a failure to, and a stray temporary file is harmless.
+        let _ = fs::remove_file(self.temp);
+    }
+}
+
+/// Builds a fresh temporary path next to `target`, so that renaming it over `target` never
+/// crosses a file system.
+fn temp_path_for(target: &Path) -> PathBuf {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let mut name = OsString::from(".");
+    name.push(target.file_name().unwrap_or_default());
+    name.push(std::format!(
+        ".{}.{}.tmp",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    target.with_file_name(name)
+}
+
+/// Syncs the directory containing `path`, which makes a rename into it durable.
+#[cfg(unix)]
+fn sync_parent(path: &Path) -> io::Result<()> {
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    File::open(parent)?.sync_all()
+}
+
+/// Other platforms have no portable way to sync a directory.
+#[cfg(not(unix))]
+fn sync_parent(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+/// A file which replaces `target` atomically once it is complete.
+///
+/// Writes go to a temporary file in the same directory as the target. [`AtomicFileGuard::commit`]
+/// syncs it to disk and renames it over the target, so readers see either the old contents or
+/// the new ones, never a partial write. On Unix, the directory is synced after the rename, so
+/// that the new contents also survive a crash. Dropping the guard without committing, e.g.
+/// because an error was propagated with `?`, deletes the temporary file and leaves the target
+/// untouched.
+#[derive(Debug)]
+#[must_use = "the file is discarded unless it is committed"]
+pub struct AtomicFileGuard {
+    inner: ConsumeOnDrop<Pending>,
+}
+
+impl AtomicFileGuard {
+    /// Creates a temporary file which will replace `target` when committed.
+    pub fn new(target: impl AsRef<Path>) -> io::Result<Self> {
+        let target = target.as_ref().to_path_buf();
+        if target.file_name().is_none() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the target of an atomic write must name a file",
+            ));
+        }
+        let temp = temp_path_for(&target);
+        let file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&temp)?;
+        Ok(Self {
+            inner: ConsumeOnDrop::new(Pending { file, temp, target }),
+        })
+    }
+
+    /// The path of the file being replaced.
+    #[inline]
+    pub fn target(x: &Self) -> &Path {
+        &x.inner.target
+    }
+
+    /// The path of the temporary file being written.
+    #[inline]
+    pub fn temp_path(x: &Self) -> &Path {
+        &x.inner.temp
+    }
+
+    /// Syncs the temporary file to disk and renames it over the target. If this fails, the
+    /// temporary file is deleted and the target is left untouched.
+    ///
+    /// On Unix, the directory containing the target is then synced too. If only that fails, the
+    /// target has already been replaced, but the replacement may be lost in a crash.
+    pub fn commit(x: Self) -> io::Result<()> {
+        let Pending { file, temp, target } = ConsumeOnDrop::into_inner(x.inner);
+        // The file is closed before renaming it, which Windows requires.
+        let result = file.sync_all();
+        drop(file);
+        if let Err(error) = result.and_then(|()| fs::rename(&temp, &target)) {
+            let _ = fs::remove_file(temp);
+            return Err(error);
+        }
+        sync_parent(&target)
+    }
+}
+
+impl Deref for AtomicFileGuard {
+    type Target = File;
+
+    #[inline]
+    fn deref(&self) -> &File {
+        &self.inner.file
+    }
+}
+
+impl DerefMut for AtomicFileGuard {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut File {
+        &mut self.inner.file
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AtomicFileGuard;
+    use std::fs;
+    use std::io::Write;
+
+    #[test]
+    fn commits_or_discards() {
+        let target = std::env::temp_dir().join(std::format!(
+            "consume_on_drop-atomic-{}.txt",
+            std::process::id()
+        ));
+        fs::write(&target, "old").unwrap();
+
+        let mut file = AtomicFileGuard::new(&target).unwrap();
+        file.write_all(b"half").unwrap();
+        let temp = AtomicFileGuard::temp_path(&file).to_path_buf();
+        drop(file);
+        assert!(!temp.exists());
+        assert_eq!(fs::read_to_string(&target).unwrap(), "old");
+
+        let mut file = AtomicFileGuard::new(&target).unwrap();
+        file.write_all(b"new").unwrap();
+        AtomicFileGuard::commit(file).unwrap();
+        assert_eq!(fs::read_to_string(&target).unwrap(), "new");
+        fs::remove_file(&target).unwrap();
+    }
+}
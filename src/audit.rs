@@ -0,0 +1,180 @@
+use crate::Consumer;
+use alloc::borrow::Cow;
+use alloc::sync::Arc;
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// One entry of an audit log, recorded by an [`AuditConsumer`] for each value it consumes.
+///
+/// It serializes as a struct with the fields `type`, `label`, `timestamp`, in milliseconds
+/// since the Unix epoch, and `payload`.
+#[derive(Clone, Copy, Debug)]
+pub struct AuditEntry<'a, P: ?Sized> {
+    /// The name of the consumed type, as given by [`core::any::type_name`].
+    pub type_name: &'static str,
+    /// The label of the consumer, if any.
+    pub label: Option<&'a str>,
+    /// When the value was consumed.
+    pub timestamp: SystemTime,
+    /// The consumed value, if the consumer records it.
+    pub payload: Option<&'a P>,
+}
+
+impl<P: Serialize + ?Sized> Serialize for AuditEntry<'_, P> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let millis = self
+            .timestamp
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let mut entry = serializer.serialize_struct("AuditEntry", 4)?;
+        entry.serialize_field("type", self.type_name)?;
+        entry.serialize_field("label", &self.label)?;
+        entry.serialize_field("timestamp", &(millis as u64))?;
+        entry.serialize_field("payload", &self.payload)?;
+        entry.end()
+    }
+}
+
+/// A destination for the entries of an audit log, such as a file of JSON lines or a
+/// tamper-evident store.
+///
+/// Sinks are shared by the consumers writing to them, so they take `&self`, and must handle
+/// their own failures: an entry cannot be reported back to the code dropping a guard.
+pub trait AuditSink {
+    /// Records `entry`, typically by serializing it.
+    fn record<P: Serialize + ?Sized>(&self, entry: &AuditEntry<'_, P>);
+}
+
+impl<S: AuditSink + ?Sized> AuditSink for &S {
+    #[inline]
+    fn record<P: Serialize + ?Sized>(&self, entry: &AuditEntry<'_, P>) {
+        (**self).record(entry)
+    }
+}
+
+impl<S: AuditSink + ?Sized> AuditSink for Arc<S> {
+    #[inline]
+    fn record<P: Serialize + ?Sized>(&self, entry: &AuditEntry<'_, P>) {
+        (**self).record(entry)
+    }
+}
+
+/// A [`Consumer<T>`] which records an [`AuditEntry`] in the sink `S` for each value, then passes
+/// the value on to the consumer `Q`.
+///
+/// This lets compliance-sensitive systems prove that resources were released, by changing only
+/// the consumer of their guards. The entry is recorded just before the value is handed to `Q`.
+/// If `PAYLOAD` is `true`, which requires `T: Serialize`, the entry includes the value itself.
+#[derive(Clone, Debug)]
+pub struct AuditConsumer<Q, S, const PAYLOAD: bool = false> {
+    inner: Q,
+    sink: S,
+    label: Option<Cow<'static, str>>,
+}
+
+impl<Q, S> AuditConsumer<Q, S> {
+    /// Builds a consumer which records entries without payloads in `sink`, then consumes values
+    /// with `inner`.
+    #[inline]
+    pub const fn new(inner: Q, sink: S) -> Self {
+        Self {
+            inner,
+            sink,
+            label: None,
+        }
+    }
+
+    /// Records the consumed values in the entries.
+    #[inline]
+    pub fn with_payload(self) -> AuditConsumer<Q, S, true> {
+        AuditConsumer {
+            inner: self.inner,
+            sink: self.sink,
+            label: self.label,
+        }
+    }
+}
+
+impl<Q, S, const PAYLOAD: bool> AuditConsumer<Q, S, PAYLOAD> {
+    /// Sets the label recorded in each entry.
+    #[inline]
+    pub fn with_label(self, label: impl Into<Cow<'static, str>>) -> Self {
+        Self {
+            label: Some(label.into()),
+            ..self
+        }
+    }
+}
+
+impl<Q, S: AuditSink, const PAYLOAD: bool> AuditConsumer<Q, S, PAYLOAD> {
+    fn record<T, P: Serialize + ?Sized>(&self, payload: Option<&P>) {
+        self.sink.record(&AuditEntry {
+            type_name: core::any::type_name::<T>(),
+            label: self.label.as_deref(),
+            timestamp: SystemTime::now(),
+            payload,
+        })
+    }
+}
+
+impl<T, Q: Consumer<T>, S: AuditSink> Consumer<T> for AuditConsumer<Q, S, false> {
+    fn consume(self, other: T) {
+        self.record::<T, ()>(None);
+        self.inner.consume(other)
+    }
+}
+
+impl<T: Serialize, Q: Consumer<T>, S: AuditSink> Consumer<T> for AuditConsumer<Q, S, true> {
+    fn consume(self, other: T) {
+        self.record::<T, T>(Some(&other));
+        self.inner.consume(other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AuditConsumer, AuditEntry, AuditSink};
+    use crate::{Closure, WithConsumer};
+    use serde::Serialize;
+    use std::string::String;
+    use std::sync::Mutex;
+    use std::vec::Vec;
+
+    #[derive(Default)]
+    struct JsonLines(Mutex<Vec<String>>);
+
+    impl AuditSink for JsonLines {
+        fn record<P: Serialize + ?Sized>(&self, entry: &AuditEntry<'_, P>) {
+            let line = serde_json::to_string(entry).unwrap();
+            self.0.lock().unwrap().push(line);
+        }
+    }
+
+    #[test]
+    fn records_each_consumption() {
+        let log = JsonLines::default();
+        let mut released = Vec::new();
+        let audited = AuditConsumer::new(Closure(|key: u32| released.push(key)), &log)
+            .with_label("kms-key")
+            .with_payload();
+        drop(WithConsumer::new(7, audited));
+        drop(WithConsumer::new(
+            "scratch",
+            AuditConsumer::new(Closure(drop), &log),
+        ));
+        assert_eq!(released, [7]);
+
+        let lines = log.0.into_inner().unwrap();
+        let entries: Vec<serde_json::Value> = lines
+            .iter()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(entries[0]["type"], "u32");
+        assert_eq!(entries[0]["label"], "kms-key");
+        assert_eq!(entries[0]["payload"], 7);
+        assert!(entries[0]["timestamp"].as_u64().unwrap() > 0);
+        assert_eq!(entries[1]["type"], "&str");
+        assert!(entries[1]["label"].is_null() && entries[1]["payload"].is_null());
+    }
+}
@@ -0,0 +1,260 @@
+use crate::sync::{Arc, Condvar, Mutex, MutexGuard};
+use crate::{Consume, Consumer, SelfConsumer, WithConsumer};
+use alloc::vec::Vec;
+use core::fmt;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
+
+#[derive(Default)]
+struct Count {
+    remaining: usize,
+    wakers: Vec<Waker>,
+}
+
+#[derive(Default)]
+struct State {
+    count: Mutex<Count>,
+    drained: Condvar,
+}
+
+impl State {
+    fn lock(&self) -> MutexGuard<'_, Count> {
+        self.count
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// A countdown of the guards bound to it, which releases its waiters once every one of them has
+/// been consumed, like a wait group.
+///
+/// Each guard built with [`ConsumeBarrier::guard`] or [`ConsumeBarrier::bind`] counts up, and
+/// counts down once its value has been consumed, or its [`Countdown`] consumer is dropped
+/// without running. Threads can block on [`ConsumeBarrier::wait`], and tasks can await
+/// [`ConsumeBarrier::drained`], to proceed with shutdown once all sessions are gone. Guards can
+/// still be bound after the count reached zero, which blocks later waiters again.
+#[derive(Clone, Default)]
+pub struct ConsumeBarrier {
+    state: Arc<State>,
+}
+
+impl ConsumeBarrier {
+    /// Builds a barrier with no guards bound to it.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of bound guards which have not been consumed yet.
+    #[inline]
+    pub fn remaining(&self) -> usize {
+        self.state.lock().remaining
+    }
+
+    /// Wraps `consumer` so that it counts down once it has consumed a value.
+    pub fn countdown<Q>(&self, consumer: Q) -> Countdown<Q> {
+        self.state.lock().remaining += 1;
+        Countdown {
+            inner: consumer,
+            _ticket: Ticket {
+                state: self.state.clone(),
+            },
+        }
+    }
+
+    /// Guards `value`, which is consumed by `consumer`, and binds the guard to the barrier.
+    #[inline]
+    pub fn bind<T, Q: Consumer<T>>(&self, value: T, consumer: Q) -> WithConsumer<T, Countdown<Q>> {
+        WithConsumer::new(value, self.countdown(consumer))
+    }
+
+    /// Guards `value`, which is consumed with [`Consume::consume`], and binds the guard to the
+    /// barrier.
+    #[inline]
+    pub fn guard<T: Consume>(&self, value: T) -> WithConsumer<T, Countdown<SelfConsumer>> {
+        self.bind(value, SelfConsumer)
+    }
+
+    /// Blocks until every bound guard has been consumed.
+    pub fn wait(&self) {
+        let mut count = self.state.lock();
+        while count.remaining > 0 {
+            count = self
+                .state
+                .drained
+                .wait(count)
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+        }
+    }
+
+    /// Blocks until every bound guard has been consumed, or `timeout` elapses. Returns whether
+    /// every guard was consumed.
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        let mut count = self.state.lock();
+        while count.remaining > 0 {
+            let left = deadline.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                return false;
+            }
+            count = self
+                .state
+                .drained
+                .wait_timeout(count, left)
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .0;
+        }
+        true
+    }
+
+    /// A future which completes once every bound guard has been consumed.
+    #[inline]
+    pub fn drained(&self) -> Drained {
+        Drained {
+            state: self.state.clone(),
+        }
+    }
+}
+
+impl fmt::Debug for ConsumeBarrier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConsumeBarrier")
+            .field("remaining", &self.remaining())
+            .finish()
+    }
+}
+
+struct Ticket {
+    state: Arc<State>,
+}
+
+impl Drop for Ticket {
+    fn drop(&mut self) {
+        let mut count = self.state.lock();
+        count.remaining -= 1;
+        if count.remaining == 0 {
+            let wakers = core::mem::take(&mut count.wakers);
+            drop(count);
+            self.state.drained.notify_all();
+            wakers.into_iter().for_each(Waker::wake);
+        }
+    }
+}
+
+/// A [`Consumer<T>`] bound to a [`ConsumeBarrier`], which passes values on to the consumer `Q`,
+/// then counts the barrier down, even if `Q` panics.
+pub struct Countdown<Q> {
+    inner: Q,
+    _ticket: Ticket,
+}
+
+impl<T, Q: Consumer<T>> Consumer<T> for Countdown<Q> {
+    #[inline]
+    fn consume(self, other: T) {
+        self.inner.consume(other)
+    }
+}
+
+impl<Q: fmt::Debug> fmt::Debug for Countdown<Q> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Countdown").field(&self.inner).finish()
+    }
+}
+
+/// The future returned by [`ConsumeBarrier::drained`].
+pub struct Drained {
+    state: Arc<State>,
+}
+
+impl Future for Drained {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let mut count = self.state.lock();
+        if count.remaining == 0 {
+            return Poll::Ready(());
+        }
+        if !count.wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+            count.wakers.push(cx.waker().clone());
+        }
+        Poll::Pending
+    }
+}
+
+impl fmt::Debug for Drained {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Drained").finish_non_exhaustive()
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use super::ConsumeBarrier;
+    use crate::Closure;
+    use core::future::Future;
+    use core::pin::pin;
+    use core::task::{Context, Poll, Waker};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+    use std::vec::Vec;
+
+    #[test]
+    fn releases_waiters_once_drained() {
+        static CLOSED: AtomicUsize = AtomicUsize::new(0);
+        let barrier = ConsumeBarrier::new();
+        let sessions: Vec<_> = (0..4)
+            .map(|_| {
+                barrier.guard(Closure(|| {
+                    CLOSED.fetch_add(1, Ordering::SeqCst);
+                }))
+            })
+            .collect();
+        assert_eq!(barrier.remaining(), 4);
+
+        let mut drained = pin!(barrier.drained());
+        let mut cx = Context::from_waker(Waker::noop());
+        assert!(drained.as_mut().poll(&mut cx).is_pending());
+        thread::scope(|scope| {
+            for session in sessions {
+                scope.spawn(move || drop(session));
+            }
+            barrier.wait();
+            assert_eq!(CLOSED.load(Ordering::SeqCst), 4);
+        });
+        assert_eq!(drained.poll(&mut cx), Poll::Ready(()));
+
+        let unfinished = barrier.bind(1, Closure(drop));
+        let (_, countdown) = crate::WithConsumer::into_pair(unfinished);
+        assert_eq!(barrier.remaining(), 1);
+        drop(countdown);
+        assert!(barrier.wait_timeout(core::time::Duration::ZERO));
+    }
+}
+
+#[cfg(all(test, loom))]
+mod loom_tests {
+    use super::ConsumeBarrier;
+    use crate::Closure;
+    use loom::sync::atomic::{AtomicUsize, Ordering};
+    use loom::sync::Arc;
+    use loom::thread;
+
+    #[test]
+    fn waiter_sees_every_consumption() {
+        loom::model(|| {
+            let closed = Arc::new(AtomicUsize::new(0));
+            let barrier = ConsumeBarrier::new();
+            for _ in 0..2 {
+                let closed = closed.clone();
+                let session = barrier.guard(Closure(move || {
+                    closed.fetch_add(1, Ordering::Relaxed);
+                }));
+                thread::spawn(move || drop(session));
+            }
+            barrier.wait();
+            assert_eq!(closed.load(Ordering::Relaxed), 2);
+        });
+    }
+}
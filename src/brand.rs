@@ -0,0 +1,169 @@
+use crate::{Consumer, SelfConsumer, WithConsumer};
+use core::fmt;
+use core::marker::PhantomData;
+use core::ops::{Deref, DerefMut};
+
+/// An invariant lifetime which is unique to one call of [`branded_scope`].
+///
+/// No two scopes share a brand, and the brand cannot be named outside of its scope, so anything
+/// carrying it cannot leave the scope.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Brand<'id> {
+    _invariant: PhantomData<fn(&'id ()) -> &'id ()>,
+}
+
+impl fmt::Debug for Brand<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Brand")
+    }
+}
+
+/// The scope passed to the closure of [`branded_scope`], which owns a cleanup context `C`,
+/// such as a device or an arena, and builds guards which cannot outlive it.
+pub struct BrandedScope<'id, C> {
+    context: C,
+    brand: Brand<'id>,
+}
+
+impl<'id, C> BrandedScope<'id, C> {
+    /// The context owned by the scope. Consumers of the scope's guards may borrow it, since the
+    /// guards are always dropped first.
+    #[inline]
+    pub fn context(&self) -> &C {
+        &self.context
+    }
+
+    /// The brand of the scope.
+    #[inline]
+    pub fn brand(&self) -> Brand<'id> {
+        self.brand
+    }
+
+    /// Guards `value`, which is consumed with [`Consume::consume`](crate::Consume::consume)
+    /// when dropped.
+    #[inline]
+    pub fn guard<T: crate::Consume>(&self, value: T) -> BrandedGuard<'id, T> {
+        self.guard_with(value, SelfConsumer)
+    }
+
+    /// Guards `value`, which is consumed by `consumer` when dropped.
+    #[inline]
+    pub fn guard_with<T, Q: Consumer<T>>(&self, value: T, consumer: Q) -> BrandedGuard<'id, T, Q> {
+        BrandedGuard {
+            inner: WithConsumer::new(value, consumer),
+            brand: self.brand,
+        }
+    }
+}
+
+impl<C: fmt::Debug> fmt::Debug for BrandedScope<'_, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BrandedScope")
+            .field("context", &self.context)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Runs `f` with a fresh [`BrandedScope`] owning `context`, and drops the context once `f`
+/// returns.
+///
+/// The guards built by the scope carry its [`Brand`], so it is a compile error to return them
+/// from `f`, or to store them anywhere which outlives the call. Their consumers may therefore
+/// rely on the scope's context, e.g. to release resources allocated from a device or an arena,
+/// without any run-time check.
+///
+/// ```
+/// use consume_on_drop::{branded_scope, Closure};
+/// use std::cell::RefCell;
+///
+/// let released = branded_scope(RefCell::new(Vec::new()), |device| {
+///     let buffer = device.guard_with(3, Closure(|id| device.context().borrow_mut().push(id)));
+///     drop(buffer);
+///     device.context().borrow().clone()
+/// });
+/// assert_eq!(released, [3]);
+/// ```
+///
+/// A guard cannot escape its scope:
+///
+/// ```compile_fail
+/// use consume_on_drop::{branded_scope, Closure};
+///
+/// let escaped = branded_scope((), |scope| scope.guard(Closure(|| ())));
+/// ```
+pub fn branded_scope<C, R>(context: C, f: impl for<'id> FnOnce(&BrandedScope<'id, C>) -> R) -> R {
+    let scope = BrandedScope {
+        context,
+        brand: Brand {
+            _invariant: PhantomData,
+        },
+    };
+    f(&scope)
+}
+
+/// A guard which is branded with the [`BrandedScope`] that built it, so it can never outlive
+/// the scope's context. When dropped, its value is consumed by the consumer `Q`.
+pub struct BrandedGuard<'id, T, Q: Consumer<T> = SelfConsumer> {
+    inner: WithConsumer<T, Q>,
+    brand: Brand<'id>,
+}
+
+impl<'id, T, Q: Consumer<T>> BrandedGuard<'id, T, Q> {
+    /// The brand of the scope which built `x`.
+    #[inline]
+    pub fn brand(x: &Self) -> Brand<'id> {
+        x.brand
+    }
+
+    /// Extracts the value without consuming it.
+    #[inline]
+    pub fn into_inner(x: Self) -> T {
+        WithConsumer::into_inner(x.inner)
+    }
+}
+
+impl<T: fmt::Debug, Q: Consumer<T>> fmt::Debug for BrandedGuard<'_, T, Q> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("BrandedGuard").field(&*self.inner).finish()
+    }
+}
+
+impl<T, Q: Consumer<T>> Deref for BrandedGuard<'_, T, Q> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        &self.inner
+    }
+}
+
+impl<T, Q: Consumer<T>> DerefMut for BrandedGuard<'_, T, Q> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{branded_scope, BrandedGuard};
+    use crate::Closure;
+    use core::cell::Cell;
+
+    #[test]
+    fn guards_released_before_context() {
+        let released = branded_scope(Cell::new(0), |arena| {
+            let mut handle = arena.guard_with(
+                7,
+                Closure(|_| arena.context().set(arena.context().get() + 1)),
+            );
+            *handle += 1;
+            assert_eq!(*handle, 8);
+            assert!(BrandedGuard::brand(&handle) == arena.brand());
+            drop(handle);
+            let _ = BrandedGuard::into_inner(arena.guard(Closure(|| unreachable!())));
+            arena.context().get()
+        });
+        assert_eq!(released, 1);
+    }
+}
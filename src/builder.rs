@@ -0,0 +1,331 @@
+use crate::label::observer::{consume_observed, observer};
+use crate::{Closure, Consume, Consumer, GuardObserver, SelfConsumer, WithConsumer};
+use alloc::borrow::Cow;
+use core::fmt::{self, Display};
+use core::{any, mem};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+/// When a guard built by [`GuardBuilder`] runs its consumer.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Strategy {
+    /// The consumer runs whenever the guard is dropped.
+    #[default]
+    Always,
+    /// The consumer only runs if the guard is dropped while the thread is panicking. Otherwise,
+    /// the value is dropped normally.
+    OnUnwind,
+}
+
+/// What a guard built by [`GuardBuilder`] does if its consumer panics.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum PanicPolicy {
+    /// The panic propagates out of the guard's destructor, as for any other guard.
+    #[default]
+    Propagate,
+    /// The panic is caught and discarded. If the guard is labeled, it is still reported to the
+    /// [`GuardObserver`].
+    ///
+    /// A panic cannot be caught while the thread is already unwinding: if the guard is dropped
+    /// during a panic and its consumer panics too, the process aborts regardless. For that
+    /// reason, [`GuardBuilder::build`] rejects this policy together with [`Strategy::OnUnwind`].
+    Catch,
+    /// The process aborts.
+    Abort,
+}
+
+/// The consumer used by guards built with [`GuardBuilder`]: a consumer `Q` together with the
+/// options controlling how and whether it runs.
+#[derive(Clone)]
+pub struct GuardOptions<Q> {
+    consumer: Q,
+    label: Option<Cow<'static, str>>,
+    observer: Option<&'static dyn GuardObserver>,
+    strategy: Strategy,
+    panic_policy: PanicPolicy,
+}
+
+impl<Q: fmt::Debug> fmt::Debug for GuardOptions<Q> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GuardOptions")
+            .field("consumer", &self.consumer)
+            .field("label", &self.label)
+            .field("strategy", &self.strategy)
+            .field("panic_policy", &self.panic_policy)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<Q> GuardOptions<Q> {
+    /// The guard's label, if any.
+    #[inline]
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    /// The guard's [`Strategy`].
+    #[inline]
+    pub fn strategy(&self) -> Strategy {
+        self.strategy
+    }
+
+    /// The guard's [`PanicPolicy`].
+    #[inline]
+    pub fn panic_policy(&self) -> PanicPolicy {
+        self.panic_policy
+    }
+}
+
+/// Aborts the process if dropped, which only happens if it is not forgotten first.
+struct AbortOnUnwind;
+
+impl Drop for AbortOnUnwind {
+    fn drop(&mut self) {
+        std::process::abort()
+    }
+}
+
+impl<T, Q: Consumer<T>> Consumer<T> for GuardOptions<Q> {
+    fn consume(self, other: T) {
+        if self.strategy == Strategy::OnUnwind && !std::thread::panicking() {
+            return;
+        }
+        let consumer = self.consumer;
+        let label = self.label;
+        let hook = self.observer;
+        let run = move || {
+            if label.is_none() && hook.is_none() {
+                return consumer.consume(other);
+            }
+            // A guard with only a hook is reported under the name of its type.
+            let type_name = any::type_name::<T>();
+            let label: &dyn Display = match &label {
+                Some(label) => label,
+                None => &type_name,
+            };
+            let consume = Closure(move || consumer.consume(other));
+            consume_observed(consume, label, hook.or_else(observer))
+        };
+        match self.panic_policy {
+            PanicPolicy::Propagate => run(),
+            PanicPolicy::Catch => {
+                let _ = catch_unwind(AssertUnwindSafe(run));
+            }
+            PanicPolicy::Abort => {
+                let bomb = AbortOnUnwind;
+                run();
+                mem::forget(bomb);
+            }
+        }
+    }
+}
+
+/// A guard assembled by [`GuardBuilder`].
+pub type BuiltGuard<T, Q = SelfConsumer> = WithConsumer<T, GuardOptions<Q>>;
+
+/// Assembles a guard with optional extras in one expression.
+///
+/// ```
+/// use consume_on_drop::{Closure, GuardBuilder, PanicPolicy, Strategy};
+///
+/// let rows = vec![1, 2, 3];
+/// let guard = GuardBuilder::with_consumer(rows, Closure(|rows: Vec<i32>| {
+///     println!("rolled back {} rows", rows.len())
+/// }))
+///     .label("txn-7")
+///     .strategy(Strategy::OnUnwind)
+///     .panic_policy(PanicPolicy::Abort)
+///     .build();
+/// ```
+///
+/// Without a call to [`GuardBuilder::consumer`], the value is consumed with
+/// [`Consume::consume`].
+#[derive(Clone, Debug)]
+#[must_use = "a builder does nothing until it is built"]
+pub struct GuardBuilder<T, Q = SelfConsumer> {
+    value: T,
+    options: GuardOptions<Q>,
+}
+
+impl<T: Consume> GuardBuilder<T> {
+    /// Starts building a guard around `value`, which will be consumed with
+    /// [`Consume::consume`] unless another consumer is supplied.
+    #[inline]
+    pub const fn new(value: T) -> Self {
+        Self::with_consumer(value, SelfConsumer)
+    }
+}
+
+impl<T, Q: Consumer<T>> GuardBuilder<T, Q> {
+    /// Starts building a guard around `value`, which will be consumed by `consumer`.
+    #[inline]
+    pub const fn with_consumer(value: T, consumer: Q) -> Self {
+        Self {
+            value,
+            options: GuardOptions {
+                consumer,
+                label: None,
+                observer: None,
+                strategy: Strategy::Always,
+                panic_policy: PanicPolicy::Propagate,
+            },
+        }
+    }
+
+    /// Replaces the consumer.
+    #[inline]
+    pub fn consumer<R: Consumer<T>>(self, consumer: R) -> GuardBuilder<T, R> {
+        let GuardOptions {
+            label,
+            observer,
+            strategy,
+            panic_policy,
+            ..
+        } = self.options;
+        GuardBuilder {
+            value: self.value,
+            options: GuardOptions {
+                consumer,
+                label,
+                observer,
+                strategy,
+                panic_policy,
+            },
+        }
+    }
+
+    /// Labels the guard for diagnostics. The label is passed to the
+    /// [`GuardObserver`], as for [`Labeled`](crate::Labeled) values.
+    #[inline]
+    pub fn label(mut self, label: impl Into<Cow<'static, str>>) -> Self {
+        self.options.label = Some(label.into());
+        self
+    }
+
+    /// Reports this guard to `observer` instead of the program-wide observer installed with
+    /// [`set_guard_observer`](crate::set_guard_observer). Unlabeled guards are reported under
+    /// the name of `T`.
+    #[inline]
+    pub fn observer(mut self, observer: &'static dyn GuardObserver) -> Self {
+        self.options.observer = Some(observer);
+        self
+    }
+
+    /// Sets when the consumer runs. The default is [`Strategy::Always`].
+    #[inline]
+    pub fn strategy(mut self, strategy: Strategy) -> Self {
+        self.options.strategy = strategy;
+        self
+    }
+
+    /// Sets what happens if the consumer panics. The default is [`PanicPolicy::Propagate`].
+    #[inline]
+    pub fn panic_policy(mut self, panic_policy: PanicPolicy) -> Self {
+        self.options.panic_policy = panic_policy;
+        self
+    }
+
+    /// Builds the guard.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the guard combines [`Strategy::OnUnwind`] with [`PanicPolicy::Catch`], since
+    /// a consumer which only runs while unwinding cannot have its panics caught.
+    #[inline]
+    pub fn build(self) -> BuiltGuard<T, Q> {
+        assert!(
+            !(self.options.strategy == Strategy::OnUnwind
+                && self.options.panic_policy == PanicPolicy::Catch),
+            "`PanicPolicy::Catch` cannot be used with `Strategy::OnUnwind`"
+        );
+        WithConsumer::new(self.value, self.options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{GuardBuilder, PanicPolicy, Strategy};
+    use crate::{Closure, GuardObserver};
+    use core::cell::Cell;
+    use core::fmt::Display;
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+    use std::string::String;
+    use std::sync::Mutex;
+    use std::vec::Vec;
+
+    #[test]
+    fn strategy_and_panic_policy() {
+        let rolled_back = Cell::new(0);
+        let rollback = Closure(|n| rolled_back.set(rolled_back.get() + n));
+
+        drop(
+            GuardBuilder::with_consumer(1, rollback)
+                .strategy(Strategy::OnUnwind)
+                .build(),
+        );
+        assert_eq!(rolled_back.get(), 0);
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            let _guard = GuardBuilder::with_consumer(2, rollback)
+                .strategy(Strategy::OnUnwind)
+                .label("txn")
+                .build();
+            panic!("request failed");
+        }));
+        assert!(result.is_err());
+        assert_eq!(rolled_back.get(), 2);
+
+        let guard = GuardBuilder::with_consumer((), Closure(|()| panic!("cleanup failed")))
+            .panic_policy(PanicPolicy::Catch)
+            .build();
+        drop(guard);
+    }
+
+    #[test]
+    #[should_panic = "cannot be used with `Strategy::OnUnwind`"]
+    fn rejects_catching_on_unwind() {
+        let _ = GuardBuilder::with_consumer((), Closure(|()| ()))
+            .strategy(Strategy::OnUnwind)
+            .panic_policy(PanicPolicy::Catch)
+            .build();
+    }
+
+    #[test]
+    fn per_guard_observer() {
+        static EVENTS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+        struct Recorder;
+
+        impl GuardObserver for Recorder {
+            fn consuming(&self, label: &dyn Display) {
+                EVENTS
+                    .lock()
+                    .unwrap()
+                    .push(std::format!("consuming {}", label))
+            }
+
+            fn panicked(&self, label: &dyn Display) {
+                EVENTS
+                    .lock()
+                    .unwrap()
+                    .push(std::format!("panicked {}", label))
+            }
+        }
+
+        drop(
+            GuardBuilder::with_consumer(1u8, Closure(drop))
+                .observer(&Recorder)
+                .build(),
+        );
+        let guard = GuardBuilder::with_consumer((), Closure(|()| panic!("cleanup failed")))
+            .label("txn")
+            .observer(&Recorder)
+            .panic_policy(PanicPolicy::Catch)
+            .build();
+        drop(guard);
+        assert_eq!(
+            *EVENTS.lock().unwrap(),
+            ["consuming u8", "consuming txn", "panicked txn"]
+        );
+    }
+}
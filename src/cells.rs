@@ -0,0 +1,99 @@
+use crate::Consume;
+use core::cell::{Cell, OnceCell, RefCell};
+
+/// Takes the value out of the cell and consumes it.
+impl<T: Consume> Consume for Cell<T> {
+    #[inline]
+    fn consume(self) {
+        self.into_inner().consume()
+    }
+}
+
+/// Takes the value out of the cell and consumes it. Since the cell is owned, it cannot be
+/// borrowed, so this never panics.
+impl<T: Consume> Consume for RefCell<T> {
+    #[inline]
+    fn consume(self) {
+        self.into_inner().consume()
+    }
+}
+
+/// Consumes the value if the cell was ever initialized.
+impl<T: Consume> Consume for OnceCell<T> {
+    #[inline]
+    fn consume(self) {
+        if let Some(value) = self.into_inner() {
+            value.consume()
+        }
+    }
+}
+
+// A poisoned lock only means that another thread panicked while holding it. Consumption is
+// cleanup, which must still happen, so the value is consumed whether or not the lock is poisoned.
+
+/// Takes the value out of the mutex and consumes it, ignoring poisoning.
+#[cfg(feature = "std")]
+impl<T: Consume> Consume for std::sync::Mutex<T> {
+    #[inline]
+    fn consume(self) {
+        self.into_inner()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .consume()
+    }
+}
+
+/// Takes the value out of the lock and consumes it, ignoring poisoning.
+#[cfg(feature = "std")]
+impl<T: Consume> Consume for std::sync::RwLock<T> {
+    #[inline]
+    fn consume(self) {
+        self.into_inner()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .consume()
+    }
+}
+
+/// Consumes the value if the lock was ever initialized.
+#[cfg(feature = "std")]
+impl<T: Consume> Consume for std::sync::OnceLock<T> {
+    #[inline]
+    fn consume(self) {
+        if let Some(value) = self.into_inner() {
+            value.consume()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Closure, ConsumeOnDrop};
+    use core::cell::{Cell, OnceCell, RefCell};
+
+    #[test]
+    fn cells_consumed() {
+        let count = Cell::new(0);
+        let bump = || Closure(|| count.set(count.get() + 1));
+        drop(ConsumeOnDrop::new(Cell::new(bump())));
+        drop(ConsumeOnDrop::new(RefCell::new(bump())));
+        drop(ConsumeOnDrop::new(OnceCell::from(bump())));
+        drop(ConsumeOnDrop::new(OnceCell::<Closure<fn()>>::new()));
+        assert_eq!(count.get(), 3);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn poisoned_mutex_consumed() {
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+        use std::sync::Mutex;
+
+        let count = Cell::new(0);
+        let state = ConsumeOnDrop::new(Mutex::new(Closure(|| count.set(count.get() + 1))));
+        let _ = catch_unwind(AssertUnwindSafe(|| {
+            let _lock = state.lock().unwrap();
+            panic!("poison the lock")
+        }));
+        assert!(state.is_poisoned());
+        drop(state);
+        assert_eq!(count.get(), 1);
+    }
+}
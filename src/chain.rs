@@ -0,0 +1,196 @@
+use crate::TryConsume;
+use alloc::vec::{IntoIter, Vec};
+use core::fmt;
+use core::mem;
+
+/// A sequence of fallible cleanups which are consumed in registration order, stopping at the
+/// first failure.
+///
+/// This is the accounting needed by rollback scripts: [`GuardChain::run`] reports how many
+/// entries succeeded, which entry failed and why, and hands back the entries which were skipped,
+/// so that they can be retried, reported, or dropped. If the chain is dropped instead, it is run
+/// and the report is discarded, so the skipped entries are dropped without being consumed.
+///
+/// If consuming an entry panics, the remaining entries are dropped without being consumed.
+pub struct GuardChain<T: TryConsume> {
+    entries: Vec<T>,
+}
+
+impl<T: TryConsume> GuardChain<T> {
+    /// Builds an empty chain.
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+
+    /// Appends `value` to the chain, returning its index in the eventual [`ChainReport`].
+    pub fn push(&mut self, value: T) -> usize {
+        self.entries.push(value);
+        self.entries.len() - 1
+    }
+
+    /// The number of entries in the chain.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the chain has no entries.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Consumes the entries in order until one fails, and reports what happened.
+    pub fn run(mut chain: Self) -> ChainReport<T> {
+        Self::run_entries(mem::take(&mut chain.entries).into_iter())
+    }
+
+    fn run_entries(mut entries: IntoIter<T>) -> ChainReport<T> {
+        let mut succeeded = 0;
+        for entry in entries.by_ref() {
+            if let Err(error) = entry.try_consume() {
+                return ChainReport {
+                    succeeded,
+                    failure: Some(error),
+                    skipped: entries.collect(),
+                };
+            }
+            succeeded += 1;
+        }
+        ChainReport {
+            succeeded,
+            failure: None,
+            skipped: Vec::new(),
+        }
+    }
+}
+
+impl<T: TryConsume> Default for GuardChain<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: TryConsume> Extend<T> for GuardChain<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        self.entries.extend(iter)
+    }
+}
+
+impl<T: TryConsume> Drop for GuardChain<T> {
+    fn drop(&mut self) {
+        Self::run_entries(mem::take(&mut self.entries).into_iter());
+    }
+}
+
+impl<T: TryConsume + fmt::Debug> fmt::Debug for GuardChain<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GuardChain")
+            .field("entries", &self.entries)
+            .finish()
+    }
+}
+
+/// The outcome of [`GuardChain::run`].
+///
+/// Entries are identified by the indices returned by [`GuardChain::push`]. The first
+/// [`ChainReport::succeeded`] entries were consumed successfully. If an entry failed, it is the
+/// next one, and every entry after it was skipped.
+pub struct ChainReport<T: TryConsume> {
+    succeeded: usize,
+    failure: Option<T::Error>,
+    skipped: Vec<T>,
+}
+
+impl<T: TryConsume> ChainReport<T> {
+    /// The number of entries which were consumed successfully.
+    #[inline]
+    pub fn succeeded(&self) -> usize {
+        self.succeeded
+    }
+
+    /// The index and error of the entry which failed, if any.
+    #[inline]
+    pub fn failure(&self) -> Option<(usize, &T::Error)> {
+        self.failure.as_ref().map(|error| (self.succeeded, error))
+    }
+
+    /// The entries which were skipped because an earlier entry failed, in order. The first of
+    /// them has index `succeeded() + 1`.
+    #[inline]
+    pub fn skipped(&self) -> &[T] {
+        &self.skipped
+    }
+
+    /// Whether every entry was consumed successfully.
+    #[inline]
+    pub fn is_complete(&self) -> bool {
+        self.failure.is_none()
+    }
+
+    /// Converts the report into the error of the failed entry along with the skipped entries,
+    /// or `Ok` with the number of entries if every entry was consumed successfully.
+    pub fn into_result(self) -> Result<usize, (T::Error, Vec<T>)> {
+        match self.failure {
+            None => Ok(self.succeeded),
+            Some(error) => Err((error, self.skipped)),
+        }
+    }
+}
+
+impl<T: TryConsume + fmt::Debug> fmt::Debug for ChainReport<T>
+where
+    T::Error: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChainReport")
+            .field("succeeded", &self.succeeded)
+            .field("failure", &self.failure)
+            .field("skipped", &self.skipped)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GuardChain;
+    use crate::Closure;
+    use core::cell::RefCell;
+    use std::vec::Vec;
+
+    #[test]
+    fn stops_at_first_failure() {
+        let log = RefCell::new(Vec::new());
+        let step = |name: &'static str, ok: bool| {
+            let log = &log;
+            Closure(move || {
+                log.borrow_mut().push(name);
+                if ok {
+                    Ok(())
+                } else {
+                    Err(name)
+                }
+            })
+        };
+
+        let mut chain = GuardChain::new();
+        chain.push(step("drop table", true));
+        let failing = chain.push(step("drop schema", false));
+        chain.push(step("drop user", true));
+        let report = GuardChain::run(chain);
+        assert_eq!(report.succeeded(), 1);
+        assert_eq!(report.failure(), Some((failing, &"drop schema")));
+        assert_eq!(report.skipped().len(), 1);
+        assert_eq!(*log.borrow(), ["drop table", "drop schema"]);
+
+        let (_, skipped) = report.into_result().unwrap_err();
+        let mut retry = GuardChain::new();
+        retry.extend(skipped);
+        assert_eq!(GuardChain::run(retry).into_result().ok(), Some(1));
+        assert_eq!(*log.borrow(), ["drop table", "drop schema", "drop user"]);
+    }
+}
@@ -0,0 +1,155 @@
+use crate::{Consume, ConsumeOnDrop};
+use core::fmt;
+use core::time::Duration;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+
+struct Shared<T> {
+    value: Mutex<T>,
+    stopped: Mutex<bool>,
+    wake: Condvar,
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+struct Checkpointing<T> {
+    shared: Arc<Shared<T>>,
+    thread: JoinHandle<()>,
+}
+
+impl<T> Checkpointing<T> {
+    /// Stops the checkpoint thread, waiting for a checkpoint in progress to finish, and
+    /// returns the value.
+    fn stop(self) -> T {
+        *lock(&self.shared.stopped) = true;
+        self.shared.wake.notify_one();
+        // A panicking checkpoint has already been reported by the panic hook.
+        let _ = self.thread.join();
+        match Arc::try_unwrap(self.shared) {
+            Ok(shared) => shared
+                .value
+                .into_inner()
+                .unwrap_or_else(|poisoned| poisoned.into_inner()),
+            Err(_) => unreachable!("the checkpoint thread has exited"),
+        }
+    }
+}
+
+impl<T: Consume> Consume for Checkpointing<T> {
+    #[inline]
+    fn consume(self) {
+        self.stop().consume()
+    }
+}
+
+/// A guard which checkpoints its value at a fixed interval on a background thread, and consumes
+/// it when dropped.
+///
+/// This persists the state of a long-running job both periodically and finally, without
+/// weaving a timer around every guarded state object. The checkpoint callback runs while
+/// holding the same lock as [`CheckpointGuard::lock`]. When the guard is dropped, the thread is
+/// stopped, waiting for a checkpoint in progress to finish, before the value is consumed. If
+/// the callback panics, checkpointing stops, but the value is still consumed.
+pub struct CheckpointGuard<T: Consume> {
+    inner: ConsumeOnDrop<Checkpointing<T>>,
+}
+
+impl<T: Consume + Send + 'static> CheckpointGuard<T> {
+    /// Guards `value`, calling `checkpoint` on it every `interval` until the guard is dropped.
+    pub fn new<F>(value: T, interval: Duration, mut checkpoint: F) -> Self
+    where
+        F: FnMut(&mut T) + Send + 'static,
+    {
+        let shared = Arc::new(Shared {
+            value: Mutex::new(value),
+            stopped: Mutex::new(false),
+            wake: Condvar::new(),
+        });
+        let thread = thread::spawn({
+            let shared = shared.clone();
+            move || loop {
+                let stopped = lock(&shared.stopped);
+                let (stopped, _) = shared
+                    .wake
+                    .wait_timeout_while(stopped, interval, |stopped| !*stopped)
+                    .unwrap_or_else(|poisoned| poisoned.into_inner());
+                if *stopped {
+                    return;
+                }
+                drop(stopped);
+                checkpoint(&mut lock(&shared.value));
+            }
+        });
+        Self {
+            inner: ConsumeOnDrop::new(Checkpointing { shared, thread }),
+        }
+    }
+}
+
+impl<T: Consume> CheckpointGuard<T> {
+    /// Locks the value for access, delaying any checkpoint until the lock is released.
+    #[inline]
+    pub fn lock(x: &Self) -> MutexGuard<'_, T> {
+        lock(&x.inner.shared.value)
+    }
+
+    /// Stops checkpointing and extracts the value without consuming it.
+    #[inline]
+    pub fn into_inner(x: Self) -> T {
+        ConsumeOnDrop::into_inner(x.inner).stop()
+    }
+}
+
+impl<T: Consume + fmt::Debug> fmt::Debug for CheckpointGuard<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("CheckpointGuard")
+            .field(&*Self::lock(self))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CheckpointGuard;
+    use crate::Consume;
+    use core::time::Duration;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::vec::Vec;
+
+    struct Job {
+        progress: u32,
+        saved: Arc<Mutex<Vec<u32>>>,
+    }
+
+    impl Consume for Job {
+        fn consume(self) {
+            self.saved.lock().unwrap().push(self.progress)
+        }
+    }
+
+    #[test]
+    fn checkpoints_then_consumes() {
+        let saved = Arc::new(Mutex::new(Vec::new()));
+        let job = Job {
+            progress: 0,
+            saved: saved.clone(),
+        };
+        let guard = CheckpointGuard::new(job, Duration::from_millis(1), |job: &mut Job| {
+            job.saved.lock().unwrap().push(job.progress)
+        });
+        CheckpointGuard::lock(&guard).progress = 7;
+        while saved.lock().unwrap().is_empty() {
+            thread::sleep(Duration::from_millis(1));
+        }
+        drop(guard);
+        let saved = saved.lock().unwrap();
+        // At least one checkpoint, followed by the final consumption.
+        assert!(saved.len() >= 2);
+        assert_eq!(saved.last(), Some(&7));
+    }
+}
@@ -0,0 +1,84 @@
+use crate::{Consumer, WithConsumer};
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+/// Wraps `future` so that, if it is dropped before completing, e.g. because its task was
+/// cancelled, `consumer` receives the unfinished future along with whatever state it holds.
+/// Once the future completes, its output is passed through and `consumer` is dropped unused.
+///
+/// The future must be [`Unpin`], since it is moved into the consumer. To use a future which is
+/// not, pin it first with [`Box::pin`](alloc::boxed::Box::pin) or [`core::pin::pin!`]; the
+/// consumer then receives the pinned pointer.
+#[inline]
+pub fn with_cleanup<F: Future + Unpin, Q: Consumer<F>>(
+    future: F,
+    consumer: Q,
+) -> WithCleanup<F, Q> {
+    WithCleanup {
+        inner: Some(WithConsumer::new(future, consumer)),
+    }
+}
+
+/// The future returned by [`with_cleanup`].
+#[derive(Debug)]
+#[must_use = "futures do nothing unless polled"]
+pub struct WithCleanup<F: Future + Unpin, Q: Consumer<F>> {
+    /// `None` once the future has completed.
+    inner: Option<WithConsumer<F, Q>>,
+}
+
+impl<F: Future + Unpin, Q: Consumer<F>> WithCleanup<F, Q> {
+    /// Disarms the cleanup, returning the future and the consumer, or `None` if the future
+    /// has already completed.
+    #[inline]
+    pub fn into_parts(x: Self) -> Option<(F, Q)> {
+        x.inner.map(WithConsumer::into_pair)
+    }
+}
+
+// The future is only ever accessed through `&mut F`, which is fine since `F: Unpin`.
+impl<F: Future + Unpin, Q: Consumer<F>> Unpin for WithCleanup<F, Q> {}
+
+impl<F: Future + Unpin, Q: Consumer<F>> Future for WithCleanup<F, Q> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        let this = self.get_mut();
+        let future = this
+            .inner
+            .as_mut()
+            .expect("`WithCleanup` polled after completion");
+        let output = Pin::new(&mut **future).poll(cx);
+        if output.is_ready() {
+            // The future finished, so there is nothing to clean up.
+            let _ = this.inner.take().map(WithConsumer::into_pair);
+        }
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::with_cleanup;
+    use crate::Closure;
+    use core::future::{pending, ready, Future};
+    use core::pin::pin;
+    use core::task::{Context, Poll, Waker};
+
+    #[test]
+    fn cleans_up_only_when_cancelled() {
+        let mut cx = Context::from_waker(Waker::noop());
+        let mut cancelled = 0;
+
+        let mut done = with_cleanup(ready(5), Closure(|_| cancelled += 1));
+        assert_eq!(pin!(&mut done).poll(&mut cx), Poll::Ready(5));
+        drop(done);
+
+        let fut = pin!(pending::<()>());
+        let mut stuck = with_cleanup(fut, Closure(|_| cancelled += 10));
+        assert_eq!(pin!(&mut stuck).poll(&mut cx), Poll::Pending);
+        drop(stuck);
+        assert_eq!(cancelled, 10);
+    }
+}
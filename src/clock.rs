@@ -0,0 +1,161 @@
+use core::fmt;
+use core::time::Duration;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread;
+use std::time::Instant;
+
+/// A source of time for the timed features of this crate, such as [`Revoker::revoke_at_with`]
+/// and the long-held guard reports of the `watchdog` feature.
+///
+/// [`SystemClock`] reads the real monotonic clock. [`MockClock`] only moves when told to, which
+/// makes timing-related behavior deterministic in tests.
+///
+/// [`Revoker::revoke_at_with`]: crate::Revoker::revoke_at_with
+pub trait Clock: Send + Sync {
+    /// The current time.
+    fn now(&self) -> Instant;
+
+    /// Blocks the current thread until [`Clock::now`] reaches `deadline`.
+    fn sleep_until(&self, deadline: Instant);
+}
+
+impl<C: Clock + ?Sized> Clock for &C {
+    #[inline]
+    fn now(&self) -> Instant {
+        (**self).now()
+    }
+
+    #[inline]
+    fn sleep_until(&self, deadline: Instant) {
+        (**self).sleep_until(deadline)
+    }
+}
+
+impl<C: Clock + ?Sized> Clock for Arc<C> {
+    #[inline]
+    fn now(&self) -> Instant {
+        (**self).now()
+    }
+
+    #[inline]
+    fn sleep_until(&self, deadline: Instant) {
+        (**self).sleep_until(deadline)
+    }
+}
+
+/// The [`Clock`] which reads [`Instant::now`] and sleeps with [`thread::sleep`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    #[inline]
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    #[inline]
+    fn sleep_until(&self, deadline: Instant) {
+        thread::sleep(deadline.saturating_duration_since(Instant::now()))
+    }
+}
+
+struct MockTime {
+    now: Mutex<Instant>,
+    advanced: Condvar,
+}
+
+/// A [`Clock`] which stands still until it is advanced, for deterministic tests.
+///
+/// Clones share the same time. Threads sleeping on the clock wake up once it is advanced past
+/// their deadlines, without any real waiting.
+#[derive(Clone)]
+pub struct MockClock {
+    time: Arc<MockTime>,
+}
+
+impl MockClock {
+    /// Builds a clock which starts at the current real time.
+    #[inline]
+    pub fn new() -> Self {
+        Self::starting_at(Instant::now())
+    }
+
+    /// Builds a clock which starts at `start`.
+    pub fn starting_at(start: Instant) -> Self {
+        Self {
+            time: Arc::new(MockTime {
+                now: Mutex::new(start),
+                advanced: Condvar::new(),
+            }),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Instant> {
+        self.time
+            .now
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Moves the clock forward by `by`, waking the threads whose deadlines have been reached.
+    pub fn advance(&self, by: Duration) {
+        *self.lock() += by;
+        self.time.advanced.notify_all();
+    }
+}
+
+impl Default for MockClock {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for MockClock {
+    #[inline]
+    fn now(&self) -> Instant {
+        *self.lock()
+    }
+
+    fn sleep_until(&self, deadline: Instant) {
+        drop(
+            self.time
+                .advanced
+                .wait_while(self.lock(), |now| *now < deadline)
+                .unwrap_or_else(|poisoned| poisoned.into_inner()),
+        )
+    }
+}
+
+impl fmt::Debug for MockClock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MockClock")
+            .field("now", &self.now())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Clock, MockClock};
+    use crate::{Closure, RevocableGuard};
+    use core::time::Duration;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    #[test]
+    fn mock_clock_drives_deadlines() {
+        static REVOKED: AtomicBool = AtomicBool::new(false);
+        let clock = MockClock::new();
+        let start = clock.now();
+        let (guard, revoker) =
+            RevocableGuard::new(Closure(|| REVOKED.store(true, Ordering::SeqCst)));
+        let revoking = revoker.revoke_at_with(start + Duration::from_secs(3600), clock.clone());
+
+        clock.advance(Duration::from_secs(1800));
+        assert!(!RevocableGuard::is_revoked(&guard));
+        clock.advance(Duration::from_secs(1800));
+        assert!(revoking.join().unwrap());
+        assert!(REVOKED.load(Ordering::SeqCst));
+        assert_eq!(clock.now() - start, Duration::from_secs(3600));
+    }
+}
@@ -0,0 +1,50 @@
+use crate::{Consume, Consumer};
+
+/// Marks a [`Consume`] or [`Consumer`] as rarely run, so that its body is kept out of line.
+///
+/// The consumption of a guard is normally inlined into every function which drops it, which
+/// adds up when many guards are instantiated, e.g. in firmware where flash is scarce. Wrapping
+/// the consumer in [`Cold`] moves its body into a separate `#[cold]` function, which is never
+/// inlined, so the hot path only keeps a call. The function is still generic, since it runs the
+/// wrapped consumer, so each instantiation is outlined once rather than eliminated.
+///
+/// To outline the drop glue of every guard instead, enable the `cold-drop` feature.
+#[repr(transparent)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Cold<Q>(pub Q);
+
+impl<Q: Consume> Consume for Cold<Q> {
+    #[cold]
+    #[inline(never)]
+    fn consume(self) {
+        self.0.consume()
+    }
+}
+
+impl<T, Q: Consumer<T>> Consumer<T> for Cold<Q> {
+    #[cold]
+    #[inline(never)]
+    fn consume(self, other: T) {
+        self.0.consume(other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Cold;
+    use crate::{Closure, ConsumeOnDrop, WithConsumer};
+    use core::cell::Cell;
+
+    #[test]
+    fn forwards_consumption() {
+        let count = Cell::new(0);
+        drop(ConsumeOnDrop::new(Cold(Closure(|| {
+            count.set(count.get() + 1)
+        }))));
+        drop(WithConsumer::new(
+            2,
+            Cold(Closure(|n| count.set(count.get() + n))),
+        ));
+        assert_eq!(count.get(), 3);
+    }
+}
@@ -0,0 +1,120 @@
+use crate::composite::consume_all;
+use crate::Consume;
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+// If consuming one element of a collection panics, the remaining elements are still consumed
+// while unwinding, as described for `Composed`.
+
+/// Moves the value out of the box and consumes it. The allocation is freed afterwards, even if
+/// consuming the value panics.
+impl<T: Consume> Consume for Box<T> {
+    #[inline]
+    fn consume(self) {
+        (*self).consume()
+    }
+}
+
+/// Consumes every element in order.
+impl<T: Consume> Consume for Vec<T> {
+    #[inline]
+    fn consume(self) {
+        consume_all(self)
+    }
+}
+
+/// Consumes every element in order.
+#[cfg(feature = "smallvec")]
+impl<A: smallvec::Array<Item: Consume>> Consume for smallvec::SmallVec<A> {
+    #[inline]
+    fn consume(self) {
+        consume_all(self)
+    }
+}
+
+/// Consumes every element in order.
+#[cfg(feature = "arrayvec")]
+impl<T: Consume, const N: usize> Consume for arrayvec::ArrayVec<T, N> {
+    #[inline]
+    fn consume(self) {
+        consume_all(self)
+    }
+}
+
+/// Consumes every value in ascending order of their keys. The keys are dropped.
+impl<K, V: Consume> Consume for BTreeMap<K, V> {
+    #[inline]
+    fn consume(self) {
+        consume_all(self.into_values())
+    }
+}
+
+/// Consumes every value in the map's iteration order, which is unspecified. The keys are dropped.
+#[cfg(feature = "std")]
+impl<K, V: Consume, S> Consume for std::collections::HashMap<K, V, S> {
+    #[inline]
+    fn consume(self) {
+        consume_all(self.into_values())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Closure, ConsumeOnDrop};
+    use alloc::collections::BTreeMap;
+    use alloc::vec::Vec;
+    use core::cell::RefCell;
+
+    #[test]
+    fn btree_map_consumed_in_key_order() {
+        let order = RefCell::new(Vec::new());
+        let order = &order;
+        let mut table = ConsumeOnDrop::new(BTreeMap::new());
+        for key in [3, 1, 2] {
+            table.insert(key, Closure(move || order.borrow_mut().push(key)));
+        }
+        drop(table);
+        assert_eq!(*order.borrow(), [1, 2, 3]);
+    }
+
+    #[test]
+    fn boxed_keeps_address() {
+        let seen = core::cell::Cell::new(0);
+        let guard = ConsumeOnDrop::boxed(Closure(|| seen.set(seen.get() + 1)));
+        let address: *const _ = &**guard;
+        let moved = guard;
+        assert!(core::ptr::eq(address, &**moved));
+        drop(moved);
+        assert_eq!(seen.get(), 1);
+    }
+
+    #[cfg(all(feature = "smallvec", feature = "arrayvec"))]
+    #[test]
+    fn small_vectors_consumed_in_order() {
+        let order = RefCell::new(Vec::new());
+        let order = &order;
+        let handle = |id| Closure(move || order.borrow_mut().push(id));
+
+        let mut inline = smallvec::SmallVec::<[_; 2]>::new();
+        inline.extend([handle(1), handle(2), handle(3)]);
+        drop(ConsumeOnDrop::new(inline));
+        let mut bounded = arrayvec::ArrayVec::<_, 4>::new();
+        bounded.extend([handle(4), handle(5)]);
+        drop(ConsumeOnDrop::new(bounded));
+        assert_eq!(*order.borrow(), [1, 2, 3, 4, 5]);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn hash_map_consumed() {
+        let count = core::cell::Cell::new(0);
+        let count = &count;
+        let mut table = std::collections::HashMap::new();
+        for key in 0..4 {
+            table.insert(key, Closure(move || count.set(count.get() + 1)));
+        }
+        drop(ConsumeOnDrop::new(table));
+        assert_eq!(count.get(), 4);
+    }
+}
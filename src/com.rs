@@ -0,0 +1,184 @@
+use crate::{Consumer, WithConsumer};
+use core::ffi::c_void;
+use core::fmt;
+use core::ptr::NonNull;
+
+/// The first three entries of every COM vtable, those of `IUnknown`.
+#[repr(C)]
+struct IUnknownVtbl {
+    _query_interface: *const c_void,
+    add_ref: unsafe extern "system" fn(this: *mut c_void) -> u32,
+    release: unsafe extern "system" fn(this: *mut c_void) -> u32,
+}
+
+/// Returns the `IUnknown` part of the vtable of the COM object at `this`.
+///
+/// # Safety
+///
+/// `this` must point to a live COM object.
+unsafe fn vtable<'a, I>(this: NonNull<I>) -> &'a IUnknownVtbl {
+    // SAFETY: a COM interface pointer points to a pointer to its vtable, which starts with the
+    // methods of `IUnknown`.
+    unsafe { &**this.cast::<*const IUnknownVtbl>().as_ptr() }
+}
+
+/// A [`Consumer<NonNull<I>>`] which calls `IUnknown::Release` on a COM interface pointer.
+///
+/// `I` may be any type: only the layout of the object is relied on, so this works with raw
+/// pointers from `windows-sys`, bindgen output, or any other COM-style interface.
+#[derive(Debug, Clone, Copy)]
+pub struct ReleaseCom {
+    _private: (),
+}
+
+impl ReleaseCom {
+    /// Builds a consumer which releases COM interface pointers.
+    ///
+    /// # Safety
+    ///
+    /// Any pointer consumed by the result must point to a live COM object, and must own one of
+    /// its references, which is not used again once consumed.
+    #[inline]
+    pub const unsafe fn new() -> Self {
+        Self { _private: () }
+    }
+}
+
+impl<I> Consumer<NonNull<I>> for ReleaseCom {
+    #[inline]
+    fn consume(self, other: NonNull<I>) {
+        // SAFETY: the constructor requires that `other` points to a live COM object and owns a
+        // reference to it.
+        unsafe { (vtable(other).release)(other.as_ptr().cast()) };
+    }
+}
+
+/// An owned reference to a COM object, which is released with `IUnknown::Release` when
+/// dropped. Cloning calls `IUnknown::AddRef`.
+///
+/// This is as cheap as the raw pointer: [`ReleaseCom`] is zero-sized, and `Option<ComPtr<I>>`
+/// is the size of a pointer. The interface type `I` is only used to type the pointer, so any
+/// COM-style interface may be guarded.
+pub struct ComPtr<I = c_void> {
+    inner: WithConsumer<NonNull<I>, ReleaseCom>,
+}
+
+impl<I> ComPtr<I> {
+    /// Takes ownership of a reference to the object at `raw`, such as one returned through an
+    /// out-parameter.
+    ///
+    /// # Safety
+    ///
+    /// `raw` must point to a live COM object, and the caller must own one of its references.
+    #[inline]
+    pub const unsafe fn from_raw(raw: NonNull<I>) -> Self {
+        Self {
+            inner: WithConsumer::new(raw, ReleaseCom::new()),
+        }
+    }
+
+    /// Adds a reference to the object at `raw` with `IUnknown::AddRef`, and takes ownership of
+    /// it, e.g. for a pointer received as an in-parameter.
+    ///
+    /// # Safety
+    ///
+    /// `raw` must point to a live COM object.
+    #[inline]
+    pub unsafe fn from_borrowed(raw: NonNull<I>) -> Self {
+        // SAFETY: the caller guarantees that `raw` points to a live COM object.
+        unsafe {
+            (vtable(raw).add_ref)(raw.as_ptr().cast());
+            Self::from_raw(raw)
+        }
+    }
+
+    /// The underlying interface pointer, whose reference stays owned by `x`.
+    #[inline]
+    pub fn as_raw(x: &Self) -> NonNull<I> {
+        *x.inner
+    }
+
+    /// Releases ownership of the reference without calling `IUnknown::Release`.
+    #[inline]
+    pub fn into_raw(x: Self) -> NonNull<I> {
+        WithConsumer::into_inner(x.inner)
+    }
+}
+
+impl<I> Clone for ComPtr<I> {
+    #[inline]
+    fn clone(&self) -> Self {
+        // SAFETY: `self` owns a reference, so the object is live.
+        unsafe { Self::from_borrowed(Self::as_raw(self)) }
+    }
+}
+
+impl<I> fmt::Debug for ComPtr<I> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ComPtr").field(&Self::as_raw(self)).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ComPtr, IUnknownVtbl};
+    use core::ffi::c_void;
+    use core::mem::size_of;
+    use core::ptr::{self, NonNull};
+    use core::sync::atomic::{AtomicU32, Ordering};
+
+    #[repr(C)]
+    struct Object {
+        vtable: *const IUnknownVtbl,
+        refs: AtomicU32,
+    }
+
+    unsafe extern "system" fn add_ref(this: *mut c_void) -> u32 {
+        unsafe {
+            (*this.cast::<Object>())
+                .refs
+                .fetch_add(1, Ordering::Relaxed)
+                + 1
+        }
+    }
+
+    unsafe extern "system" fn release(this: *mut c_void) -> u32 {
+        unsafe {
+            (*this.cast::<Object>())
+                .refs
+                .fetch_sub(1, Ordering::Relaxed)
+                - 1
+        }
+    }
+
+    const VTABLE: IUnknownVtbl = IUnknownVtbl {
+        _query_interface: ptr::null(),
+        add_ref,
+        release,
+    };
+
+    #[test]
+    fn counts_references() {
+        assert_eq!(
+            size_of::<Option<ComPtr<Object>>>(),
+            size_of::<*mut Object>()
+        );
+        let mut object = Object {
+            vtable: &VTABLE,
+            refs: AtomicU32::new(1),
+        };
+        let raw = NonNull::from(&mut object);
+        let first = unsafe { ComPtr::from_raw(raw) };
+        let second = first.clone();
+        let third = unsafe { ComPtr::from_borrowed(raw) };
+        assert_eq!(object_refs(raw), 3);
+        drop((first, second));
+        assert_eq!(object_refs(raw), 1);
+        assert_eq!(ComPtr::into_raw(third), raw);
+        assert_eq!(object_refs(raw), 1);
+    }
+
+    fn object_refs(raw: NonNull<Object>) -> u32 {
+        unsafe { raw.as_ref() }.refs.load(Ordering::Relaxed)
+    }
+}
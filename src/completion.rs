@@ -0,0 +1,209 @@
+use crate::{Closure, Consume, ConsumeOnDrop, Consumer};
+use core::fmt;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::Duration;
+
+/// Something which can signal that an event happened, such as the end of a consumption. See
+/// [`NotifyOnConsume`].
+pub trait Notifier {
+    /// Signals the event.
+    fn notify(self);
+}
+
+impl<F: FnOnce()> Notifier for Closure<F> {
+    #[inline]
+    fn notify(self) {
+        (self.0)()
+    }
+}
+
+#[derive(Default)]
+struct Flag {
+    done: Mutex<bool>,
+    changed: Condvar,
+}
+
+impl Flag {
+    fn lock(&self) -> MutexGuard<'_, bool> {
+        self.done
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// A cloneable flag, paired with a [`Condvar`], which threads can wait on until it is signaled.
+///
+/// Used as the [`Notifier`] of a [`NotifyOnConsume`], it lets a coordinator block until a
+/// resource is actually gone, rather than merely until its guard was dropped somewhere.
+#[derive(Clone, Default)]
+pub struct Completion {
+    flag: Arc<Flag>,
+}
+
+impl Completion {
+    /// Builds a flag which has not been signaled.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the flag has been signaled.
+    #[inline]
+    pub fn is_complete(&self) -> bool {
+        *self.flag.lock()
+    }
+
+    /// Signals the flag, waking every thread waiting for it.
+    pub fn signal(&self) {
+        *self.flag.lock() = true;
+        self.flag.changed.notify_all();
+    }
+
+    /// Blocks until the flag is signaled.
+    pub fn wait(&self) {
+        let done = self.flag.lock();
+        drop(
+            self.flag
+                .changed
+                .wait_while(done, |done| !*done)
+                .unwrap_or_else(|poisoned| poisoned.into_inner()),
+        )
+    }
+
+    /// Blocks until the flag is signaled, or `timeout` elapses. Returns whether the flag was
+    /// signaled.
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        let done = self.flag.lock();
+        let (done, _) = self
+            .flag
+            .changed
+            .wait_timeout_while(done, timeout, |done| !*done)
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        *done
+    }
+}
+
+impl Notifier for Completion {
+    #[inline]
+    fn notify(self) {
+        self.signal()
+    }
+}
+
+impl fmt::Debug for Completion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Completion")
+            .field("complete", &self.is_complete())
+            .finish()
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl Notifier for tokio::sync::oneshot::Sender<()> {
+    /// If the receiver is gone, nobody is waiting, so there is nothing to do.
+    #[inline]
+    fn notify(self) {
+        let _ = self.send(());
+    }
+}
+
+/// Stores a permit if no task is waiting yet, so the next `notified().await` returns at once.
+#[cfg(feature = "tokio")]
+impl Notifier for Arc<tokio::sync::Notify> {
+    #[inline]
+    fn notify(self) {
+        self.notify_one()
+    }
+}
+
+struct NotifyNow<N>(N);
+
+impl<N: Notifier> Consume for NotifyNow<N> {
+    #[inline]
+    fn consume(self) {
+        self.0.notify()
+    }
+}
+
+/// A [`Consumer<T>`] which passes values on to the consumer `Q`, and signals the [`Notifier`]
+/// `N` once it is done, or just before it starts, with [`NotifyOnConsume::notify_before`].
+///
+/// When notifying afterwards, the notifier is signaled even if `Q` panics, so that waiters are
+/// never left blocked forever.
+#[derive(Clone, Debug)]
+pub struct NotifyOnConsume<Q, N> {
+    inner: Q,
+    notifier: N,
+    before: bool,
+}
+
+impl<Q, N> NotifyOnConsume<Q, N> {
+    /// Builds a consumer which consumes values with `inner`, then signals `notifier`.
+    #[inline]
+    pub const fn new(inner: Q, notifier: N) -> Self {
+        Self {
+            inner,
+            notifier,
+            before: false,
+        }
+    }
+
+    /// Signals the notifier before passing the value on, rather than after.
+    #[inline]
+    pub fn notify_before(self) -> Self {
+        Self {
+            before: true,
+            ..self
+        }
+    }
+}
+
+impl<T, Q: Consumer<T>, N: Notifier> Consumer<T> for NotifyOnConsume<Q, N> {
+    fn consume(self, other: T) {
+        if self.before {
+            self.notifier.notify();
+            self.inner.consume(other)
+        } else {
+            let _notify = ConsumeOnDrop::new(NotifyNow(self.notifier));
+            self.inner.consume(other)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Completion, NotifyOnConsume};
+    use crate::{Closure, WithConsumer};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn waits_until_consumed() {
+        let closed = AtomicBool::new(false);
+        let done = Completion::new();
+        let consumer = Closure(|_: u32| {
+            thread::sleep(Duration::from_millis(20));
+            closed.store(true, Ordering::SeqCst);
+        });
+        let guard = WithConsumer::new(5, NotifyOnConsume::new(consumer, done.clone()));
+        assert!(!done.wait_timeout(Duration::from_millis(1)));
+        thread::scope(|scope| {
+            scope.spawn(move || drop(guard));
+            done.wait();
+            assert!(closed.load(Ordering::SeqCst));
+        });
+        assert!(done.is_complete());
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn notifies_tokio_receiver() {
+        let (sender, receiver) = tokio::sync::oneshot::channel();
+        drop(WithConsumer::new(
+            (),
+            NotifyOnConsume::new(Closure(drop), sender).notify_before(),
+        ));
+        assert!(receiver.blocking_recv().is_ok());
+    }
+}
@@ -0,0 +1,195 @@
+use crate::{Consume, ConsumeOnDrop, Consumer, WithConsumer};
+
+/// Consumes every item of `items` in order. If consuming an item panics, the remaining items
+/// are still consumed, in order, while unwinding.
+pub(crate) fn consume_all<I>(items: I)
+where
+    I: IntoIterator,
+    I::Item: Consume,
+{
+    struct Rest<I: Iterator<Item: Consume>>(I);
+
+    impl<I: Iterator<Item: Consume>> Drop for Rest<I> {
+        fn drop(&mut self) {
+            // This only has work left to do if we are unwinding.
+            for item in &mut self.0 {
+                item.consume()
+            }
+        }
+    }
+
+    let mut rest = Rest(items.into_iter());
+    for item in rest.0.by_ref() {
+        item.consume()
+    }
+}
+
+/// A pair of values which are consumed in a fixed order: `first`, then `second`.
+///
+/// `second` is consumed even if consuming `first` panics, in which case it is consumed while
+/// unwinding. Nest [`Composed`] to spell out the order of any number of values. Tuples and arrays
+/// follow the same rules: their elements are consumed from first to last, and a panic while
+/// consuming one element doesn't prevent the later elements from being consumed.
+///
+/// Note that if a second consumption panics while unwinding from the first, the process aborts,
+/// just as for any other panic in a destructor during unwinding.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Composed<A, B> {
+    /// The value consumed first.
+    pub first: A,
+    /// The value consumed second.
+    pub second: B,
+}
+
+impl<A, B> Composed<A, B> {
+    /// Builds a [`Composed`] which consumes `first`, then `second`.
+    #[inline]
+    pub const fn new(first: A, second: B) -> Self {
+        Self { first, second }
+    }
+}
+
+impl<A: Consume, B: Consume> Consume for Composed<A, B> {
+    #[inline]
+    fn consume(self) {
+        let second = ConsumeOnDrop::new(self.second);
+        self.first.consume();
+        drop(second)
+    }
+}
+
+/// A guard over two values which are consumed in a fixed order. See [`Composed`].
+pub type ComposedGuard<A, B> = ConsumeOnDrop<Composed<A, B>>;
+
+/// A [`Consumer<(A, B)>`] which splits a pair and routes each part to its own consumer:
+/// `first` consumes the `A`, then `second` consumes the `B`.
+///
+/// As with [`Composed`], the `B` is consumed even if consuming the `A` panics, in which case it
+/// is consumed while unwinding. Nest [`SplitConsumer`] to route larger composite values.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SplitConsumer<QA, QB> {
+    /// The consumer for the first part.
+    pub first: QA,
+    /// The consumer for the second part.
+    pub second: QB,
+}
+
+impl<QA, QB> SplitConsumer<QA, QB> {
+    /// Builds a [`SplitConsumer`] which routes the first part to `first` and the second part to
+    /// `second`.
+    #[inline]
+    pub const fn new(first: QA, second: QB) -> Self {
+        Self { first, second }
+    }
+}
+
+impl<A, B, QA: Consumer<A>, QB: Consumer<B>> Consumer<(A, B)> for SplitConsumer<QA, QB> {
+    #[inline]
+    fn consume(self, (a, b): (A, B)) {
+        let second = WithConsumer::new(b, self.second);
+        self.first.consume(a);
+        drop(second)
+    }
+}
+
+/// Consumes every element in order. See [`Composed`] for what happens on panic.
+impl<T: Consume, const N: usize> Consume for [T; N] {
+    #[inline]
+    fn consume(self) {
+        consume_all(self)
+    }
+}
+
+macro_rules! tuple_consume {
+    ($first:ident) => {
+        /// Consumes the only element.
+        impl<$first: Consume> Consume for ($first,) {
+            #[inline]
+            fn consume(self) {
+                self.0.consume()
+            }
+        }
+    };
+    ($first:ident, $($rest:ident),+) => {
+        /// Consumes every element in order. See [`Composed`] for what happens on panic.
+        impl<$first: Consume, $($rest: Consume),+> Consume for ($first, $($rest),+) {
+            #[inline]
+            #[allow(non_snake_case)]
+            fn consume(self) {
+                let ($first, $($rest),+) = self;
+                Composed::new($first, ($($rest,)+)).consume()
+            }
+        }
+
+        tuple_consume!($($rest),+);
+    };
+}
+
+tuple_consume!(A, B, C, D, E, F, G, H, I, J, K, L);
+
+#[cfg(test)]
+mod tests {
+    use super::{Composed, ComposedGuard, SplitConsumer};
+    use crate::{Closure, ConsumeOnDrop, WithConsumer};
+    use alloc::vec::Vec;
+    use core::cell::{Cell, RefCell};
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    #[test]
+    fn composites_consume_in_order() {
+        let log = RefCell::new(Vec::new());
+        let log = &log;
+        let push = |i| Closure(move || log.borrow_mut().push(i));
+
+        drop(ConsumeOnDrop::new((push(0), push(1), push(2))));
+        drop(ComposedGuard::new(Composed::new(
+            push(3),
+            [push(4), push(5)],
+        )));
+        drop(ConsumeOnDrop::new((
+            Cell::new(push(6)),
+            RefCell::new(push(7)),
+        )));
+        assert_eq!(*log.borrow(), [0, 1, 2, 3, 4, 5, 6, 7]);
+    }
+
+    #[test]
+    fn split_consumer_routes_parts() {
+        let log = RefCell::new(Vec::new());
+        let log = &log;
+        let split = SplitConsumer::new(
+            Closure(|socket: u16| log.borrow_mut().push(std::format!("close {}", socket))),
+            Closure(|path: &str| log.borrow_mut().push(std::format!("unlink {}", path))),
+        );
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            let _guard = WithConsumer::new((8080, "/tmp/app.sock"), split);
+            panic!("request failed");
+        }));
+        assert!(result.is_err());
+        assert_eq!(*log.borrow(), ["close 8080", "unlink /tmp/app.sock"]);
+    }
+
+    #[test]
+    fn composites_survive_panics() {
+        let log = RefCell::new(Vec::new());
+        let log = &log;
+        let push = |i| {
+            Closure(move || {
+                log.borrow_mut().push(i);
+                if i % 2 == 1 {
+                    panic!("failed to consume {}", i);
+                }
+            })
+        };
+
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            drop(ConsumeOnDrop::new([push(0), push(1), push(2)]))
+        }));
+        assert!(result.is_err());
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            drop(ConsumeOnDrop::new((push(3), push(4), push(6))))
+        }));
+        assert!(result.is_err());
+        assert_eq!(*log.borrow(), [0, 1, 2, 3, 4, 6]);
+    }
+}
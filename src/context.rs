@@ -0,0 +1,133 @@
+use crate::{Closure, Consumer, WithConsumer};
+use core::ops::{Deref, DerefMut};
+
+/// A type implementing [`ContextConsume<C>`] can be consumed given shared access to a context of
+/// type `C`, such as a graphics device which must be passed to every `destroy` call.
+pub trait ContextConsume<C: ?Sized> {
+    /// Consumes `self` using `ctx`.
+    fn consume(self, ctx: &C);
+}
+
+impl<C: ?Sized, F: FnOnce(&C)> ContextConsume<C> for Closure<F> {
+    #[inline]
+    fn consume(self, ctx: &C) {
+        (self.0)(ctx)
+    }
+}
+
+/// A [`Consumer`] which consumes values with a [`ContextConsume`] impl using the context behind
+/// the handle `H`, e.g. a `&C` or an `Arc<C>`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WithContext<H>(pub H);
+
+impl<T, H> Consumer<T> for WithContext<H>
+where
+    H: Deref,
+    T: ContextConsume<H::Target>,
+{
+    #[inline]
+    fn consume(self, other: T) {
+        other.consume(&self.0)
+    }
+}
+
+/// A `T` along with a handle `H` to the context it needs to be consumed, such as a `&Device` or
+/// an `Arc<Device>`. When a [`ScopedGuard`] is dropped, the `T` is consumed using the context.
+#[derive(Debug)]
+pub struct ScopedGuard<T, H>
+where
+    H: Deref,
+    T: ContextConsume<H::Target>,
+{
+    inner: WithConsumer<T, WithContext<H>>,
+}
+
+impl<T, H> ScopedGuard<T, H>
+where
+    H: Deref,
+    T: ContextConsume<H::Target>,
+{
+    /// Guards `value`, which will be consumed using the context behind `handle`.
+    #[inline]
+    pub const fn new(value: T, handle: H) -> Self {
+        Self {
+            inner: WithConsumer::new(value, WithContext(handle)),
+        }
+    }
+
+    /// Provides a reference to the context.
+    #[inline]
+    pub fn context(x: &Self) -> &H::Target {
+        &WithConsumer::as_refs(&x.inner).1 .0
+    }
+
+    /// Extracts the `T` and the context handle without consuming the `T`.
+    #[inline]
+    pub fn into_parts(x: Self) -> (T, H) {
+        let (value, WithContext(handle)) = WithConsumer::into_pair(x.inner);
+        (value, handle)
+    }
+
+    /// Extracts the `T` without consuming it, dropping the context handle.
+    #[inline]
+    pub fn into_inner(x: Self) -> T {
+        WithConsumer::into_inner(x.inner)
+    }
+}
+
+impl<T, H> Deref for ScopedGuard<T, H>
+where
+    H: Deref,
+    T: ContextConsume<H::Target>,
+{
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        &self.inner
+    }
+}
+
+impl<T, H> DerefMut for ScopedGuard<T, H>
+where
+    H: Deref,
+    T: ContextConsume<H::Target>,
+{
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ContextConsume, ScopedGuard};
+    use alloc::vec::Vec;
+    use core::cell::RefCell;
+
+    #[test]
+    fn consumes_with_context() {
+        struct Device {
+            destroyed: RefCell<Vec<u32>>,
+        }
+
+        struct Buffer(u32);
+
+        impl ContextConsume<Device> for Buffer {
+            fn consume(self, device: &Device) {
+                device.destroyed.borrow_mut().push(self.0)
+            }
+        }
+
+        let device = Device {
+            destroyed: RefCell::new(Vec::new()),
+        };
+        {
+            let _first = ScopedGuard::new(Buffer(1), &device);
+            let second = ScopedGuard::new(Buffer(2), &device);
+            assert_eq!(second.0, 2);
+            assert!(core::ptr::eq(ScopedGuard::context(&second), &device));
+        }
+        assert_eq!(*device.destroyed.borrow(), [2, 1]);
+    }
+}
@@ -0,0 +1,108 @@
+use crate::{Consumer, WithConsumer};
+use alloc::rc::Rc;
+use core::fmt;
+use core::ops::Deref;
+
+/// A [`Consumer<Rc<T>>`] which runs a cycle-breaking routine on the value before releasing the
+/// reference. See [`BreakCycleOnDrop`].
+#[derive(Clone, Copy, Debug)]
+pub struct BreakCycle<F>(pub F);
+
+impl<T: ?Sized, F: FnOnce(&T)> Consumer<Rc<T>> for BreakCycle<F> {
+    #[inline]
+    fn consume(self, other: Rc<T>) {
+        (self.0)(&other);
+        drop(other)
+    }
+}
+
+/// An owning handle into a graph of [`Rc`]s, which breaks the graph's cycles when dropped so
+/// that it can actually be deallocated.
+///
+/// Reference-counted graphs with back-references, such as widget trees whose children point
+/// to their parents, are never freed if each node holds a strong reference to another. Holding
+/// the root through this guard runs `break_cycle` on it when the guard is dropped, which should
+/// clear the references which form cycles, e.g. by emptying each node's list of children.
+/// The routine runs even if other handles to the root remain, since the owner is going away.
+pub struct BreakCycleOnDrop<T: ?Sized, F: FnOnce(&T) = fn(&T)> {
+    inner: WithConsumer<Rc<T>, BreakCycle<F>>,
+}
+
+impl<T: ?Sized, F: FnOnce(&T)> BreakCycleOnDrop<T, F> {
+    /// Holds `root`, and runs `break_cycle` on it when dropped.
+    #[inline]
+    pub const fn new(root: Rc<T>, break_cycle: F) -> Self {
+        Self {
+            inner: WithConsumer::new(root, BreakCycle(break_cycle)),
+        }
+    }
+
+    /// Returns another strong reference to the root, which does not break cycles when dropped.
+    #[inline]
+    pub fn root(x: &Self) -> Rc<T> {
+        Rc::clone(&x.inner)
+    }
+
+    /// Extracts the root without breaking its cycles.
+    #[inline]
+    pub fn into_inner(x: Self) -> Rc<T> {
+        WithConsumer::into_inner(x.inner)
+    }
+}
+
+impl<T: ?Sized + fmt::Debug, F: FnOnce(&T)> fmt::Debug for BreakCycleOnDrop<T, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("BreakCycleOnDrop").field(&*self.inner).finish()
+    }
+}
+
+impl<T: ?Sized, F: FnOnce(&T)> Deref for BreakCycleOnDrop<T, F> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        &self.inner
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BreakCycleOnDrop;
+    use alloc::rc::{Rc, Weak};
+    use alloc::vec::Vec;
+    use core::cell::RefCell;
+
+    struct Widget {
+        parent: RefCell<Option<Rc<Widget>>>,
+        children: RefCell<Vec<Rc<Widget>>>,
+    }
+
+    fn widget() -> Rc<Widget> {
+        Rc::new(Widget {
+            parent: RefCell::new(None),
+            children: RefCell::new(Vec::new()),
+        })
+    }
+
+    fn clear(widget: &Widget) {
+        for child in widget.children.take() {
+            child.parent.take();
+            clear(&child);
+        }
+    }
+
+    #[test]
+    fn graph_is_freed() {
+        let window = widget();
+        let button = widget();
+        *button.parent.borrow_mut() = Some(window.clone());
+        window.children.borrow_mut().push(button.clone());
+        let (window_ref, button_ref): (Weak<_>, Weak<_>) =
+            (Rc::downgrade(&window), Rc::downgrade(&button));
+        drop(button);
+
+        drop(BreakCycleOnDrop::new(window, clear));
+        assert!(window_ref.upgrade().is_none());
+        assert!(button_ref.upgrade().is_none());
+    }
+}
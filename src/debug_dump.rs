@@ -0,0 +1,112 @@
+use crate::{Closure, Consumer};
+use alloc::borrow::Cow;
+use core::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Where a [`DebugDump`] writes the values it consumes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum DumpTarget {
+    /// Standard error.
+    #[default]
+    Stderr,
+    /// The `log` facade, with the given target and level.
+    #[cfg(feature = "log")]
+    Log {
+        /// The `log` target, such as `"teardown"`.
+        target: &'static str,
+        /// The level the records are logged at.
+        level: log::Level,
+    },
+}
+
+/// A [`Consumer<T>`] which writes the value's [`Debug`](fmt::Debug) representation, along with
+/// its label, if any, and a timestamp, before passing it on to the consumer `Q`.
+///
+/// This shows exactly what is being thrown away while debugging an incident, by changing only
+/// the consumer of a guard. The timestamp is the time since the Unix epoch, in seconds.
+#[derive(Clone, Debug)]
+pub struct DebugDump<Q> {
+    inner: Q,
+    label: Option<Cow<'static, str>>,
+    target: DumpTarget,
+}
+
+impl<Q> DebugDump<Q> {
+    /// Builds a consumer which dumps values to standard error, then consumes them with `inner`.
+    #[inline]
+    pub const fn new(inner: Q) -> Self {
+        Self {
+            inner,
+            label: None,
+            target: DumpTarget::Stderr,
+        }
+    }
+
+    /// Sets the label written along with each value.
+    #[inline]
+    pub fn with_label(self, label: impl Into<Cow<'static, str>>) -> Self {
+        Self {
+            label: Some(label.into()),
+            ..self
+        }
+    }
+
+    /// Sets where values are written.
+    #[inline]
+    pub fn with_target(self, target: DumpTarget) -> Self {
+        Self { target, ..self }
+    }
+}
+
+impl<T> DebugDump<Closure<fn(T)>> {
+    /// Builds a consumer which dumps values to standard error, then drops them.
+    #[inline]
+    pub fn dump_only() -> Self {
+        Self::new(Closure(drop::<T>))
+    }
+}
+
+impl<T: fmt::Debug, Q: Consumer<T>> Consumer<T> for DebugDump<Q> {
+    fn consume(self, other: T) {
+        let since_epoch = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let (secs, millis) = (since_epoch.as_secs(), since_epoch.subsec_millis());
+        let label = self.label.as_deref().unwrap_or("guard");
+        match self.target {
+            DumpTarget::Stderr => {
+                std::eprintln!("[{}.{:03}] {} consumed: {:?}", secs, millis, label, other)
+            }
+            #[cfg(feature = "log")]
+            DumpTarget::Log { target, level } => {
+                log::log!(
+                    target: target,
+                    level,
+                    "[{}.{:03}] {} consumed: {:?}",
+                    secs,
+                    millis,
+                    label,
+                    other
+                )
+            }
+        }
+        self.inner.consume(other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DebugDump;
+    use crate::{Closure, WithConsumer};
+
+    #[test]
+    fn dumps_then_delegates() {
+        let mut closed = None;
+        let dump = DebugDump::new(Closure(|id: u32| closed = Some(id))).with_label("session");
+        drop(WithConsumer::new(42, dump));
+        assert_eq!(closed, Some(42));
+
+        drop(WithConsumer::new("scratch", DebugDump::dump_only()));
+    }
+}
@@ -0,0 +1,155 @@
+use crate::{Consume, ConsumeOnDrop, Consumer, WithConsumer};
+use alloc::collections::VecDeque;
+use core::cell::{Cell, RefCell};
+
+/// A queue which delays consumption by a fixed number of frames, for resources which must
+/// outlive the work still in flight, such as GPU buffers referenced by command buffers which
+/// have been submitted but not yet executed.
+///
+/// Values are added with [`DeferredDestroyQueue::defer`], or by dropping a [`DeferredGuard`].
+/// Once per frame, the application calls [`DeferredDestroyQueue::collect`] with the index of the
+/// current frame, which consumes every value deferred at least `delay` frames earlier. Values
+/// are consumed in the order they were deferred. Any values still queued when the queue is
+/// dropped are consumed then, so drop the queue only once all in-flight work has finished.
+#[derive(Debug)]
+pub struct DeferredDestroyQueue<T: Consume> {
+    delay: u64,
+    frame: Cell<u64>,
+    pending: RefCell<VecDeque<(u64, ConsumeOnDrop<T>)>>,
+}
+
+impl<T: Consume> DeferredDestroyQueue<T> {
+    /// Builds an empty queue which holds values for `delay` frames, which is typically the
+    /// number of frames in flight.
+    #[inline]
+    pub const fn new(delay: u64) -> Self {
+        Self {
+            delay,
+            frame: Cell::new(0),
+            pending: RefCell::new(VecDeque::new()),
+        }
+    }
+
+    /// The number of frames values are held for.
+    #[inline]
+    pub fn delay(&self) -> u64 {
+        self.delay
+    }
+
+    /// The frame index passed to the latest call to [`DeferredDestroyQueue::collect`], or 0.
+    #[inline]
+    pub fn frame(&self) -> u64 {
+        self.frame.get()
+    }
+
+    /// Queues `value` to be consumed `delay` frames after the current frame.
+    pub fn defer(&self, value: T) {
+        self.pending
+            .borrow_mut()
+            .push_back((self.frame.get(), ConsumeOnDrop::new(value)))
+    }
+
+    /// Guards `value` so that it is deferred to this queue when dropped.
+    #[inline]
+    pub fn guard(&self, value: T) -> DeferredGuard<'_, T> {
+        WithConsumer::new(value, Defer(self))
+    }
+
+    /// Advances to `frame_index`, consuming every value deferred at or before
+    /// `frame_index - delay`. Consumption may itself defer more values.
+    pub fn collect(&self, frame_index: u64) {
+        self.frame.set(frame_index);
+        loop {
+            let mut pending = self.pending.borrow_mut();
+            match pending.front() {
+                Some(&(deferred_at, _))
+                    if deferred_at.saturating_add(self.delay) <= frame_index =>
+                {
+                    let (_, value) = pending.pop_front().unwrap();
+                    drop(pending);
+                    drop(value);
+                }
+                _ => break,
+            }
+        }
+    }
+
+    /// Consumes every queued value now, regardless of its age, e.g. once the device is idle.
+    pub fn flush(&self) {
+        loop {
+            let value = self.pending.borrow_mut().pop_front();
+            match value {
+                Some((_, value)) => drop(value),
+                None => break,
+            }
+        }
+    }
+
+    /// The number of values waiting to be consumed.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.pending.borrow().len()
+    }
+
+    /// Whether no values are waiting to be consumed.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.pending.borrow().is_empty()
+    }
+}
+
+/// A [`Consumer`] which defers values to a [`DeferredDestroyQueue`].
+#[derive(Debug)]
+pub struct Defer<'q, T: Consume>(pub &'q DeferredDestroyQueue<T>);
+
+impl<'q, T: Consume> Clone for Defer<'q, T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'q, T: Consume> Copy for Defer<'q, T> {}
+
+impl<'q, T: Consume> Consumer<T> for Defer<'q, T> {
+    #[inline]
+    fn consume(self, other: T) {
+        self.0.defer(other)
+    }
+}
+
+/// A value which is deferred to a [`DeferredDestroyQueue`] when dropped.
+pub type DeferredGuard<'q, T> = WithConsumer<T, Defer<'q, T>>;
+
+#[cfg(test)]
+mod tests {
+    use super::DeferredDestroyQueue;
+    use crate::Consume;
+    use alloc::vec::Vec;
+    use core::cell::RefCell;
+
+    struct Buffer<'a>(u32, &'a RefCell<Vec<u32>>);
+
+    impl Consume for Buffer<'_> {
+        fn consume(self) {
+            self.1.borrow_mut().push(self.0)
+        }
+    }
+
+    #[test]
+    fn consumes_after_delay() {
+        let destroyed = RefCell::new(Vec::new());
+        let queue = DeferredDestroyQueue::new(2);
+        drop(queue.guard(Buffer(1, &destroyed)));
+        queue.collect(1);
+        queue.defer(Buffer(2, &destroyed));
+        queue.collect(2);
+        assert_eq!(*destroyed.borrow(), [1]);
+        queue.collect(3);
+        assert_eq!(*destroyed.borrow(), [1, 2]);
+
+        queue.defer(Buffer(3, &destroyed));
+        drop(queue);
+        assert_eq!(*destroyed.borrow(), [1, 2, 3]);
+    }
+}
@@ -0,0 +1,246 @@
+use crate::composite::consume_all;
+use crate::Consume;
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+use core::mem::{self, MaybeUninit};
+use core::ptr;
+
+/// The number of cleanups a [`DropBag`] holds before it allocates.
+pub const DROP_BAG_INLINE_CAPACITY: usize = 4;
+
+/// The storage of a cleanup small enough not to need a box of its own.
+type Slot = [MaybeUninit<usize>; 3];
+
+/// A type-erased cleanup, stored inline when it fits in a [`Slot`].
+enum Cleanup<'a> {
+    Inline {
+        slot: Slot,
+        consume: unsafe fn(*mut Slot),
+        /// Keeps the auto traits and the variance of the boxed representation.
+        _marker: PhantomData<Box<dyn FnOnce() + 'a>>,
+    },
+    Boxed(Box<dyn FnOnce() + 'a>),
+}
+
+impl<'a> Cleanup<'a> {
+    fn new<T: Consume + 'a>(value: T) -> Self {
+        /// Safety: `slot` must hold a `T`, which must not be used afterwards.
+        unsafe fn consume<T: Consume>(slot: *mut Slot) {
+            unsafe { ptr::read(slot.cast::<T>()) }.consume()
+        }
+
+        if mem::size_of::<T>() <= mem::size_of::<Slot>()
+            && mem::align_of::<T>() <= mem::align_of::<Slot>()
+        {
+            let mut slot: Slot = [MaybeUninit::uninit(); 3];
+            // Safety: the slot is large enough and sufficiently aligned for a `T`.
+            unsafe { ptr::write(slot.as_mut_ptr().cast::<T>(), value) };
+            Cleanup::Inline {
+                slot,
+                consume: consume::<T>,
+                _marker: PhantomData,
+            }
+        } else {
+            Cleanup::Boxed(Box::new(move || value.consume()))
+        }
+    }
+}
+
+impl Consume for Cleanup<'_> {
+    #[inline]
+    fn consume(self) {
+        match self {
+            Cleanup::Inline {
+                mut slot, consume, ..
+            } => {
+                // Safety: `consume` was built for the type written to `slot`, and `slot` is
+                // consumed by value, so it is never read again.
+                unsafe { consume(&mut slot) }
+            }
+            Cleanup::Boxed(cleanup) => cleanup(),
+        }
+    }
+}
+
+/// Cleanups with their priorities, sorted by ascending priority, then by registration order.
+/// The first [`DROP_BAG_INLINE_CAPACITY`] live inline, and all of them move to the heap once
+/// there are more.
+enum Cleanups<'a> {
+    Inline {
+        entries: [Option<(i32, Cleanup<'a>)>; DROP_BAG_INLINE_CAPACITY],
+        len: usize,
+    },
+    Spilled(Vec<(i32, Cleanup<'a>)>),
+}
+
+impl<'a> Cleanups<'a> {
+    fn len(&self) -> usize {
+        match self {
+            Cleanups::Inline { len, .. } => *len,
+            Cleanups::Spilled(entries) => entries.len(),
+        }
+    }
+
+    fn insert(&mut self, priority: i32, cleanup: Cleanup<'a>) {
+        match self {
+            Cleanups::Inline { entries, len } if *len < DROP_BAG_INLINE_CAPACITY => {
+                let live = &mut entries[..=*len];
+                let index = live[..*len]
+                    .iter()
+                    .position(|entry| matches!(entry, Some((p, _)) if *p > priority))
+                    .unwrap_or(*len);
+                live[*len] = Some((priority, cleanup));
+                live[index..].rotate_right(1);
+                *len += 1;
+            }
+            Cleanups::Inline { entries, .. } => {
+                let mut spilled = Vec::with_capacity(2 * DROP_BAG_INLINE_CAPACITY);
+                spilled.extend(entries.iter_mut().filter_map(Option::take));
+                *self = Cleanups::Spilled(spilled);
+                self.insert(priority, cleanup)
+            }
+            Cleanups::Spilled(entries) => {
+                let index = entries.partition_point(|&(p, _)| p <= priority);
+                entries.insert(index, (priority, cleanup));
+            }
+        }
+    }
+
+    /// Removes the cleanup which runs first.
+    fn pop(&mut self) -> Option<Cleanup<'a>> {
+        let (_, cleanup) = match self {
+            Cleanups::Inline { len: 0, .. } => None,
+            Cleanups::Inline { entries, len } => {
+                *len -= 1;
+                entries[*len].take()
+            }
+            Cleanups::Spilled(entries) => entries.pop(),
+        }?;
+        Some(cleanup)
+    }
+}
+
+/// A bag of heterogeneous cleanups which are all consumed when the bag is dropped.
+///
+/// Cleanups run in order of descending priority. Cleanups with equal priorities run in reverse
+/// registration order, just like local variables going out of scope. [`DropBag::push`] uses
+/// priority 0, so a bag without explicit priorities is purely last in, first out.
+///
+/// If a cleanup panics, the remaining cleanups are still consumed, in the same order, while
+/// unwinding.
+///
+/// The first [`DROP_BAG_INLINE_CAPACITY`] cleanups are stored in the bag itself, and cleanups no
+/// larger than three pointers are stored without a box, so a bag with a few small cleanups never
+/// allocates.
+pub struct DropBag<'a> {
+    cleanups: Cleanups<'a>,
+}
+
+impl<'a> DropBag<'a> {
+    /// Builds an empty [`DropBag`].
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            cleanups: Cleanups::Inline {
+                entries: [None, None, None, None],
+                len: 0,
+            },
+        }
+    }
+
+    /// Registers `value` to be consumed with priority 0.
+    #[inline]
+    pub fn push(&mut self, value: impl Consume + 'a) {
+        self.push_with_priority(0, value)
+    }
+
+    /// Registers `value` to be consumed before every cleanup with a lower priority, and after
+    /// every cleanup with a higher one.
+    pub fn push_with_priority(&mut self, priority: i32, value: impl Consume + 'a) {
+        self.cleanups.insert(priority, Cleanup::new(value))
+    }
+
+    /// The number of cleanups waiting to run.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.cleanups.len()
+    }
+
+    /// Whether there are no cleanups waiting to run.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Whether the cleanups have outgrown the inline buffer and moved to the heap.
+    #[inline]
+    pub fn spilled(&self) -> bool {
+        matches!(self.cleanups, Cleanups::Spilled(_))
+    }
+
+    /// Runs every cleanup now. This is equivalent to dropping `bag`.
+    #[inline]
+    pub fn run(bag: Self) {
+        drop(bag)
+    }
+}
+
+impl Default for DropBag<'_> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for DropBag<'_> {
+    fn drop(&mut self) {
+        let cleanups = &mut self.cleanups;
+        consume_all(core::iter::from_fn(|| cleanups.pop()))
+    }
+}
+
+impl Consume for DropBag<'_> {
+    #[inline]
+    fn consume(self) {
+        drop(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DropBag;
+    use crate::Closure;
+    use alloc::vec::Vec;
+    use core::cell::RefCell;
+
+    #[test]
+    fn priorities_then_lifo() {
+        let log = RefCell::new(Vec::new());
+        let log = &log;
+        let record = |name| Closure(move || log.borrow_mut().push(name));
+
+        let mut bag = DropBag::new();
+        bag.push(record("a"));
+        bag.push_with_priority(10, record("database"));
+        bag.push(record("b"));
+        bag.push_with_priority(-1, record("logger"));
+        assert_eq!(bag.len(), 4);
+        assert!(!bag.spilled());
+        bag.push_with_priority(10, record("cache"));
+        assert_eq!(bag.len(), 5);
+        assert!(bag.spilled());
+        DropBag::run(bag);
+        assert_eq!(*log.borrow(), ["cache", "database", "b", "a", "logger"]);
+
+        let mut bag = DropBag::new();
+        bag.push(record("small"));
+        let large = [7u64; 8];
+        bag.push(Closure(move || {
+            log.borrow_mut()
+                .push(if large[7] == 7 { "large" } else { "corrupt" })
+        }));
+        drop(bag);
+        assert_eq!(log.borrow()[5..], ["large", "small"]);
+    }
+}
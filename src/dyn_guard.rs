@@ -0,0 +1,114 @@
+use crate::{Closure, Consume, ConsumeOnDrop};
+use alloc::boxed::Box;
+use core::fmt;
+
+type Cleanup<'a> = Closure<Box<dyn FnOnce() + 'a>>;
+
+/// A single boxed cleanup, which may borrow local state for `'a`, and runs when the guard is
+/// dropped.
+///
+/// Unlike a [`ConsumeOnDrop<Closure<F>>`], the type of a [`DynGuard`] does not name the type of
+/// its closure, so it can be stored in a struct field or returned from a function, e.g. from a
+/// function which chooses between several cleanups at run time.
+pub struct DynGuard<'a> {
+    inner: ConsumeOnDrop<Cleanup<'a>>,
+}
+
+impl<'a> DynGuard<'a> {
+    /// Builds a guard which runs `cleanup` when dropped.
+    #[inline]
+    pub fn new(cleanup: impl FnOnce() + 'a) -> Self {
+        Self {
+            inner: ConsumeOnDrop::new(Closure(Box::new(cleanup))),
+        }
+    }
+
+    /// Builds a guard which consumes `value` when dropped.
+    #[inline]
+    pub fn from_consume(value: impl Consume + 'a) -> Self {
+        Self::new(move || value.consume())
+    }
+
+    /// Erases the type of an existing guard, such as a [`ConsumeOnDrop`] or a
+    /// [`WithConsumer`](crate::WithConsumer), which does its cleanup when dropped.
+    #[inline]
+    pub fn from_guard<G: 'a>(guard: G) -> Self {
+        Self::new(move || drop(guard))
+    }
+
+    /// Extracts the cleanup without running it.
+    #[inline]
+    pub fn into_inner(x: Self) -> Box<dyn FnOnce() + 'a> {
+        ConsumeOnDrop::into_inner(x.inner).0
+    }
+
+    /// Drops the cleanup without running it.
+    #[inline]
+    pub fn cancel(x: Self) {
+        drop(Self::into_inner(x))
+    }
+
+    /// Runs the cleanup now. This is equivalent to dropping `x`.
+    #[inline]
+    pub fn run(x: Self) {
+        drop(x)
+    }
+}
+
+/// A guard with nothing to clean up.
+impl Default for DynGuard<'_> {
+    #[inline]
+    fn default() -> Self {
+        Self::new(|| {})
+    }
+}
+
+impl Consume for DynGuard<'_> {
+    #[inline]
+    fn consume(self) {
+        drop(self)
+    }
+}
+
+impl fmt::Debug for DynGuard<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DynGuard").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DynGuard;
+    use crate::{Closure, ConsumeOnDrop};
+    use alloc::vec::Vec;
+    use core::cell::RefCell;
+
+    struct Session<'log> {
+        _cleanup: DynGuard<'log>,
+    }
+
+    fn open<'log>(log: &'log RefCell<Vec<&'static str>>, pooled: bool) -> Session<'log> {
+        let cleanup = if pooled {
+            DynGuard::new(move || log.borrow_mut().push("returned"))
+        } else {
+            DynGuard::from_guard(ConsumeOnDrop::new(Closure(move || {
+                log.borrow_mut().push("closed")
+            })))
+        };
+        Session { _cleanup: cleanup }
+    }
+
+    #[test]
+    fn erased_cleanups_borrow_locals() {
+        let log = RefCell::new(Vec::new());
+        drop(open(&log, true));
+        drop(open(&log, false));
+        DynGuard::run(DynGuard::from_consume(Closure(|| {
+            log.borrow_mut().push("done")
+        })));
+        drop(DynGuard::default());
+        let _ = DynGuard::into_inner(DynGuard::new(|| unreachable!()));
+        DynGuard::cancel(DynGuard::new(|| unreachable!()));
+        assert_eq!(*log.borrow(), ["returned", "closed", "done"]);
+    }
+}
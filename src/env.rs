@@ -0,0 +1,104 @@
+use crate::{Consume, ConsumeOnDrop};
+use std::env;
+use std::ffi::{OsStr, OsString};
+
+#[derive(Debug)]
+struct RestoreVar {
+    key: OsString,
+    prior: Option<OsString>,
+}
+
+impl Consume for RestoreVar {
+    #[inline]
+    fn consume(self) {
+        match self.prior {
+            Some(value) => env::set_var(self.key, value),
+            None => env::remove_var(self.key),
+        }
+    }
+}
+
+/// Sets or removes an environment variable for a scope, restoring its prior value (or absence)
+/// when dropped, including while unwinding.
+///
+/// The environment is shared by the whole process, so guards for the same variable should be
+/// dropped in the reverse order they were created, and tests using them should not run
+/// concurrently with other code reading that variable.
+#[derive(Debug)]
+#[must_use = "the variable is restored as soon as the guard is dropped"]
+pub struct EnvGuard {
+    inner: ConsumeOnDrop<RestoreVar>,
+}
+
+impl EnvGuard {
+    fn record(key: &OsStr) -> Self {
+        Self {
+            inner: ConsumeOnDrop::new(RestoreVar {
+                key: key.to_os_string(),
+                prior: env::var_os(key),
+            }),
+        }
+    }
+
+    /// Sets the variable `key` to `value` until the guard is dropped.
+    ///
+    /// Panics under the same conditions as [`env::set_var`].
+    pub fn set(key: impl AsRef<OsStr>, value: impl AsRef<OsStr>) -> Self {
+        let guard = Self::record(key.as_ref());
+        env::set_var(key, value);
+        guard
+    }
+
+    /// Removes the variable `key` until the guard is dropped.
+    ///
+    /// Panics under the same conditions as [`env::remove_var`].
+    pub fn remove(key: impl AsRef<OsStr>) -> Self {
+        let guard = Self::record(key.as_ref());
+        env::remove_var(key);
+        guard
+    }
+
+    /// The name of the guarded variable.
+    #[inline]
+    pub fn key(&self) -> &OsStr {
+        &self.inner.key
+    }
+
+    /// The value the variable had before the guard was created, which will be restored.
+    #[inline]
+    pub fn prior(&self) -> Option<&OsStr> {
+        self.inner.prior.as_deref()
+    }
+
+    /// Keeps the current value of the variable, forgetting the prior one.
+    #[inline]
+    pub fn keep(guard: Self) {
+        ConsumeOnDrop::into_inner(guard.inner);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::EnvGuard;
+    use std::env;
+
+    #[test]
+    fn restores_prior_value() {
+        const KEY: &str = "CONSUME_ON_DROP_ENV_GUARD_TEST";
+        assert_eq!(env::var_os(KEY), None);
+        {
+            let _set = EnvGuard::set(KEY, "outer");
+            {
+                let removed = EnvGuard::remove(KEY);
+                assert_eq!(removed.prior(), Some("outer".as_ref()));
+                assert_eq!(env::var_os(KEY), None);
+            }
+            assert_eq!(env::var(KEY).as_deref(), Ok("outer"));
+        }
+        assert_eq!(env::var_os(KEY), None);
+
+        EnvGuard::keep(EnvGuard::set(KEY, "kept"));
+        assert_eq!(env::var(KEY).as_deref(), Ok("kept"));
+        env::remove_var(KEY);
+    }
+}
@@ -0,0 +1,45 @@
+use crate::{Consume, Consumer, WithConsumer};
+use crossbeam_epoch::Guard;
+
+/// A [`Consumer`] which defers consumption with [`Guard::defer`], so that a value removed from
+/// a lock-free structure is only consumed once every thread which was pinned at the time has
+/// left its epoch, and can therefore no longer be reading it.
+///
+/// The value must be [`Send`] and `'static`, since it may be consumed on another thread at an
+/// arbitrary later point. When the guard is [`unprotected`](crossbeam_epoch::unprotected), the
+/// value is consumed immediately.
+#[derive(Debug, Clone, Copy)]
+pub struct DeferToEpoch<'g>(pub &'g Guard);
+
+impl<T: Consume + Send + 'static> Consumer<T> for DeferToEpoch<'_> {
+    #[inline]
+    fn consume(self, other: T) {
+        self.0.defer(move || other.consume())
+    }
+}
+
+/// A value which is handed to the epoch-based collector for consumption when dropped.
+/// See [`DeferToEpoch`].
+pub type EpochGuard<'g, T> = WithConsumer<T, DeferToEpoch<'g>>;
+
+#[cfg(test)]
+mod tests {
+    use super::{DeferToEpoch, EpochGuard};
+    use crate::Closure;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn unprotected_consumes_immediately() {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        // SAFETY: no other thread accesses the value.
+        let guard = unsafe { crossbeam_epoch::unprotected() };
+        let value: EpochGuard<'_, _> = EpochGuard::new(
+            Closure(|| {
+                COUNT.fetch_add(1, Ordering::Relaxed);
+            }),
+            DeferToEpoch(guard),
+        );
+        drop(value);
+        assert_eq!(COUNT.load(Ordering::Relaxed), 1);
+    }
+}
@@ -0,0 +1,144 @@
+use crate::{Consumer, WithConsumer};
+use alloc::boxed::Box;
+use core::any::{type_name, Any};
+use core::fmt;
+
+/// Object-safe access to a boxed [`WithConsumer`] whose types have been forgotten.
+trait ErasedWithConsumer {
+    fn value(&self) -> &dyn Any;
+    fn value_mut(&mut self) -> &mut dyn Any;
+    fn value_type_name(&self) -> &'static str;
+    fn as_any(&self) -> &dyn Any;
+    fn into_any(self: Box<Self>) -> Box<dyn Any>;
+}
+
+impl<T: 'static, Q: Consumer<T> + 'static> ErasedWithConsumer for WithConsumer<T, Q> {
+    fn value(&self) -> &dyn Any {
+        WithConsumer::as_refs(self).0
+    }
+
+    fn value_mut(&mut self) -> &mut dyn Any {
+        WithConsumer::as_muts(self).0
+    }
+
+    fn value_type_name(&self) -> &'static str {
+        type_name::<T>()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        self
+    }
+}
+
+/// A [`WithConsumer`] whose value and consumer types have been erased, built with
+/// [`WithConsumer::erase`].
+///
+/// Guards of different types can be stored side by side as [`ErasedGuard`]s, e.g. in a `Vec` or a
+/// map. The value is still consumed by its original consumer when the [`ErasedGuard`] is dropped,
+/// and it can be accessed by downcasting to its original type.
+pub struct ErasedGuard {
+    inner: Box<dyn ErasedWithConsumer>,
+}
+
+impl ErasedGuard {
+    /// Whether the wrapped value is a `T`.
+    #[inline]
+    pub fn is<T: 'static>(&self) -> bool {
+        self.inner.value().is::<T>()
+    }
+
+    /// Returns a reference to the wrapped value if it is a `T`.
+    #[inline]
+    pub fn downcast_ref<T: 'static>(&self) -> Option<&T> {
+        self.inner.value().downcast_ref()
+    }
+
+    /// Returns a mutable reference to the wrapped value if it is a `T`.
+    #[inline]
+    pub fn downcast_mut<T: 'static>(&mut self) -> Option<&mut T> {
+        self.inner.value_mut().downcast_mut()
+    }
+
+    /// Recovers the original [`WithConsumer<T, Q>`], or returns the guard unchanged if it
+    /// wraps some other type.
+    pub fn downcast<T: 'static, Q: Consumer<T> + 'static>(
+        guard: Self,
+    ) -> Result<WithConsumer<T, Q>, Self> {
+        if guard.inner.as_any().is::<WithConsumer<T, Q>>() {
+            Ok(*guard.inner.into_any().downcast().unwrap())
+        } else {
+            Err(guard)
+        }
+    }
+
+    /// The name of the type of the wrapped value, for diagnostics.
+    #[inline]
+    pub fn value_type_name(&self) -> &'static str {
+        self.inner.value_type_name()
+    }
+}
+
+impl fmt::Debug for ErasedGuard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ErasedGuard")
+            .field("value_type", &self.value_type_name())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T: 'static, Q: Consumer<T> + 'static> WithConsumer<T, Q> {
+    /// Erases the value and consumer types of `x`, so that it can be stored alongside guards of
+    /// other types. The value stays guarded throughout.
+    #[inline]
+    pub fn erase(x: Self) -> ErasedGuard {
+        ErasedGuard { inner: Box::new(x) }
+    }
+}
+
+impl<T: 'static, Q: Consumer<T> + 'static> From<WithConsumer<T, Q>> for ErasedGuard {
+    #[inline]
+    fn from(x: WithConsumer<T, Q>) -> Self {
+        WithConsumer::erase(x)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ErasedGuard;
+    use crate::{Closure, WithConsumer};
+    use alloc::string::String;
+    use alloc::vec::Vec;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    static CONSUMED: AtomicUsize = AtomicUsize::new(0);
+
+    fn count<T>(_: T) {
+        CONSUMED.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[test]
+    fn heterogeneous_guards() {
+        let mut guards: Vec<ErasedGuard> = Vec::new();
+        guards.push(WithConsumer::erase(WithConsumer::new(5u32, Closure(count))));
+        let consumer: Closure<fn(String)> = Closure(count);
+        guards.push(WithConsumer::new(String::from("hi"), consumer).into());
+
+        assert!(guards[0].is::<u32>());
+        guards[1].downcast_mut::<String>().unwrap().push('!');
+        assert_eq!(guards[1].downcast_ref::<String>().unwrap(), "hi!");
+
+        let guard = guards.pop().unwrap();
+        let guard = ErasedGuard::downcast::<u32, Closure<fn(u32)>>(guard).unwrap_err();
+        let guard = ErasedGuard::downcast::<String, Closure<fn(String)>>(guard).unwrap();
+        assert_eq!(*guard, "hi!");
+
+        drop(guards);
+        assert_eq!(CONSUMED.load(Ordering::Relaxed), 1);
+        drop(guard);
+        assert_eq!(CONSUMED.load(Ordering::Relaxed), 2);
+    }
+}
@@ -0,0 +1,169 @@
+use crate::{Consumer, ContextConsume, WithConsumer, WithContext};
+use core::fmt;
+use core::marker::PhantomData;
+use core::ops::{Deref, DerefMut};
+
+/// A family of [`Consumer<T>`]s which borrow from a context of type [`Self::Context`], with one
+/// consumer type per lifetime of the borrow.
+///
+/// A [`Consumer`] is taken by value, so one which borrows its context must name the borrow's
+/// lifetime in its type. A family does so once and for all, so a [`FamilyGuard<'ctx, T, F>`]
+/// can be named, e.g. in a struct field, and holds its consumer inline for a borrow chosen when
+/// the guard is built, rather than a `'static` or boxed one.
+pub trait ConsumerFamily<T> {
+    /// The context the consumers borrow from.
+    type Context: ?Sized;
+
+    /// The consumer borrowing the context for `'ctx`.
+    type Consumer<'ctx>: Consumer<T>
+    where
+        Self::Context: 'ctx;
+
+    /// Builds the consumer which borrows `ctx`.
+    fn bind(ctx: &Self::Context) -> Self::Consumer<'_>;
+}
+
+/// The [`ConsumerFamily`] which consumes a `T: ContextConsume<C>` using a borrowed `C`.
+pub struct ContextFamily<C: ?Sized>(PhantomData<fn(&C)>);
+
+impl<C: ?Sized, T: ContextConsume<C>> ConsumerFamily<T> for ContextFamily<C> {
+    type Context = C;
+    type Consumer<'ctx>
+        = WithContext<&'ctx C>
+    where
+        C: 'ctx;
+
+    #[inline]
+    fn bind(ctx: &C) -> WithContext<&C> {
+        WithContext(ctx)
+    }
+}
+
+impl<C: ?Sized> fmt::Debug for ContextFamily<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ContextFamily")
+    }
+}
+
+/// A `T` along with the consumer of the family `F` which borrows a context for `'ctx`. When
+/// dropped, the `T` is consumed by that consumer.
+pub struct FamilyGuard<'ctx, T, F: ConsumerFamily<T>>
+where
+    F::Context: 'ctx,
+{
+    inner: WithConsumer<T, F::Consumer<'ctx>>,
+}
+
+impl<'ctx, T, F: ConsumerFamily<T>> FamilyGuard<'ctx, T, F>
+where
+    F::Context: 'ctx,
+{
+    /// Guards `value`, which will be consumed by the consumer of `F` borrowing `ctx`.
+    #[inline]
+    pub fn new(value: T, ctx: &'ctx F::Context) -> Self {
+        Self {
+            inner: WithConsumer::new(value, F::bind(ctx)),
+        }
+    }
+
+    /// Extracts the `T` and its consumer without consuming the `T`.
+    #[inline]
+    pub fn into_pair(x: Self) -> (T, F::Consumer<'ctx>) {
+        WithConsumer::into_pair(x.inner)
+    }
+
+    /// Extracts the `T` without consuming it.
+    #[inline]
+    pub fn into_inner(x: Self) -> T {
+        WithConsumer::into_inner(x.inner)
+    }
+}
+
+impl<'ctx, T: fmt::Debug, F: ConsumerFamily<T>> fmt::Debug for FamilyGuard<'ctx, T, F>
+where
+    F::Context: 'ctx,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("FamilyGuard").field(&*self.inner).finish()
+    }
+}
+
+impl<'ctx, T, F: ConsumerFamily<T>> Deref for FamilyGuard<'ctx, T, F>
+where
+    F::Context: 'ctx,
+{
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        &self.inner
+    }
+}
+
+impl<'ctx, T, F: ConsumerFamily<T>> DerefMut for FamilyGuard<'ctx, T, F>
+where
+    F::Context: 'ctx,
+{
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ConsumerFamily, ContextFamily, FamilyGuard};
+    use crate::{Consumer, ContextConsume};
+    use alloc::vec::Vec;
+    use core::cell::RefCell;
+
+    struct Pool {
+        free: RefCell<Vec<u32>>,
+    }
+
+    struct PushTo<'pool>(&'pool Pool);
+
+    impl Consumer<u32> for PushTo<'_> {
+        fn consume(self, slot: u32) {
+            self.0.free.borrow_mut().push(slot)
+        }
+    }
+
+    struct ReturnTo;
+
+    impl ConsumerFamily<u32> for ReturnTo {
+        type Context = Pool;
+        type Consumer<'ctx> = PushTo<'ctx>;
+
+        fn bind(pool: &Pool) -> PushTo<'_> {
+            PushTo(pool)
+        }
+    }
+
+    struct Connections<'pool> {
+        slots: Vec<FamilyGuard<'pool, u32, ReturnTo>>,
+    }
+
+    #[test]
+    fn consumers_borrow_context() {
+        let pool = Pool {
+            free: RefCell::new(Vec::new()),
+        };
+        let connections = Connections {
+            slots: (0..3).map(|slot| FamilyGuard::new(slot, &pool)).collect(),
+        };
+        assert_eq!(*connections.slots[1], 1);
+        drop(connections);
+        assert_eq!(*pool.free.borrow(), [0, 1, 2]);
+
+        struct Counter(u32);
+        impl ContextConsume<RefCell<u32>> for Counter {
+            fn consume(self, total: &RefCell<u32>) {
+                *total.borrow_mut() += self.0
+            }
+        }
+        let total = RefCell::new(0);
+        drop(FamilyGuard::<_, ContextFamily<_>>::new(Counter(5), &total));
+        assert_eq!(*total.borrow(), 5);
+    }
+}
@@ -0,0 +1,205 @@
+use crate::{Consumer, TryConsumer};
+use core::fmt;
+use core::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// The error returned, or the panic payload raised, by a [`FaultyConsumer`] when it injects a
+/// fault.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct InjectedFault;
+
+impl fmt::Display for InjectedFault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("injected fault")
+    }
+}
+
+impl std::error::Error for InjectedFault {}
+
+#[derive(Debug)]
+enum Schedule {
+    Nth(u64),
+    EveryNth(u64),
+    Random { threshold: u64, state: AtomicU64 },
+}
+
+#[derive(Debug)]
+struct Plan {
+    schedule: Schedule,
+    invocations: AtomicU64,
+    faults: AtomicU64,
+}
+
+/// Decides which invocations of the [`FaultyConsumer`]s sharing it fail. Clones share their
+/// counters, so a plan can count invocations across many guards.
+#[derive(Clone, Debug)]
+pub struct FaultPlan {
+    plan: Arc<Plan>,
+}
+
+impl FaultPlan {
+    fn new(schedule: Schedule) -> Self {
+        Self {
+            plan: Arc::new(Plan {
+                schedule,
+                invocations: AtomicU64::new(0),
+                faults: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// Fails only the `n`th invocation, counting from 1.
+    #[inline]
+    pub fn on_nth(n: u64) -> Self {
+        Self::new(Schedule::Nth(n))
+    }
+
+    /// Fails every `n`th invocation, counting from 1.
+    ///
+    /// Panics if `n` is 0.
+    #[inline]
+    pub fn every_nth(n: u64) -> Self {
+        assert!(n > 0, "cannot fail every 0th invocation");
+        Self::new(Schedule::EveryNth(n))
+    }
+
+    /// Fails each invocation with the given `probability`, drawn from a pseudo-random sequence
+    /// determined by `seed`, so that a run can be reproduced.
+    pub fn random(probability: f64, seed: u64) -> Self {
+        let threshold = if probability >= 1.0 {
+            u64::MAX
+        } else {
+            (probability.max(0.0) * u64::MAX as f64) as u64
+        };
+        Self::new(Schedule::Random {
+            threshold,
+            state: AtomicU64::new(seed),
+        })
+    }
+
+    /// The number of invocations so far.
+    #[inline]
+    pub fn invocations(&self) -> u64 {
+        self.plan.invocations.load(Ordering::Relaxed)
+    }
+
+    /// The number of faults injected so far.
+    #[inline]
+    pub fn faults(&self) -> u64 {
+        self.plan.faults.load(Ordering::Relaxed)
+    }
+
+    /// Records an invocation, and decides whether it fails.
+    fn next(&self) -> bool {
+        let n = self.plan.invocations.fetch_add(1, Ordering::Relaxed) + 1;
+        let fault = match &self.plan.schedule {
+            Schedule::Nth(nth) => n == *nth,
+            Schedule::EveryNth(every) => n.is_multiple_of(*every),
+            Schedule::Random { threshold, state } => {
+                // SplitMix64, advanced atomically so that concurrent invocations draw
+                // different numbers.
+                let mut z = state
+                    .fetch_add(0x9e37_79b9_7f4a_7c15, Ordering::Relaxed)
+                    .wrapping_add(0x9e37_79b9_7f4a_7c15);
+                z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+                z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+                z ^= z >> 31;
+                z < *threshold
+            }
+        };
+        if fault {
+            self.plan.faults.fetch_add(1, Ordering::Relaxed);
+        }
+        fault
+    }
+}
+
+/// A consumer which passes values on to the consumer `Q`, except on the invocations chosen by
+/// its [`FaultPlan`], for exercising cleanup-error and double-panic handling in tests.
+///
+/// As a [`Consumer<T>`], a faulty invocation panics with an [`InjectedFault`] payload. As a
+/// [`TryConsumer<T>`], it returns an [`InjectedFault`] converted into `Q`'s error. Either way,
+/// the value is dropped without being passed on.
+#[derive(Clone, Debug)]
+pub struct FaultyConsumer<Q> {
+    inner: Q,
+    plan: FaultPlan,
+}
+
+impl<Q> FaultyConsumer<Q> {
+    /// Wraps `inner`, injecting faults as decided by `plan`.
+    #[inline]
+    pub const fn new(inner: Q, plan: FaultPlan) -> Self {
+        Self { inner, plan }
+    }
+
+    /// The plan deciding which invocations fail.
+    #[inline]
+    pub fn plan(&self) -> &FaultPlan {
+        &self.plan
+    }
+}
+
+impl<T, Q: Consumer<T>> Consumer<T> for FaultyConsumer<Q> {
+    fn consume(self, other: T) {
+        if self.plan.next() {
+            std::panic::panic_any(InjectedFault)
+        }
+        self.inner.consume(other)
+    }
+}
+
+impl<T, Q> TryConsumer<T> for FaultyConsumer<Q>
+where
+    Q: TryConsumer<T>,
+    Q::Error: From<InjectedFault>,
+{
+    type Error = Q::Error;
+
+    fn try_consume(self, other: T) -> Result<(), Q::Error> {
+        if self.plan.next() {
+            return Err(InjectedFault.into());
+        }
+        self.inner.try_consume(other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FaultPlan, FaultyConsumer, InjectedFault};
+    use crate::{Closure, TryConsumer, WithConsumer};
+    use std::panic::{self, AssertUnwindSafe};
+    use std::vec::Vec;
+
+    #[test]
+    fn faults_as_planned() {
+        let plan = FaultPlan::on_nth(2);
+        let mut closed = Vec::new();
+        for id in 1..=3 {
+            let consumer = FaultyConsumer::new(Closure(|id| closed.push(id)), plan.clone());
+            let dropped =
+                panic::catch_unwind(AssertUnwindSafe(|| drop(WithConsumer::new(id, consumer))));
+            if let Err(payload) = dropped {
+                assert!(payload.is::<InjectedFault>());
+            }
+        }
+        assert_eq!(closed, [1, 3]);
+        assert_eq!((plan.invocations(), plan.faults()), (3, 1));
+
+        let plan = FaultPlan::every_nth(2);
+        let close =
+            || FaultyConsumer::new(Closure(|_: u8| Ok::<_, InjectedFault>(())), plan.clone());
+        let results: Vec<_> = (0..4).map(|_| close().try_consume(0)).collect();
+        assert_eq!(
+            results,
+            [Ok(()), Err(InjectedFault), Ok(()), Err(InjectedFault)]
+        );
+
+        let draw = |seed| {
+            let plan = FaultPlan::random(0.5, seed);
+            (0..64).map(|_| plan.next()).collect::<Vec<_>>()
+        };
+        assert_eq!(draw(7), draw(7));
+        assert!(draw(7).contains(&true) && draw(7).contains(&false));
+    }
+}
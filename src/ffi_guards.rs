@@ -0,0 +1,259 @@
+use crate::Consume;
+use core::fmt;
+use core::marker::PhantomData;
+
+/// Implementation detail of [`ffi_guards!`](crate::ffi_guards): destroys a `T` on behalf of
+/// the generated guard type.
+#[doc(hidden)]
+pub trait FfiDestroy<T> {
+    /// # Safety
+    ///
+    /// `value` must be valid to pass to the destructor of the guard type.
+    unsafe fn destroy(value: T);
+}
+
+/// Implementation detail of [`ffi_guards!`](crate::ffi_guards): a raw value tagged with the
+/// guard type which knows how to destroy it.
+#[doc(hidden)]
+#[repr(transparent)]
+pub struct FfiRaw<T, Tag>(T, PhantomData<fn() -> Tag>);
+
+impl<T, Tag> FfiRaw<T, Tag> {
+    /// # Safety
+    ///
+    /// `value` must be valid to pass to [`FfiDestroy::destroy`] of `Tag`.
+    #[inline]
+    pub const unsafe fn new(value: T) -> Self {
+        Self(value, PhantomData)
+    }
+
+    #[inline]
+    pub const fn get(&self) -> &T {
+        &self.0
+    }
+
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T, Tag: FfiDestroy<T>> Consume for FfiRaw<T, Tag> {
+    #[inline]
+    fn consume(self) {
+        // SAFETY: `FfiRaw::new` requires that the value may be passed to `Tag::destroy`.
+        unsafe { Tag::destroy(self.0) }
+    }
+}
+
+impl<T: fmt::Debug, Tag> fmt::Debug for FfiRaw<T, Tag> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// Generates guard types for foreign handles from a table of destructors.
+///
+/// Each entry `struct Name(Raw) => destroy;` defines a zero-cost newtype around
+/// [`ConsumeOnDrop`](crate::ConsumeOnDrop) which passes its `Raw` value to `destroy` when
+/// dropped. Each guard gets [`Deref<Target = Raw>`](core::ops::Deref), `Name::into_inner(x)`
+/// for releasing the raw value without destroying it, and a constructor:
+///
+/// - If `destroy` is a safe function, `Name::new(raw)` is safe, since any `Raw` may be passed to
+///   `destroy`.
+/// - If the entry says `=> unsafe destroy`, as is typical for `extern "C"` functions, the
+///   constructor is the unsafe `Name::from_raw(raw)`, whose safety contract is that dropping the
+///   guard may call `destroy(raw)`.
+///
+/// Attributes on an entry, such as doc comments or `#[derive(Debug)]`, are applied to the
+/// generated type.
+///
+/// ```
+/// mod sys {
+///     pub struct Window(u32);
+///     pub unsafe extern "C" fn destroy_window(_window: *mut Window) {}
+///     pub fn free_id(_id: u32) {}
+/// }
+///
+/// consume_on_drop::ffi_guards! {
+///     /// An owned window.
+///     pub struct Window(*mut sys::Window) => unsafe sys::destroy_window;
+///     #[derive(Debug)]
+///     pub struct Id(u32) => sys::free_id;
+/// }
+///
+/// let id = Id::new(7);
+/// assert_eq!(*id, 7);
+/// let window = unsafe { Window::from_raw(core::ptr::null_mut()) };
+/// let _raw: *mut sys::Window = Window::into_inner(window); // not destroyed
+/// ```
+///
+/// The destructor of an `unsafe` entry can only be reached through `unsafe` code. Neither the
+/// raw value underlying a guard nor the destructor can be used safely:
+///
+/// ```compile_fail,E0133
+/// # mod sys {
+/// #     pub struct Window(u32);
+/// #     pub unsafe extern "C" fn destroy_window(_window: *mut Window) {}
+/// # }
+/// # consume_on_drop::ffi_guards! {
+/// #     pub struct Window(*mut sys::Window) => unsafe sys::destroy_window;
+/// # }
+/// use consume_on_drop::{ConsumeOnDrop, FfiRaw};
+///
+/// drop(ConsumeOnDrop::new(FfiRaw::<_, Window>::new(core::ptr::null_mut())));
+/// ```
+///
+/// ```compile_fail,E0133
+/// # mod sys {
+/// #     pub struct Window(u32);
+/// #     pub unsafe extern "C" fn destroy_window(_window: *mut Window) {}
+/// # }
+/// # consume_on_drop::ffi_guards! {
+/// #     pub struct Window(*mut sys::Window) => unsafe sys::destroy_window;
+/// # }
+/// use consume_on_drop::FfiDestroy;
+///
+/// <Window as FfiDestroy<_>>::destroy(core::ptr::null_mut());
+/// ```
+///
+/// Unsafe destructors must be marked as such:
+///
+/// ```compile_fail,E0133
+/// # mod sys {
+/// #     pub struct Window(u32);
+/// #     pub unsafe extern "C" fn destroy_window(_window: *mut Window) {}
+/// # }
+/// consume_on_drop::ffi_guards! {
+///     pub struct Window(*mut sys::Window) => sys::destroy_window;
+/// }
+/// ```
+#[macro_export]
+macro_rules! ffi_guards {
+    () => {};
+    (
+        $(#[$attr:meta])*
+        $vis:vis struct $name:ident($raw:ty) => unsafe $destroy:path;
+        $($rest:tt)*
+    ) => {
+        $crate::ffi_guards!(@guard [$(#[$attr])*] [$vis] $name [$raw]);
+
+        impl $crate::FfiDestroy<$raw> for $name {
+            #[inline]
+            unsafe fn destroy(value: $raw) {
+                // SAFETY: the caller guarantees that the raw value may be passed to `$destroy`.
+                unsafe { $destroy(value) }
+            }
+        }
+
+        impl $name {
+            /// Takes ownership of `raw`, which will be destroyed when the guard is dropped.
+            ///
+            /// # Safety
+            ///
+            #[doc = ::core::concat!("`raw` must be valid to pass to `", ::core::stringify!($destroy), "`.")]
+            #[inline]
+            $vis unsafe fn from_raw(raw: $raw) -> Self {
+                Self {
+                    // SAFETY: the caller guarantees that `raw` may be passed to `$destroy`.
+                    inner: $crate::ConsumeOnDrop::new(unsafe { $crate::FfiRaw::new(raw) }),
+                }
+            }
+        }
+
+        $crate::ffi_guards!($($rest)*);
+    };
+    (
+        $(#[$attr:meta])*
+        $vis:vis struct $name:ident($raw:ty) => $destroy:path;
+        $($rest:tt)*
+    ) => {
+        $crate::ffi_guards!(@guard [$(#[$attr])*] [$vis] $name [$raw]);
+
+        impl $crate::FfiDestroy<$raw> for $name {
+            #[inline]
+            unsafe fn destroy(value: $raw) {
+                // Calling `$destroy` from a safe function rejects destructors which are unsafe
+                // but were not marked as such.
+                fn destroy(value: $raw) {
+                    $destroy(value)
+                }
+                destroy(value)
+            }
+        }
+
+        impl $name {
+            /// Takes ownership of `raw`, which will be destroyed when the guard is dropped.
+            #[inline]
+            $vis fn new(raw: $raw) -> Self {
+                Self {
+                    // SAFETY: `$destroy` is safe, so it accepts any raw value.
+                    inner: $crate::ConsumeOnDrop::new(unsafe { $crate::FfiRaw::new(raw) }),
+                }
+            }
+        }
+
+        $crate::ffi_guards!($($rest)*);
+    };
+    (@guard [$($attr:tt)*] [$vis:vis] $name:ident [$raw:ty]) => {
+        $($attr)*
+        #[repr(transparent)]
+        $vis struct $name {
+            inner: $crate::ConsumeOnDrop<$crate::FfiRaw<$raw, $name>>,
+        }
+
+        #[allow(dead_code)]
+        impl $name {
+            /// Releases the raw value without destroying it.
+            #[inline]
+            $vis fn into_inner(x: Self) -> $raw {
+                $crate::ConsumeOnDrop::into_inner(x.inner).into_inner()
+            }
+        }
+
+        impl ::core::ops::Deref for $name {
+            type Target = $raw;
+
+            #[inline]
+            fn deref(&self) -> &$raw {
+                self.inner.get()
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use core::cell::Cell;
+
+    std::thread_local! {
+        static FREED: Cell<u32> = const { Cell::new(0) };
+    }
+
+    fn free_buffer(id: u32) {
+        FREED.with(|freed| freed.set(freed.get() + id))
+    }
+
+    unsafe extern "C" fn release_texture(id: u64) {
+        FREED.with(|freed| freed.set(freed.get() + id as u32))
+    }
+
+    ffi_guards! {
+        /// A buffer.
+        #[derive(Debug)]
+        struct Buffer(u32) => free_buffer;
+        struct Texture(u64) => unsafe release_texture;
+    }
+
+    #[test]
+    fn generated_guards() {
+        let buffer = Buffer::new(1);
+        assert_eq!(*buffer, 1);
+        assert!(std::format!("{:?}", buffer).starts_with("Buffer"));
+        drop(buffer);
+        let texture = unsafe { Texture::from_raw(10) };
+        drop(texture);
+        assert_eq!(Texture::into_inner(unsafe { Texture::from_raw(100) }), 100);
+        assert_eq!(FREED.with(Cell::get), 11);
+    }
+}
@@ -0,0 +1,197 @@
+use crate::{Consumer, WithConsumer};
+use core::ops::{Deref, DerefMut};
+use std::fs::{File, OpenOptions, TryLockError};
+use std::io;
+use std::path::Path;
+
+/// The default error hook used by [`UnlockFile`]. It silently ignores the error, since the lock
+/// is released anyway when the file is closed.
+fn ignore_unlock_error(_error: io::Error) {}
+
+/// A [`Consumer<File>`] which releases an advisory lock held on the file with [`File::unlock`],
+/// then closes it.
+///
+/// If unlocking fails, the error is passed to the hook `F`. By default, errors are ignored.
+#[derive(Debug, Clone, Copy)]
+pub struct UnlockFile<F = fn(io::Error)> {
+    on_error: F,
+}
+
+impl UnlockFile {
+    /// Builds a consumer which ignores errors from unlocking.
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            on_error: ignore_unlock_error,
+        }
+    }
+}
+
+impl Default for UnlockFile {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<F: FnOnce(io::Error)> UnlockFile<F> {
+    /// Builds a consumer which reports errors from unlocking to `on_error`.
+    #[inline]
+    pub const fn with_hook(on_error: F) -> Self {
+        Self { on_error }
+    }
+}
+
+impl<F: FnOnce(io::Error)> Consumer<File> for UnlockFile<F> {
+    fn consume(self, file: File) {
+        if let Err(error) = file.unlock() {
+            (self.on_error)(error)
+        }
+    }
+}
+
+fn open_lock_file(path: &Path) -> io::Result<File> {
+    OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)
+}
+
+/// Whether a non-blocking attempt to lock a file succeeded.
+fn acquired(attempt: Result<(), TryLockError>) -> io::Result<bool> {
+    match attempt {
+        Ok(()) => Ok(true),
+        Err(TryLockError::WouldBlock) => Ok(false),
+        Err(TryLockError::Error(error)) => Err(error),
+    }
+}
+
+/// A file holding an advisory lock, such as `flock(2)` on Unix or `LockFileEx` on Windows, which
+/// is released when dropped.
+///
+/// Advisory locks only exclude other processes, or other handles in this process, which lock the
+/// same file, so they are a natural way to keep several instances of a program from working on
+/// the same data at once. The guard dereferences to the locked [`File`]. Errors from unlocking
+/// can be observed with [`FileLockGuard::with_hook`].
+#[derive(Debug)]
+pub struct FileLockGuard<F: FnOnce(io::Error) = fn(io::Error)> {
+    inner: WithConsumer<File, UnlockFile<F>>,
+    shared: bool,
+}
+
+impl FileLockGuard {
+    fn locked(file: File, shared: bool) -> Self {
+        Self {
+            inner: WithConsumer::new(file, UnlockFile::new()),
+            shared,
+        }
+    }
+
+    /// Takes an exclusive lock on `file`, blocking until no other lock is held on it.
+    pub fn lock(file: File) -> io::Result<Self> {
+        file.lock()?;
+        Ok(Self::locked(file, false))
+    }
+
+    /// Takes a shared lock on `file`, blocking until no exclusive lock is held on it.
+    pub fn lock_shared(file: File) -> io::Result<Self> {
+        file.lock_shared()?;
+        Ok(Self::locked(file, true))
+    }
+
+    /// Takes an exclusive lock on `file` if no other lock is held on it. Returns `None` if the
+    /// file is already locked.
+    pub fn try_lock(file: File) -> io::Result<Option<Self>> {
+        Ok(acquired(file.try_lock())?.then(|| Self::locked(file, false)))
+    }
+
+    /// Takes a shared lock on `file` if no exclusive lock is held on it. Returns `None` if the
+    /// file is already locked exclusively.
+    pub fn try_lock_shared(file: File) -> io::Result<Option<Self>> {
+        Ok(acquired(file.try_lock_shared())?.then(|| Self::locked(file, true)))
+    }
+
+    /// Opens the lock file at `path`, creating it if needed, and takes an exclusive lock on it,
+    /// blocking until no other lock is held on it.
+    pub fn lock_path(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::lock(open_lock_file(path.as_ref())?)
+    }
+
+    /// Opens the lock file at `path`, creating it if needed, and takes an exclusive lock on it if
+    /// no other lock is held on it. Returns `None` if the file is already locked.
+    pub fn try_lock_path(path: impl AsRef<Path>) -> io::Result<Option<Self>> {
+        Self::try_lock(open_lock_file(path.as_ref())?)
+    }
+
+    /// Reports any error from unlocking `x` to `on_error`.
+    #[inline]
+    pub fn with_hook<F: FnOnce(io::Error)>(x: Self, on_error: F) -> FileLockGuard<F> {
+        let (file, _) = WithConsumer::into_pair(x.inner);
+        FileLockGuard {
+            inner: WithConsumer::new(file, UnlockFile::with_hook(on_error)),
+            shared: x.shared,
+        }
+    }
+}
+
+impl<F: FnOnce(io::Error)> FileLockGuard<F> {
+    /// Whether `x` holds a shared lock rather than an exclusive one.
+    #[inline]
+    pub fn is_shared(x: &Self) -> bool {
+        x.shared
+    }
+
+    /// Releases the lock now, reporting any error, and hands back the file.
+    pub fn unlock(x: Self) -> io::Result<File> {
+        let file = WithConsumer::into_inner(x.inner);
+        file.unlock()?;
+        Ok(file)
+    }
+}
+
+impl<F: FnOnce(io::Error)> Deref for FileLockGuard<F> {
+    type Target = File;
+
+    #[inline]
+    fn deref(&self) -> &File {
+        &self.inner
+    }
+}
+
+impl<F: FnOnce(io::Error)> DerefMut for FileLockGuard<F> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut File {
+        &mut self.inner
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FileLockGuard;
+    use std::fs;
+
+    #[test]
+    fn excludes_other_handles_until_dropped() {
+        let path = std::env::temp_dir().join(std::format!(
+            "consume_on_drop-lock-{}.lock",
+            std::process::id()
+        ));
+        let held = FileLockGuard::lock_path(&path).unwrap();
+        assert!(FileLockGuard::try_lock_path(&path).unwrap().is_none());
+        let other = fs::File::open(&path).unwrap();
+        assert!(FileLockGuard::try_lock_shared(other).unwrap().is_none());
+
+        let mut reported = false;
+        drop(FileLockGuard::with_hook(held, |_| reported = true));
+        assert!(!reported);
+        let shared = FileLockGuard::try_lock_shared(fs::File::open(&path).unwrap())
+            .unwrap()
+            .unwrap();
+        assert!(FileLockGuard::is_shared(&shared));
+        FileLockGuard::unlock(shared).unwrap();
+        assert!(FileLockGuard::try_lock_path(&path).unwrap().is_some());
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,139 @@
+use crate::{Closure, Consumer, WithConsumer};
+use core::fmt;
+use core::ops::{Deref, DerefMut};
+
+/// A type which must be finished by value, such as an encoder, a hasher, or an archive
+/// builder whose `finish(self)` writes trailing data and reports whether that succeeded.
+///
+/// Implement this for such types to guard them with a [`FinishOnDrop`].
+pub trait Finish {
+    /// What finishing produces on success, such as the underlying writer or a digest.
+    type Output;
+    /// The error produced when finishing fails.
+    type Error;
+
+    /// Finishes `self`.
+    fn finish(self) -> Result<Self::Output, Self::Error>;
+}
+
+impl<O, E, F: FnOnce() -> Result<O, E>> Finish for Closure<F> {
+    type Output = O;
+    type Error = E;
+
+    #[inline]
+    fn finish(self) -> Result<O, E> {
+        (self.0)()
+    }
+}
+
+/// Flushes the buffer and returns the underlying writer.
+#[cfg(feature = "std")]
+impl<W: std::io::Write> Finish for std::io::BufWriter<W> {
+    type Output = W;
+    type Error = std::io::IntoInnerError<Self>;
+
+    #[inline]
+    fn finish(self) -> Result<W, Self::Error> {
+        self.into_inner()
+    }
+}
+
+/// The result of finishing a `T`.
+pub type Finished<T> = Result<<T as Finish>::Output, <T as Finish>::Error>;
+
+/// The default handler used by [`FinishOnDrop`]. It discards the result.
+fn discard_finished<T: Finish>(_result: Finished<T>) {}
+
+/// The consumer used by [`FinishOnDrop`]: it finishes a value and passes the result to `H`.
+#[derive(Clone, Copy, Debug)]
+pub struct FinishWith<H>(pub H);
+
+impl<T: Finish, H: FnOnce(Finished<T>)> Consumer<T> for FinishWith<H> {
+    #[inline]
+    fn consume(self, other: T) {
+        (self.0)(other.finish())
+    }
+}
+
+/// A guard which calls [`Finish::finish`] when dropped, so that forgetting to finish an encoder
+/// or builder no longer silently truncates its output.
+///
+/// The result of finishing on drop is passed to the handler `H`, which by default discards
+/// it. To handle the result in place, call [`FinishOnDrop::finish_now`] instead.
+pub struct FinishOnDrop<T: Finish, H: FnOnce(Finished<T>) = fn(Finished<T>)> {
+    inner: WithConsumer<T, FinishWith<H>>,
+}
+
+impl<T: Finish> FinishOnDrop<T> {
+    /// Guards `value`, discarding the result if it is finished on drop.
+    #[inline]
+    pub fn new(value: T) -> Self {
+        Self::with_handler(value, discard_finished::<T>)
+    }
+}
+
+impl<T: Finish, H: FnOnce(Finished<T>)> FinishOnDrop<T, H> {
+    /// Guards `value`, passing the result to `handler` if it is finished on drop.
+    #[inline]
+    pub const fn with_handler(value: T, handler: H) -> Self {
+        Self {
+            inner: WithConsumer::new(value, FinishWith(handler)),
+        }
+    }
+
+    /// Finishes the value now and returns the result. The handler is not called.
+    #[inline]
+    pub fn finish_now(x: Self) -> Finished<T> {
+        Self::into_inner(x).finish()
+    }
+
+    /// Extracts the value without finishing it.
+    #[inline]
+    pub fn into_inner(x: Self) -> T {
+        WithConsumer::into_inner(x.inner)
+    }
+}
+
+impl<T: Finish + fmt::Debug, H: FnOnce(Finished<T>)> fmt::Debug for FinishOnDrop<T, H> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("FinishOnDrop").field(&*self.inner).finish()
+    }
+}
+
+impl<T: Finish, H: FnOnce(Finished<T>)> Deref for FinishOnDrop<T, H> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        &self.inner
+    }
+}
+
+impl<T: Finish, H: FnOnce(Finished<T>)> DerefMut for FinishOnDrop<T, H> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::FinishOnDrop;
+    use std::io::{BufWriter, Write};
+    use std::vec::Vec;
+
+    #[test]
+    fn finishes_on_drop_or_now() {
+        let mut output = None;
+        let mut writer = FinishOnDrop::with_handler(BufWriter::new(Vec::new()), |result| {
+            output = result.ok();
+        });
+        writer.write_all(b"trailer").unwrap();
+        drop(writer);
+        assert_eq!(output.as_deref(), Some(&b"trailer"[..]));
+
+        let mut writer = FinishOnDrop::new(BufWriter::new(Vec::new()));
+        writer.write_all(b"body").unwrap();
+        assert_eq!(FinishOnDrop::finish_now(writer).unwrap(), b"body");
+    }
+}
@@ -0,0 +1,188 @@
+use crate::{Consume, ConsumeOnDrop, Consumer, WithConsumer};
+use core::cell::RefCell;
+use core::fmt;
+use core::marker::PhantomData;
+
+/// A destination which a [`FormatConsumer`] can write to.
+///
+/// This is implemented for `&mut W` and, so that many guards can share one sink, for
+/// `&RefCell<W>`, where `W` is any [`fmt::Write`], such as a [`String`](alloc::string::String).
+pub trait FormatSink {
+    /// Writes formatted text to the sink.
+    fn write_args(self, args: fmt::Arguments<'_>) -> fmt::Result;
+}
+
+impl<W: fmt::Write + ?Sized> FormatSink for &mut W {
+    #[inline]
+    fn write_args(self, args: fmt::Arguments<'_>) -> fmt::Result {
+        self.write_fmt(args)
+    }
+}
+
+impl<W: fmt::Write + ?Sized> FormatSink for &RefCell<W> {
+    #[inline]
+    fn write_args(self, args: fmt::Arguments<'_>) -> fmt::Result {
+        self.borrow_mut().write_fmt(args)
+    }
+}
+
+/// Selects [`fmt::Display`] formatting for a [`FormatConsumer`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AsDisplay;
+
+/// Selects [`fmt::Debug`] formatting for a [`FormatConsumer`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AsDebug;
+
+/// A [`Consumer`] which writes the value it consumes into a [`FormatSink`], using either its
+/// [`fmt::Display`] or its [`fmt::Debug`] representation, followed by an optional suffix.
+///
+/// Errors from the sink are ignored, since there is nobody to report them to when a guard is
+/// dropped.
+#[derive(Debug, Clone, Copy)]
+pub struct FormatConsumer<S, M = AsDisplay> {
+    sink: S,
+    suffix: &'static str,
+    mode: PhantomData<M>,
+}
+
+impl<S: FormatSink> FormatConsumer<S> {
+    /// Builds a consumer which writes values using [`fmt::Display`].
+    #[inline]
+    pub const fn display(sink: S) -> Self {
+        Self {
+            sink,
+            suffix: "",
+            mode: PhantomData,
+        }
+    }
+}
+
+impl<S: FormatSink> FormatConsumer<S, AsDebug> {
+    /// Builds a consumer which writes values using [`fmt::Debug`].
+    #[inline]
+    pub const fn debug(sink: S) -> Self {
+        Self {
+            sink,
+            suffix: "",
+            mode: PhantomData,
+        }
+    }
+}
+
+impl<S: FormatSink, M> FormatConsumer<S, M> {
+    /// Writes `suffix` after each value, e.g. `"\n"` to put every value on its own line.
+    #[inline]
+    pub fn with_suffix(self, suffix: &'static str) -> Self {
+        Self { suffix, ..self }
+    }
+}
+
+impl<T: fmt::Display, S: FormatSink> Consumer<T> for FormatConsumer<S, AsDisplay> {
+    fn consume(self, other: T) {
+        let _ = self
+            .sink
+            .write_args(format_args!("{}{}", other, self.suffix));
+    }
+}
+
+impl<T: fmt::Debug, S: FormatSink> Consumer<T> for FormatConsumer<S, AsDebug> {
+    fn consume(self, other: T) {
+        let _ = self
+            .sink
+            .write_args(format_args!("{:?}{}", other, self.suffix));
+    }
+}
+
+// Guarded writers are writers too, so that they can be passed to functions generic over
+// `fmt::Write`, which dereferencing alone does not allow.
+
+impl<T: Consume + fmt::Write> fmt::Write for ConsumeOnDrop<T> {
+    #[inline]
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        (**self).write_str(s)
+    }
+
+    #[inline]
+    fn write_char(&mut self, c: char) -> fmt::Result {
+        (**self).write_char(c)
+    }
+
+    #[inline]
+    fn write_fmt(&mut self, args: fmt::Arguments<'_>) -> fmt::Result {
+        (**self).write_fmt(args)
+    }
+}
+
+impl<T: fmt::Write, Q: Consumer<T>> fmt::Write for WithConsumer<T, Q> {
+    #[inline]
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        (**self).write_str(s)
+    }
+
+    #[inline]
+    fn write_char(&mut self, c: char) -> fmt::Result {
+        (**self).write_char(c)
+    }
+
+    #[inline]
+    fn write_fmt(&mut self, args: fmt::Arguments<'_>) -> fmt::Result {
+        (**self).write_fmt(args)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FormatConsumer;
+    use crate::{Closure, Consume, ConsumeOnDrop, WithConsumer};
+    use alloc::string::String;
+    use core::cell::RefCell;
+    use core::fmt::{self, Write};
+
+    #[test]
+    fn report_retired_values() {
+        let report = RefCell::new(String::new());
+        {
+            let _first = WithConsumer::new(1, FormatConsumer::display(&report).with_suffix("\n"));
+            let _second =
+                WithConsumer::new("two", FormatConsumer::debug(&report).with_suffix("\n"));
+        }
+        assert_eq!(*report.borrow(), "\"two\"\n1\n");
+
+        let mut out = String::new();
+        drop(WithConsumer::new(3.5, FormatConsumer::display(&mut out)));
+        assert_eq!(out, "3.5");
+    }
+
+    #[test]
+    fn guarded_writers_are_writers() {
+        fn greet(out: &mut impl Write, name: &str) -> fmt::Result {
+            write!(out, "hello, {}", name)?;
+            out.write_char('!')
+        }
+
+        let mut sent = String::new();
+        let mut message = WithConsumer::new(String::new(), Closure(|text| sent = text));
+        greet(&mut message, "world").unwrap();
+        drop(message);
+        assert_eq!(sent, "hello, world!");
+
+        struct Line<'a>(String, &'a RefCell<String>);
+
+        impl Write for Line<'_> {
+            fn write_str(&mut self, s: &str) -> fmt::Result {
+                self.0.write_str(s)
+            }
+        }
+
+        impl Consume for Line<'_> {
+            fn consume(self) {
+                self.1.borrow_mut().push_str(&self.0)
+            }
+        }
+
+        let log = RefCell::new(String::new());
+        greet(&mut ConsumeOnDrop::new(Line(String::new(), &log)), "again").unwrap();
+        assert_eq!(*log.borrow(), "hello, again!");
+    }
+}
@@ -0,0 +1,172 @@
+use crate::consume_on_drop::unwrap_transparent;
+use crate::{Consume, ConsumeOnDrop, Consumer, SelfConsumer};
+use core::fmt;
+use core::mem::ManuallyDrop;
+use core::ops::{Deref, DerefMut};
+use core::ptr;
+
+/// A type implementing [`Disposer<T>`] knows how to dispose of a `T` when a [`Guard`]
+/// holding it is dropped.
+///
+/// Every [`Consumer<T>`] is a [`Disposer<T>`]. Implement this trait directly only for
+/// disposers which are not meant to be used as general-purpose consumers.
+pub trait Disposer<T> {
+    /// Disposes of `value`, using up `self` in the process.
+    fn dispose(self, value: T);
+}
+
+impl<T, Q: Consumer<T>> Disposer<T> for Q {
+    #[inline]
+    fn dispose(self, value: T) {
+        self.consume(value)
+    }
+}
+
+#[derive(Default, Debug, Clone)]
+struct RawGuard<T, D>(T, D);
+
+impl<T, D: Disposer<T>> Consume for RawGuard<T, D> {
+    #[inline]
+    fn consume(self) {
+        self.1.dispose(self.0)
+    }
+}
+
+/// The generic guard underlying [`WithConsumer`](crate::WithConsumer) and most other guards
+/// in this crate: a `T` paired with a [`Disposer<T>`], which disposes of the `T` when the
+/// guard is dropped.
+///
+/// Functionality which applies to every guard is implemented here once. [`ConsumeOnDrop<T>`]
+/// remains the primitive that this type is built on, since it guarantees the same layout as
+/// `T`; it behaves exactly like a `Guard<T, SelfConsumer>`, and the two convert freely.
+///
+/// Like [`WithConsumer`](crate::WithConsumer), the [`Debug`](fmt::Debug) impl only prints the `T`.
+#[repr(transparent)]
+#[derive(Default, Clone)]
+pub struct Guard<T, D: Disposer<T>> {
+    inner: ConsumeOnDrop<RawGuard<T, D>>,
+}
+
+impl<T, D: Disposer<T>> Guard<T, D> {
+    /// Builds a [`Guard`] from a value and a disposer.
+    #[inline]
+    pub const fn new(value: T, disposer: D) -> Self {
+        Self {
+            inner: ConsumeOnDrop::new(RawGuard(value, disposer)),
+        }
+    }
+
+    /// Extracts the underlying `T` and [`Disposer<T>`].
+    #[inline]
+    pub const fn into_pair(x: Self) -> (T, D) {
+        // SAFETY: `Guard` is a `#[repr(transparent)]` wrapper around its `ConsumeOnDrop`.
+        let raw = ManuallyDrop::new(ConsumeOnDrop::into_inner(unsafe { unwrap_transparent(x) }));
+        let raw = (&raw as *const ManuallyDrop<RawGuard<T, D>>).cast::<RawGuard<T, D>>();
+        // SAFETY: each field is read exactly once, and `raw` is never dropped.
+        unsafe { (ptr::read(&(*raw).0), ptr::read(&(*raw).1)) }
+    }
+
+    /// Extracts the underlying `T`, dropping the [`Disposer`].
+    #[inline]
+    pub fn into_inner(x: Self) -> T {
+        Self::into_pair(x).0
+    }
+
+    /// Provides references to both the `T` and the [`Disposer<T>`] wrapped by `x`.
+    #[inline]
+    pub const fn as_refs(x: &Self) -> (&T, &D) {
+        let raw = ConsumeOnDrop::get_ref(&x.inner);
+        (&raw.0, &raw.1)
+    }
+
+    /// Provides mutable references to both the `T` and the [`Disposer<T>`] wrapped by `x`.
+    #[inline]
+    pub const fn as_muts(x: &mut Self) -> (&mut T, &mut D) {
+        let raw = ConsumeOnDrop::get_mut(&mut x.inner);
+        (&mut raw.0, &mut raw.1)
+    }
+
+    /// Converts `x` into a [`ConsumeOnDrop`] which disposes of the `T` with the [`Disposer`]
+    /// when dropped. The pair stays guarded throughout.
+    #[inline]
+    pub const fn into_consume_on_drop(x: Self) -> ConsumeOnDrop<impl Consume> {
+        // SAFETY: as in `into_pair`.
+        unsafe { unwrap_transparent::<Self, ConsumeOnDrop<RawGuard<T, D>>>(x) }
+    }
+}
+
+impl<T: Consume> From<ConsumeOnDrop<T>> for Guard<T, SelfConsumer> {
+    #[inline]
+    fn from(guard: ConsumeOnDrop<T>) -> Self {
+        Self::new(ConsumeOnDrop::into_inner(guard), SelfConsumer)
+    }
+}
+
+impl<T: Consume> From<Guard<T, SelfConsumer>> for ConsumeOnDrop<T> {
+    #[inline]
+    fn from(guard: Guard<T, SelfConsumer>) -> Self {
+        Self::new(Guard::into_inner(guard))
+    }
+}
+
+impl<T: fmt::Debug, D: Disposer<T>> fmt::Debug for Guard<T, D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Guard")
+            .field("value", Self::as_refs(self).0)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T, D: Disposer<T>> Deref for Guard<T, D> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        Self::as_refs(self).0
+    }
+}
+
+impl<T, D: Disposer<T>> DerefMut for Guard<T, D> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        Self::as_muts(self).0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Disposer, Guard};
+    use crate::{Closure, ConsumeOnDrop};
+    use core::cell::Cell;
+
+    #[test]
+    fn custom_disposer() {
+        struct Recycle<'a>(&'a Cell<usize>);
+
+        impl Disposer<usize> for Recycle<'_> {
+            fn dispose(self, value: usize) {
+                self.0.set(self.0.get() + value)
+            }
+        }
+
+        let pool = Cell::new(0);
+        let mut guard = Guard::new(2, Recycle(&pool));
+        *guard += 1;
+        drop(guard);
+        assert_eq!(pool.get(), 3);
+
+        let guard = Guard::new(5, Recycle(&pool));
+        assert_eq!(Guard::into_inner(guard), 5);
+        assert_eq!(pool.get(), 3);
+    }
+
+    #[test]
+    fn consume_on_drop_round_trip() {
+        let count = Cell::new(0);
+        let guard = Guard::from(ConsumeOnDrop::new(Closure(|| count.set(count.get() + 1))));
+        let guard = ConsumeOnDrop::from(guard);
+        assert_eq!(count.get(), 0);
+        drop(guard);
+        assert_eq!(count.get(), 1);
+    }
+}
@@ -0,0 +1,120 @@
+use crate::{Consume, ConsumeOnDrop};
+use core::cell::{Ref, RefCell, RefMut};
+use core::mem;
+
+/// An interior-mutable slot which always holds a guarded `T`.
+///
+/// A [`GuardCell`] is never observably empty: every operation which removes the current value
+/// installs its replacement first. If producing the replacement panics, the current value stays
+/// in place, and if consuming the old value panics, the replacement is already in place.
+///
+/// Borrowing follows the rules of [`RefCell`]. Every method which replaces the value panics if
+/// the value is currently borrowed.
+#[derive(Debug, Default)]
+pub struct GuardCell<T: Consume> {
+    inner: RefCell<ConsumeOnDrop<T>>,
+}
+
+impl<T: Consume> GuardCell<T> {
+    /// Builds a [`GuardCell`] holding `value`.
+    #[inline]
+    pub const fn new(value: T) -> Self {
+        Self {
+            inner: RefCell::new(ConsumeOnDrop::new(value)),
+        }
+    }
+
+    /// Immutably borrows the current value.
+    #[inline]
+    pub fn borrow(&self) -> Ref<'_, T> {
+        Ref::map(self.inner.borrow(), |guard| &**guard)
+    }
+
+    /// Mutably borrows the current value.
+    #[inline]
+    pub fn borrow_mut(&self) -> RefMut<'_, T> {
+        RefMut::map(self.inner.borrow_mut(), |guard| &mut **guard)
+    }
+
+    /// Provides a mutable reference to the current value. No runtime borrow checking is needed,
+    /// since we have unique access to the cell.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut T {
+        self.inner.get_mut()
+    }
+
+    /// Installs `value`, returning the old value still guarded.
+    #[inline]
+    pub fn replace(&self, value: T) -> ConsumeOnDrop<T> {
+        mem::replace(&mut *self.inner.borrow_mut(), ConsumeOnDrop::new(value))
+    }
+
+    /// Installs the value produced by `refill`, returning the old value still guarded.
+    ///
+    /// `refill` runs before the cell is borrowed, so it may itself use the cell. If it panics,
+    /// the cell keeps its current value.
+    #[inline]
+    pub fn take_with_refill(&self, refill: impl FnOnce() -> T) -> ConsumeOnDrop<T> {
+        let value = refill();
+        self.replace(value)
+    }
+
+    /// Installs the value produced by `refill`, then consumes the old value.
+    ///
+    /// The old value is consumed after the cell has been refilled and released, so its
+    /// consumption may use the cell and sees the new value.
+    #[inline]
+    pub fn consume_and_refill(&self, refill: impl FnOnce() -> T) {
+        drop(self.take_with_refill(refill))
+    }
+
+    /// Unwraps the current value without consuming it.
+    #[inline]
+    pub fn into_inner(cell: Self) -> T {
+        ConsumeOnDrop::into_inner(cell.inner.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GuardCell;
+    use crate::Consume;
+    use alloc::vec::Vec;
+    use core::cell::RefCell;
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    struct Frame<'a> {
+        id: u32,
+        retired: &'a RefCell<Vec<u32>>,
+    }
+
+    impl<'a> Consume for Frame<'a> {
+        fn consume(self) {
+            self.retired.borrow_mut().push(self.id)
+        }
+    }
+
+    #[test]
+    fn refill_keeps_cell_full() {
+        let retired = RefCell::new(Vec::new());
+        let frame = |id| Frame {
+            id,
+            retired: &retired,
+        };
+        let cell = GuardCell::new(frame(0));
+
+        cell.consume_and_refill(|| frame(1));
+        assert_eq!(*retired.borrow(), [0]);
+        assert_eq!(cell.borrow().id, 1);
+
+        let result = catch_unwind(AssertUnwindSafe(|| cell.consume_and_refill(|| panic!())));
+        assert!(result.is_err());
+        assert_eq!(cell.borrow().id, 1);
+
+        let old = cell.replace(frame(2));
+        assert_eq!(old.id, 1);
+        drop(old);
+        drop(cell);
+        assert_eq!(*retired.borrow(), [0, 1, 2]);
+    }
+}
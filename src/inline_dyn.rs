@@ -0,0 +1,183 @@
+use crate::{Consume, ConsumeOnDrop};
+use core::any::{type_name, TypeId};
+use core::fmt;
+use core::marker::PhantomData;
+use core::mem::{align_of, size_of, MaybeUninit};
+use core::ptr;
+
+/// The largest alignment of a value stored in an [`InlineDynGuard`].
+pub const INLINE_DYN_ALIGN: usize = 16;
+
+#[repr(C, align(16))]
+struct Storage<const N: usize>([MaybeUninit<u8>; N]);
+
+struct VTable {
+    consume: unsafe fn(*mut u8),
+    type_id: fn() -> TypeId,
+    type_name: fn() -> &'static str,
+}
+
+unsafe fn consume_erased<T: Consume>(value: *mut u8) {
+    // SAFETY: the caller passes the storage of a live `T`, which it never uses again.
+    unsafe { ptr::read(value.cast::<T>()) }.consume()
+}
+
+struct VTableFor<T>(PhantomData<T>);
+
+impl<T: Consume + 'static> VTableFor<T> {
+    const VTABLE: VTable = VTable {
+        consume: consume_erased::<T>,
+        type_id: TypeId::of::<T>,
+        type_name: type_name::<T>,
+    };
+}
+
+struct Erased<const N: usize> {
+    storage: Storage<N>,
+    vtable: &'static VTable,
+    // The erased value may be neither `Send` nor `Sync`.
+    _not_send: PhantomData<*mut ()>,
+}
+
+impl<const N: usize> Consume for Erased<N> {
+    #[inline]
+    fn consume(mut self) {
+        // SAFETY: `storage` holds a live value of the type `vtable` was built for.
+        unsafe { (self.vtable.consume)(self.storage.0.as_mut_ptr().cast()) }
+    }
+}
+
+/// A guard whose value, of any type `T: Consume` of at most `N` bytes, is stored inline along
+/// with a vtable to consume it. This erases the type of the value without allocating.
+///
+/// Guards of different types can thus be stored side by side, e.g. in an array, in `no_std`
+/// code or on paths which can't afford the allocation of an [`ErasedGuard`](crate::ErasedGuard).
+/// The value must be `'static`, and aligned to at most [`INLINE_DYN_ALIGN`] bytes. Storing a
+/// value which is too large or too aligned fails to compile:
+///
+/// ```compile_fail
+/// use consume_on_drop::{Closure, InlineDynGuard};
+///
+/// let buffer = [0u8; 64];
+/// let guard = InlineDynGuard::<32>::new(Closure(move || drop(buffer)));
+/// ```
+pub struct InlineDynGuard<const N: usize> {
+    inner: ConsumeOnDrop<Erased<N>>,
+}
+
+impl<const N: usize> InlineDynGuard<N> {
+    /// Stores `value`, which will be consumed when the guard is dropped.
+    #[inline]
+    pub fn new<T: Consume + 'static>(value: T) -> Self {
+        const {
+            assert!(size_of::<T>() <= N, "the value is too large for the guard");
+            assert!(
+                align_of::<T>() <= INLINE_DYN_ALIGN,
+                "the value is too aligned for the guard"
+            );
+        }
+        let mut storage = Storage([MaybeUninit::uninit(); N]);
+        // SAFETY: the storage is large and aligned enough for a `T`, as checked above.
+        unsafe { ptr::write(storage.0.as_mut_ptr().cast::<T>(), value) };
+        Self {
+            inner: ConsumeOnDrop::new(Erased {
+                storage,
+                vtable: &VTableFor::<T>::VTABLE,
+                _not_send: PhantomData,
+            }),
+        }
+    }
+
+    /// Whether the stored value is a `T`.
+    #[inline]
+    pub fn is<T: 'static>(&self) -> bool {
+        (self.inner.vtable.type_id)() == TypeId::of::<T>()
+    }
+
+    /// Returns a reference to the stored value if it is a `T`.
+    #[inline]
+    pub fn downcast_ref<T: 'static>(&self) -> Option<&T> {
+        if self.is::<T>() {
+            // SAFETY: the storage holds a live `T`.
+            Some(unsafe { &*self.inner.storage.0.as_ptr().cast::<T>() })
+        } else {
+            None
+        }
+    }
+
+    /// Returns a mutable reference to the stored value if it is a `T`.
+    #[inline]
+    pub fn downcast_mut<T: 'static>(&mut self) -> Option<&mut T> {
+        if self.is::<T>() {
+            // SAFETY: the storage holds a live `T`.
+            Some(unsafe { &mut *self.inner.storage.0.as_mut_ptr().cast::<T>() })
+        } else {
+            None
+        }
+    }
+
+    /// Extracts the stored value without consuming it, or returns the guard unchanged if the
+    /// value is not a `T`.
+    pub fn downcast<T: 'static>(guard: Self) -> Result<T, Self> {
+        if guard.is::<T>() {
+            let erased = ConsumeOnDrop::into_inner(guard.inner);
+            // SAFETY: the storage holds a live `T`, which `erased` forgets about.
+            Ok(unsafe { ptr::read(erased.storage.0.as_ptr().cast::<T>()) })
+        } else {
+            Err(guard)
+        }
+    }
+
+    /// The name of the type of the stored value, for diagnostics.
+    #[inline]
+    pub fn value_type_name(&self) -> &'static str {
+        (self.inner.vtable.type_name)()
+    }
+}
+
+impl<const N: usize> fmt::Debug for InlineDynGuard<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InlineDynGuard")
+            .field("value_type", &self.value_type_name())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::InlineDynGuard;
+    use crate::{Closure, Consume};
+    use core::cell::Cell;
+    use std::thread_local;
+
+    thread_local! {
+        static CONSUMED: Cell<u32> = const { Cell::new(0) };
+    }
+
+    struct Handle(u32);
+
+    impl Consume for Handle {
+        fn consume(self) {
+            CONSUMED.with(|consumed| consumed.set(consumed.get() + self.0))
+        }
+    }
+
+    #[test]
+    fn heterogeneous_inline_guards() {
+        let mut guards: [InlineDynGuard<16>; 3] = [
+            InlineDynGuard::new(Handle(1)),
+            InlineDynGuard::new(Closure(|| CONSUMED.with(|c| c.set(c.get() + 10)))),
+            InlineDynGuard::new(Handle(100)),
+        ];
+        assert!(guards[1].downcast_ref::<Handle>().is_none());
+        guards[2].downcast_mut::<Handle>().unwrap().0 = 1000;
+        drop(guards);
+        assert_eq!(CONSUMED.with(Cell::get), 1011);
+
+        let guard = InlineDynGuard::<8>::new(Handle(5));
+        let guard = InlineDynGuard::downcast::<u32>(guard).unwrap_err();
+        assert!(guard.value_type_name().ends_with("Handle"));
+        assert_eq!(InlineDynGuard::downcast::<Handle>(guard).ok().unwrap().0, 5);
+        assert_eq!(CONSUMED.with(Cell::get), 1011);
+    }
+}
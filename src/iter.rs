@@ -0,0 +1,95 @@
+use crate::{Consumer, WithConsumer};
+use core::iter::FusedIterator;
+
+/// Extension methods for guarding the items of an [`Iterator`].
+pub trait IteratorGuardExt: Iterator + Sized {
+    /// Wraps every item in a [`WithConsumer`] with a clone of `consumer`, so later stages which
+    /// drop items early can't silently leak them. Items which complete the pipeline can be
+    /// released with [`WithConsumer::into_inner`].
+    ///
+    /// To share one consumer between all items rather than cloning it, pass a reference to it,
+    /// e.g. a `Closure(&f)` for some `f: Fn(T)`.
+    #[inline]
+    fn with_consumer<Q>(self, consumer: Q) -> WithConsumerIter<Self, Q>
+    where
+        Q: Consumer<Self::Item> + Clone,
+    {
+        WithConsumerIter {
+            iter: self,
+            consumer,
+        }
+    }
+}
+
+impl<I: Iterator> IteratorGuardExt for I {}
+
+/// The iterator returned by [`IteratorGuardExt::with_consumer`].
+#[derive(Debug, Clone)]
+#[must_use = "iterators are lazy and do nothing unless consumed"]
+pub struct WithConsumerIter<I, Q> {
+    iter: I,
+    consumer: Q,
+}
+
+impl<I, Q> WithConsumerIter<I, Q> {
+    /// Unwraps the underlying iterator and consumer.
+    #[inline]
+    pub fn into_parts(x: Self) -> (I, Q) {
+        (x.iter, x.consumer)
+    }
+}
+
+impl<I: Iterator, Q: Consumer<I::Item> + Clone> Iterator for WithConsumerIter<I, Q> {
+    type Item = WithConsumer<I::Item, Q>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.iter.next()?;
+        Some(WithConsumer::new(item, self.consumer.clone()))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl<I, Q> DoubleEndedIterator for WithConsumerIter<I, Q>
+where
+    I: DoubleEndedIterator,
+    Q: Consumer<I::Item> + Clone,
+{
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let item = self.iter.next_back()?;
+        Some(WithConsumer::new(item, self.consumer.clone()))
+    }
+}
+
+impl<I, Q> ExactSizeIterator for WithConsumerIter<I, Q>
+where
+    I: ExactSizeIterator,
+    Q: Consumer<I::Item> + Clone,
+{
+}
+
+impl<I: FusedIterator, Q: Consumer<I::Item> + Clone> FusedIterator for WithConsumerIter<I, Q> {}
+
+#[cfg(test)]
+mod tests {
+    use super::IteratorGuardExt;
+    use crate::{Closure, WithConsumer};
+    use core::cell::Cell;
+
+    #[test]
+    fn dropped_items_are_consumed() {
+        let leaked = Cell::new(0);
+        let release = |n| leaked.set(leaked.get() + n);
+        let kept: u32 = (1..=4)
+            .with_consumer(Closure(&release))
+            .filter(|n| **n % 2 == 0)
+            .map(WithConsumer::into_inner)
+            .sum();
+        assert_eq!((kept, leaked.get()), (6, 4));
+    }
+}
@@ -0,0 +1,147 @@
+use crate::{Consumer, WithConsumer};
+use jni::errors::Error;
+use jni::sys::jobject;
+use jni::{JNIEnv, JavaVM};
+
+/// The default error hook used by [`DeleteGlobalRef`]. It silently ignores the error.
+fn ignore_jni_error(_error: Error) {}
+
+/// Copies the handle to a Java VM. A `JavaVM` is only a pointer to the VM, which lives for the
+/// rest of the process.
+fn copy_vm(vm: &JavaVM) -> JavaVM {
+    // SAFETY: the pointer comes from a valid `JavaVM`.
+    unsafe { JavaVM::from_raw(vm.get_java_vm_pointer()) }.expect("a JavaVM pointer is never null")
+}
+
+/// A [`Consumer<jobject>`] which deletes a JNI global reference with `DeleteGlobalRef`.
+///
+/// Global references may be deleted from any thread, but only through the `JNIEnv` of a thread
+/// attached to the VM. If the current thread is not attached, it is attached for the duration
+/// of the call. If attaching fails, the reference is leaked, and the error is passed to the hook
+/// `F`. By default, such errors are ignored.
+#[derive(Debug)]
+pub struct DeleteGlobalRef<F = fn(Error)> {
+    vm: JavaVM,
+    on_error: F,
+}
+
+impl DeleteGlobalRef {
+    /// Builds a consumer which deletes global references created in `vm`.
+    ///
+    /// # Safety
+    ///
+    /// Any reference consumed by the result must be a global reference created in `vm`, and
+    /// must not be used again once consumed.
+    #[inline]
+    pub unsafe fn new(vm: &JavaVM) -> Self {
+        Self {
+            vm: copy_vm(vm),
+            on_error: ignore_jni_error,
+        }
+    }
+}
+
+impl<F: FnOnce(Error)> DeleteGlobalRef<F> {
+    /// Builds a consumer which deletes global references created in `vm`, reporting any error
+    /// to `on_error`.
+    ///
+    /// # Safety
+    ///
+    /// See [`DeleteGlobalRef::new`].
+    #[inline]
+    pub unsafe fn with_hook(vm: &JavaVM, on_error: F) -> Self {
+        Self {
+            vm: copy_vm(vm),
+            on_error,
+        }
+    }
+}
+
+impl<F: Clone> Clone for DeleteGlobalRef<F> {
+    fn clone(&self) -> Self {
+        Self {
+            vm: copy_vm(&self.vm),
+            on_error: self.on_error.clone(),
+        }
+    }
+}
+
+impl<F: FnOnce(Error)> Consumer<jobject> for DeleteGlobalRef<F> {
+    // The unsafe constructors vouch for every reference this consumes.
+    #[allow(clippy::not_unsafe_ptr_arg_deref)]
+    fn consume(self, other: jobject) {
+        let delete = |env: &JNIEnv| {
+            let raw = env.get_raw();
+            // SAFETY: `raw` is the valid `JNIEnv` of the current thread, and the constructors
+            // require that `other` is a global reference which is never used again.
+            // `DeleteGlobalRef` may be called with an exception pending.
+            unsafe {
+                if let Some(delete) = (**raw).DeleteGlobalRef {
+                    delete(raw, other)
+                }
+            }
+        };
+        match self.vm.get_env() {
+            Ok(env) => delete(&env),
+            Err(_) => match self.vm.attach_current_thread() {
+                Ok(env) => delete(&env),
+                Err(error) => (self.on_error)(error),
+            },
+        }
+    }
+}
+
+/// An owned raw JNI global reference which is deleted when dropped, attaching the current
+/// thread to the VM if needed.
+///
+/// Unlike [`jni::objects::GlobalRef`], this adopts references created by raw JNI code, such as
+/// those received through a native method, and errors can be observed by supplying a hook.
+#[derive(Debug)]
+pub struct JniGlobalRef<F: FnOnce(Error) = fn(Error)> {
+    inner: WithConsumer<jobject, DeleteGlobalRef<F>>,
+}
+
+impl JniGlobalRef {
+    /// Takes ownership of `global`, ignoring any error when it is deleted.
+    ///
+    /// # Safety
+    ///
+    /// `global` must be a global reference created in `vm` and owned by the caller.
+    #[inline]
+    pub unsafe fn from_raw(vm: &JavaVM, global: jobject) -> Self {
+        Self {
+            inner: WithConsumer::new(global, DeleteGlobalRef::new(vm)),
+        }
+    }
+}
+
+impl<F: FnOnce(Error)> JniGlobalRef<F> {
+    /// Takes ownership of `global`, reporting any error when it is deleted to `on_error`.
+    ///
+    /// # Safety
+    ///
+    /// `global` must be a global reference created in `vm` and owned by the caller.
+    #[inline]
+    pub unsafe fn with_hook(vm: &JavaVM, global: jobject, on_error: F) -> Self {
+        Self {
+            inner: WithConsumer::new(global, DeleteGlobalRef::with_hook(vm, on_error)),
+        }
+    }
+
+    /// The underlying global reference, which stays owned by `x`.
+    #[inline]
+    pub fn as_raw(x: &Self) -> jobject {
+        *x.inner
+    }
+
+    /// Releases ownership of the global reference without deleting it.
+    #[inline]
+    pub fn into_raw(x: Self) -> jobject {
+        WithConsumer::into_inner(x.inner)
+    }
+}
+
+// SAFETY: global references are valid on every thread, and are only deleted through the `JNIEnv`
+// of the thread doing the deleting.
+unsafe impl<F: FnOnce(Error) + Send> Send for JniGlobalRef<F> {}
+unsafe impl<F: FnOnce(Error) + Sync> Sync for JniGlobalRef<F> {}
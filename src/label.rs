@@ -0,0 +1,207 @@
+use crate::{Consume, ConsumeOnDrop};
+use core::fmt;
+use core::ops::{Deref, DerefMut};
+
+/// A value paired with a human-readable label, such as `"ws-session-42"`, for diagnostics.
+///
+/// When several guards share a type, the label tells them apart. With the `std` feature, the
+/// label is passed to the [`GuardObserver`] installed with [`set_guard_observer`], and is
+/// reported on standard error if consuming the value panics and no observer is installed.
+/// Build one with [`ConsumeOnDrop::new_labeled`].
+///
+/// A `Labeled` value which is forgotten or leaked is never reported, since nothing runs when it
+/// goes missing. To find such values, use `Watched` values from the `watchdog` feature instead,
+/// which are tracked for as long as they are alive.
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Labeled<T, L = &'static str> {
+    value: T,
+    label: L,
+}
+
+impl<T, L> Labeled<T, L> {
+    /// Pairs `value` with `label`.
+    #[inline]
+    pub const fn new(value: T, label: L) -> Self {
+        Self { value, label }
+    }
+
+    /// The label attached to `x`.
+    #[inline]
+    pub fn label(x: &Self) -> &L {
+        &x.label
+    }
+
+    /// Separates the value from its label.
+    #[inline]
+    pub fn into_parts(x: Self) -> (T, L) {
+        (x.value, x.label)
+    }
+
+    /// Extracts the value, dropping the label.
+    #[inline]
+    pub fn into_inner(x: Self) -> T {
+        x.value
+    }
+}
+
+impl<T: Consume, L: fmt::Display> Consume for Labeled<T, L> {
+    #[cfg(not(feature = "std"))]
+    #[inline]
+    fn consume(self) {
+        self.value.consume()
+    }
+
+    #[cfg(feature = "std")]
+    fn consume(self) {
+        observer::consume_labeled(self.value, &self.label)
+    }
+}
+
+impl<T, L> Deref for Labeled<T, L> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T, L> DerefMut for Labeled<T, L> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
+
+impl<T: Consume> ConsumeOnDrop<T> {
+    /// Wraps a `T` in a [`ConsumeOnDrop`] along with a label for diagnostics. See [`Labeled`].
+    #[inline]
+    pub const fn new_labeled<L: fmt::Display>(value: T, label: L) -> ConsumeOnDrop<Labeled<T, L>> {
+        ConsumeOnDrop::new(Labeled::new(value, label))
+    }
+}
+
+#[cfg(feature = "std")]
+pub use self::observer::*;
+
+#[cfg(feature = "std")]
+pub(crate) mod observer {
+    use crate::Consume;
+    use core::fmt::Display;
+    use std::sync::OnceLock;
+
+    /// Receives notifications about [`Labeled`](crate::Labeled) values as they are consumed.
+    ///
+    /// Install one for the whole program with [`set_guard_observer`].
+    pub trait GuardObserver: Sync {
+        /// Called just before the value labeled `label` is consumed.
+        fn consuming(&self, label: &dyn Display) {
+            let _ = label;
+        }
+
+        /// Called while unwinding if consuming the value labeled `label` panicked.
+        fn panicked(&self, label: &dyn Display) {
+            let _ = label;
+        }
+
+        /// Called by [`report_long_held`](crate::report_long_held) when the
+        /// [`Watched`](crate::Watched) value labeled `label` has been alive for `held`.
+        #[cfg(feature = "watchdog")]
+        fn held_too_long(&self, label: &dyn Display, held: core::time::Duration) {
+            let _ = (label, held);
+        }
+    }
+
+    static OBSERVER: OnceLock<&'static dyn GuardObserver> = OnceLock::new();
+
+    #[cfg_attr(not(feature = "watchdog"), allow(dead_code))]
+    pub(crate) fn observer() -> Option<&'static dyn GuardObserver> {
+        OBSERVER.get().copied()
+    }
+
+    /// Installs the program-wide [`GuardObserver`]. Only one observer can ever be installed; if
+    /// one already is, `observer` is handed back.
+    pub fn set_guard_observer(
+        observer: &'static dyn GuardObserver,
+    ) -> Result<(), &'static dyn GuardObserver> {
+        OBSERVER.set(observer)
+    }
+
+    /// Reports a panic in progress, unless it is disarmed by being forgotten.
+    struct PanicReport<'a>(&'a dyn Display, Option<&'a dyn GuardObserver>);
+
+    impl Drop for PanicReport<'_> {
+        fn drop(&mut self) {
+            match self.1 {
+                Some(observer) => observer.panicked(self.0),
+                None => std::eprintln!("guard `{}` panicked while being consumed", self.0),
+            }
+        }
+    }
+
+    pub(crate) fn consume_labeled<T: Consume>(value: T, label: &dyn Display) {
+        consume_observed(value, label, observer())
+    }
+
+    /// Consumes `value`, reporting to `observer` rather than to the program-wide observer.
+    pub(crate) fn consume_observed<T: Consume>(
+        value: T,
+        label: &dyn Display,
+        observer: Option<&dyn GuardObserver>,
+    ) {
+        if let Some(observer) = observer {
+            observer.consuming(label)
+        }
+        let report = PanicReport(label, observer);
+        value.consume();
+        core::mem::forget(report);
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::{set_guard_observer, GuardObserver};
+    use crate::{Closure, ConsumeOnDrop};
+    use core::cell::RefCell;
+    use core::fmt::Display;
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+    use std::string::{String, ToString};
+    use std::vec::Vec;
+
+    std::thread_local! {
+        // The observer is shared by every test, so it only records the events of each thread.
+        static EVENTS: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+    }
+
+    struct Recorder;
+
+    impl GuardObserver for Recorder {
+        fn consuming(&self, label: &dyn Display) {
+            EVENTS.with_borrow_mut(|events| events.push(std::format!("consuming {}", label)))
+        }
+
+        fn panicked(&self, label: &dyn Display) {
+            EVENTS.with_borrow_mut(|events| events.push(std::format!("panicked {}", label)))
+        }
+    }
+
+    #[test]
+    fn labels_reach_observer() {
+        set_guard_observer(&Recorder).ok().unwrap();
+        let mut count = 0;
+        drop(ConsumeOnDrop::new_labeled(
+            Closure(|| count += 1),
+            "ws-session-42",
+        ));
+        assert_eq!(count, 1);
+        let session = 7;
+        let guard = ConsumeOnDrop::new_labeled(Closure(|| panic!("boom")), session.to_string());
+        assert!(catch_unwind(AssertUnwindSafe(|| drop(guard))).is_err());
+        EVENTS.with_borrow(|events| {
+            assert_eq!(
+                *events,
+                ["consuming ws-session-42", "consuming 7", "panicked 7"]
+            )
+        });
+    }
+}
@@ -0,0 +1,78 @@
+/// Statically asserts that guards around the given types are zero-cost: each guard must have
+/// the same size and alignment as the type it guards, or compilation fails.
+///
+/// A plain type `T` checks [`ConsumeOnDrop<T>`](crate::ConsumeOnDrop), and `T => Q` checks
+/// [`WithConsumer<T, Q>`](crate::WithConsumer), which holds only when the consumer `Q` is
+/// zero-sized. The macro can be used anywhere an item can, and costs nothing at runtime.
+///
+/// ```
+/// use consume_on_drop::{assert_transparent, Consume, Consumer};
+///
+/// struct Handle(*mut u8);
+///
+/// impl Consume for Handle {
+///     fn consume(self) {}
+/// }
+///
+/// struct Free;
+///
+/// impl Consumer<*mut u8> for Free {
+///     fn consume(self, _ptr: *mut u8) {}
+/// }
+///
+/// assert_transparent!(Handle, *mut u8 => Free);
+/// ```
+///
+/// A consumer which carries data, such as a function pointer, fails the check:
+///
+/// ```compile_fail
+/// use consume_on_drop::{assert_transparent, Closure};
+///
+/// assert_transparent!(u32 => Closure<fn(u32)>);
+/// ```
+#[macro_export]
+macro_rules! assert_transparent {
+    (@one $t:ty) => {
+        const _: () = {
+            ::core::assert!(
+                ::core::mem::size_of::<$crate::ConsumeOnDrop<$t>>() == ::core::mem::size_of::<$t>(),
+                ::core::concat!("`ConsumeOnDrop<", ::core::stringify!($t), ">` is larger than the type it guards"),
+            );
+            ::core::assert!(
+                ::core::mem::align_of::<$crate::ConsumeOnDrop<$t>>() == ::core::mem::align_of::<$t>(),
+                ::core::concat!("`ConsumeOnDrop<", ::core::stringify!($t), ">` is aligned differently from the type it guards"),
+            );
+        };
+    };
+    (@one $t:ty => $q:ty) => {
+        const _: () = {
+            ::core::assert!(
+                ::core::mem::size_of::<$crate::WithConsumer<$t, $q>>() == ::core::mem::size_of::<$t>(),
+                ::core::concat!("`WithConsumer<", ::core::stringify!($t), ", ", ::core::stringify!($q), ">` is larger than the type it guards"),
+            );
+            ::core::assert!(
+                ::core::mem::align_of::<$crate::WithConsumer<$t, $q>>() == ::core::mem::align_of::<$t>(),
+                ::core::concat!("`WithConsumer<", ::core::stringify!($t), ", ", ::core::stringify!($q), ">` is aligned differently from the type it guards"),
+            );
+        };
+    };
+    ($($t:ty $(=> $q:ty)?),+ $(,)?) => {
+        $($crate::assert_transparent!(@one $t $(=> $q)?);)+
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Closure, Consumer};
+
+    struct Discard;
+
+    impl<T> Consumer<T> for Discard {
+        fn consume(self, _other: T) {}
+    }
+
+    assert_transparent!(
+        Closure<fn()>,
+        [u64; 3] => Discard,
+    );
+}
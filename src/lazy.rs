@@ -0,0 +1,246 @@
+use crate::{Consume, ConsumeOnDrop};
+use core::cell::{Cell, OnceCell};
+use core::fmt;
+use core::ops::Deref;
+
+/// A [`OnceCell`] whose value, if it was ever initialized, is consumed when the cell is
+/// dropped. Build one with `ConsumeOnDrop::new(OnceCell::new())`, which is `const`.
+pub type GuardedOnceCell<T> = ConsumeOnDrop<OnceCell<T>>;
+
+/// A [`OnceLock`](std::sync::OnceLock) whose value, if it was ever initialized, is consumed
+/// when the lock is dropped. Build one with `ConsumeOnDrop::new(OnceLock::new())`, which is
+/// `const`.
+#[cfg(feature = "std")]
+pub type GuardedOnceLock<T> = ConsumeOnDrop<std::sync::OnceLock<T>>;
+
+#[cfg(feature = "std")]
+pub use self::sync::*;
+
+const POISONED: &str = "the initializer of a guarded lazy value panicked";
+
+struct LazyState<T, F> {
+    cell: OnceCell<T>,
+    init: Cell<Option<F>>,
+}
+
+impl<T: Consume, F> Consume for LazyState<T, F> {
+    #[inline]
+    fn consume(self) {
+        self.cell.consume()
+    }
+}
+
+/// A value which is created by `F` on first access, and consumed when dropped, but only if it
+/// was ever created.
+///
+/// This suits expensive resources, such as GPU pipelines or connections, which may never be
+/// needed, but need teardown if they were. If `F` panics, the value is never created, and later
+/// accesses panic as well. For a lazy value which can be shared between threads, see
+/// [`GuardedLazyLock`].
+pub struct GuardedLazy<T: Consume, F = fn() -> T> {
+    inner: ConsumeOnDrop<LazyState<T, F>>,
+}
+
+impl<T: Consume, F: FnOnce() -> T> GuardedLazy<T, F> {
+    /// Builds a lazy value which will be created by `init`.
+    #[inline]
+    pub const fn new(init: F) -> Self {
+        Self {
+            inner: ConsumeOnDrop::new(LazyState {
+                cell: OnceCell::new(),
+                init: Cell::new(Some(init)),
+            }),
+        }
+    }
+
+    /// Creates the value if needed, and returns it.
+    pub fn force(x: &Self) -> &T {
+        x.inner.cell.get_or_init(|| match x.inner.init.take() {
+            Some(init) => init(),
+            None => panic!("{}", POISONED),
+        })
+    }
+
+    /// The value, if it has been created.
+    #[inline]
+    pub fn get(x: &Self) -> Option<&T> {
+        x.inner.cell.get()
+    }
+
+    /// Extracts the value without consuming it, or returns the initializer if the value was
+    /// never created.
+    ///
+    /// Panics if the initializer panicked.
+    pub fn into_inner(x: Self) -> Result<T, F> {
+        let state = ConsumeOnDrop::into_inner(x.inner);
+        match state.cell.into_inner() {
+            Some(value) => Ok(value),
+            None => Err(state.init.into_inner().expect(POISONED)),
+        }
+    }
+}
+
+impl<T: Consume + fmt::Debug, F> fmt::Debug for GuardedLazy<T, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("GuardedLazy")
+            .field(&self.inner.cell.get())
+            .finish()
+    }
+}
+
+impl<T: Consume, F: FnOnce() -> T> Deref for GuardedLazy<T, F> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        Self::force(self)
+    }
+}
+
+#[cfg(feature = "std")]
+mod sync {
+    use super::POISONED;
+    use crate::{Consume, ConsumeOnDrop};
+    use core::fmt;
+    use core::ops::Deref;
+    use std::sync::{Mutex, OnceLock};
+
+    struct LazyState<T, F> {
+        lock: OnceLock<T>,
+        init: Mutex<Option<F>>,
+    }
+
+    impl<T: Consume, F> Consume for LazyState<T, F> {
+        #[inline]
+        fn consume(self) {
+            self.lock.consume()
+        }
+    }
+
+    /// A value which is created by `F` on first access from any thread, and consumed when
+    /// dropped, but only if it was ever created.
+    ///
+    /// This is the thread-safe counterpart of [`GuardedLazy`](super::GuardedLazy). It can be
+    /// a `static`, though statics are never dropped, so their values are never consumed.
+    pub struct GuardedLazyLock<T: Consume, F = fn() -> T> {
+        inner: ConsumeOnDrop<LazyState<T, F>>,
+    }
+
+    impl<T: Consume, F: FnOnce() -> T> GuardedLazyLock<T, F> {
+        /// Builds a lazy value which will be created by `init`.
+        #[inline]
+        pub const fn new(init: F) -> Self {
+            Self {
+                inner: ConsumeOnDrop::new(LazyState {
+                    lock: OnceLock::new(),
+                    init: Mutex::new(Some(init)),
+                }),
+            }
+        }
+
+        /// Creates the value if needed, and returns it. Other threads accessing the value in
+        /// the meantime wait for it to be created.
+        pub fn force(x: &Self) -> &T {
+            x.inner.lock.get_or_init(|| {
+                let init = x
+                    .inner
+                    .init
+                    .lock()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner())
+                    .take();
+                init.expect(POISONED)()
+            })
+        }
+
+        /// The value, if it has been created.
+        #[inline]
+        pub fn get(x: &Self) -> Option<&T> {
+            x.inner.lock.get()
+        }
+
+        /// Extracts the value without consuming it, or returns the initializer if the value was
+        /// never created.
+        ///
+        /// Panics if the initializer panicked.
+        pub fn into_inner(x: Self) -> Result<T, F> {
+            let state = ConsumeOnDrop::into_inner(x.inner);
+            match state.lock.into_inner() {
+                Some(value) => Ok(value),
+                None => Err(state
+                    .init
+                    .into_inner()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner())
+                    .expect(POISONED)),
+            }
+        }
+    }
+
+    impl<T: Consume + fmt::Debug, F> fmt::Debug for GuardedLazyLock<T, F> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_tuple("GuardedLazyLock")
+                .field(&self.inner.lock.get())
+                .finish()
+        }
+    }
+
+    impl<T: Consume, F: FnOnce() -> T> Deref for GuardedLazyLock<T, F> {
+        type Target = T;
+
+        #[inline]
+        fn deref(&self) -> &T {
+            Self::force(self)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{GuardedLazy, GuardedOnceCell};
+    use crate::{Closure, ConsumeOnDrop};
+    use core::cell::{Cell, OnceCell};
+
+    #[test]
+    fn consumes_only_if_initialized() {
+        let created = Cell::new(0);
+        let consumed = Cell::new(0);
+        let connect = || {
+            created.set(created.get() + 1);
+            Closure(|| consumed.set(consumed.get() + 1))
+        };
+
+        drop(GuardedLazy::new(connect));
+        assert_eq!((created.get(), consumed.get()), (0, 0));
+
+        let lazy = GuardedLazy::new(connect);
+        let _: &Closure<_> = &lazy;
+        assert!(GuardedLazy::get(&lazy).is_some());
+        drop(lazy);
+        assert_eq!((created.get(), consumed.get()), (1, 1));
+
+        let cell: GuardedOnceCell<_> = ConsumeOnDrop::new(OnceCell::new());
+        cell.get_or_init(connect);
+        drop(cell);
+        assert_eq!((created.get(), consumed.get()), (2, 2));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn lazy_lock_shared_between_threads() {
+        use super::GuardedLazyLock;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static CONSUMED: AtomicUsize = AtomicUsize::new(0);
+        let pool = GuardedLazyLock::new(|| {
+            Closure(|| {
+                CONSUMED.fetch_add(1, Ordering::Relaxed);
+            })
+        });
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| GuardedLazyLock::force(&pool));
+            }
+        });
+        drop(pool);
+        assert_eq!(CONSUMED.load(Ordering::Relaxed), 1);
+    }
+}
@@ -1,6 +1,12 @@
+//! A zero-cost abstraction that allows [`Drop::drop`] to consume `self` by value.
+//!
+//! Implement [`Consume`] for your type and wrap it in a [`ConsumeOnDrop`], or pair any value
+//! with a [`Consumer`] using [`WithConsumer`].
 #![no_std]
-#[warn(missing_docs)]
+#![warn(missing_docs)]
 extern crate alloc;
+#[cfg(any(feature = "std", test))]
+extern crate std;
 
 /// This trait is for types with a specified means of consumption.
 /// It is a counterpart to [`Drop`]. While [`Drop::drop`] takes `self`
@@ -14,19 +20,292 @@ pub trait Consume {
     fn consume(self);
 }
 
-impl<T: FnOnce()> Consume for T {
+#[cfg(feature = "derive")]
+pub use consume_on_drop_derive::Consume;
+
+/// Adapts a closure or function pointer into a [`Consume`] or a [`Consumer`].
+///
+/// `Closure<F>` implements [`Consume`] when `F: FnOnce()`, and [`Consumer<T>`] when
+/// `F: FnOnce(T)`. These impls live on an adapter rather than on every `FnOnce` so that
+/// downstream crates are free to write their own generic impls of [`Consume`] and [`Consumer`].
+#[repr(transparent)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Closure<F>(pub F);
+
+impl<F: FnOnce()> Consume for Closure<F> {
+    #[inline]
     fn consume(self) {
-        self()
+        (self.0)()
     }
 }
 
+pub use crate::async_consume::*;
+pub use crate::brand::*;
+pub use crate::cleanup_future::*;
+pub use crate::cold::*;
+pub use crate::composite::*;
 pub use crate::consume_on_drop::*;
+pub use crate::context::*;
+pub use crate::family::*;
+pub use crate::ffi_guards::*;
+pub use crate::finish::*;
+pub use crate::format::*;
+pub use crate::guard::*;
+pub use crate::guard_cell::*;
+pub use crate::inline_dyn::*;
+pub use crate::iter::*;
+pub use crate::label::*;
+pub use crate::lazy::*;
+pub use crate::opaque::*;
+pub use crate::policy::*;
+pub use crate::read_only::*;
+pub use crate::realtime::*;
+pub use crate::snapshot::*;
+pub use crate::staged::*;
+pub use crate::transition::*;
+pub use crate::try_consume::*;
 pub use crate::with_consumer::*;
 
+#[cfg(feature = "alloc")]
+pub use crate::alloc_guard::*;
+#[cfg(feature = "anyhow")]
+pub use crate::anyhow_support::*;
+#[cfg(feature = "bumpalo")]
+pub use crate::arena::*;
+#[cfg(feature = "alloc")]
+pub use crate::async_drop_bag::*;
+#[cfg(feature = "std")]
+pub use crate::atomic_file::*;
+#[cfg(all(feature = "serde", feature = "std"))]
+pub use crate::audit::*;
+#[cfg(feature = "std")]
+pub use crate::barrier::*;
+#[cfg(feature = "std")]
+pub use crate::builder::*;
+#[cfg(feature = "alloc")]
+pub use crate::chain::*;
+#[cfg(feature = "std")]
+pub use crate::checkpoint::*;
+#[cfg(feature = "alloc")]
+pub use crate::cycle::*;
+#[cfg(feature = "std")]
+pub use crate::clock::*;
+#[cfg(feature = "std")]
+pub use crate::completion::*;
+#[cfg(feature = "std")]
+pub use crate::debug_dump::*;
+#[cfg(feature = "alloc")]
+pub use crate::deferred::*;
+#[cfg(feature = "alloc")]
+pub use crate::drop_bag::*;
+#[cfg(feature = "alloc")]
+pub use crate::dyn_guard::*;
+#[cfg(feature = "std")]
+pub use crate::env::*;
+#[cfg(feature = "windows")]
+pub use crate::com::*;
+#[cfg(feature = "crossbeam-epoch")]
+pub use crate::epoch::*;
+#[cfg(feature = "alloc")]
+pub use crate::erased::*;
+#[cfg(feature = "fault-inject")]
+pub use crate::fault::*;
+#[cfg(all(feature = "std", any(unix, windows)))]
+pub use crate::file_lock::*;
+#[cfg(feature = "jni")]
+pub use crate::jni_ref::*;
+#[cfg(all(feature = "mlock", unix))]
+pub use crate::mlock::*;
+#[cfg(all(feature = "std", unix))]
+pub use crate::mmap::*;
+#[cfg(feature = "napi")]
+pub use crate::napi_ref::*;
+#[cfg(feature = "std")]
+pub use crate::net::*;
+#[cfg(feature = "std")]
+pub use crate::once_consumer::*;
+#[cfg(all(feature = "std", any(unix, windows)))]
+pub use crate::os_handle::*;
+#[cfg(feature = "alloc")]
+pub use crate::phases::*;
+#[cfg(feature = "std")]
+pub use crate::poison::*;
+#[cfg(feature = "pyo3")]
+pub use crate::python::*;
+#[cfg(feature = "alloc")]
+pub use crate::retire_queue::*;
+#[cfg(feature = "std")]
+pub use crate::registry::*;
+#[cfg(feature = "alloc")]
+pub use crate::requeue::*;
+#[cfg(feature = "std")]
+pub use crate::revocable::*;
+#[cfg(feature = "alloc")]
+pub use crate::scope::*;
+#[cfg(feature = "std")]
+pub use crate::scoped_tasks::*;
+#[cfg(feature = "serde")]
+pub use crate::serde_support::*;
+#[cfg(feature = "std")]
+pub use crate::shutdown::*;
+#[cfg(feature = "tracing-error")]
+pub use crate::span_trace::*;
+#[cfg(feature = "stable-abi")]
+pub use crate::stable_abi::*;
+#[cfg(feature = "stats")]
+pub use crate::stats::*;
+#[cfg(feature = "futures")]
+pub use crate::stream::*;
+#[cfg(all(feature = "termios", unix))]
+pub use crate::terminal::*;
+#[cfg(feature = "alloc")]
+pub use crate::transaction::*;
+#[cfg(feature = "wasm-bindgen")]
+pub use crate::wasm::*;
+#[cfg(feature = "watchdog")]
+pub use crate::watchdog::*;
+
+#[cfg(feature = "alloc")]
+mod alloc_guard;
+#[cfg(feature = "anyhow")]
+mod anyhow_support;
+#[cfg(feature = "bumpalo")]
+mod arena;
+mod async_consume;
+#[cfg(feature = "alloc")]
+mod async_drop_bag;
+#[cfg(feature = "std")]
+mod atomic_file;
+#[cfg(all(feature = "serde", feature = "std"))]
+mod audit;
+#[cfg(feature = "alloc")]
+mod collections;
+mod brand;
+#[cfg(feature = "std")]
+mod barrier;
+#[cfg(feature = "std")]
+mod builder;
+mod cells;
+#[cfg(feature = "alloc")]
+mod chain;
+#[cfg(feature = "std")]
+mod checkpoint;
+mod cleanup_future;
+mod cold;
+#[cfg(feature = "windows")]
+mod com;
+mod composite;
+mod context;
+#[cfg(feature = "alloc")]
+mod cycle;
+#[cfg(feature = "std")]
+mod clock;
+#[cfg(feature = "std")]
+mod completion;
+#[cfg(feature = "std")]
+mod debug_dump;
+#[cfg(feature = "alloc")]
+mod deferred;
+#[cfg(feature = "alloc")]
+mod drop_bag;
+#[cfg(feature = "alloc")]
+mod dyn_guard;
+#[cfg(feature = "std")]
+mod env;
+#[cfg(feature = "crossbeam-epoch")]
+mod epoch;
+#[cfg(feature = "alloc")]
+mod erased;
+#[cfg(feature = "fault-inject")]
+mod fault;
+mod family;
+mod ffi_guards;
+#[cfg(all(feature = "std", any(unix, windows)))]
+mod file_lock;
+mod finish;
+mod format;
+mod guard;
+mod guard_cell;
+mod inline_dyn;
+mod iter;
+#[cfg(feature = "jni")]
+mod jni_ref;
+mod label;
+mod layout;
+mod lazy;
+#[cfg(all(feature = "mlock", unix))]
+mod mlock;
+#[cfg(all(feature = "std", unix))]
+mod mmap;
+#[cfg(feature = "napi")]
+mod napi_ref;
+#[cfg(feature = "std")]
+mod net;
+#[cfg(feature = "std")]
+mod once_consumer;
+mod opaque;
+#[cfg(feature = "ops")]
+mod operators;
+#[cfg(all(feature = "std", any(unix, windows)))]
+mod os_handle;
+#[cfg(feature = "alloc")]
+mod phases;
+#[cfg(feature = "std")]
+mod poison;
+mod policy;
+mod project;
+#[cfg(feature = "pyo3")]
+mod python;
+mod read_only;
+mod realtime;
+#[cfg(feature = "alloc")]
+mod retire_queue;
+#[cfg(feature = "std")]
+mod registry;
+#[cfg(feature = "alloc")]
+mod requeue;
+#[cfg(feature = "std")]
+mod revocable;
+mod scope;
+#[cfg(feature = "std")]
+mod scoped_tasks;
+#[cfg(feature = "serde")]
+mod serde_support;
+#[cfg(feature = "std")]
+mod shutdown;
+mod snapshot;
+#[cfg(feature = "tracing-error")]
+mod span_trace;
+#[cfg(feature = "stable-abi")]
+mod stable_abi;
+mod staged;
+#[cfg(feature = "stats")]
+mod stats;
+#[cfg(feature = "futures")]
+mod stream;
+#[cfg(feature = "alloc")]
+mod sync;
+#[cfg(all(feature = "termios", unix))]
+mod terminal;
+#[cfg(feature = "alloc")]
+mod transaction;
+mod transition;
+mod try_consume;
+#[cfg(feature = "wasm-bindgen")]
+mod wasm;
+#[cfg(feature = "watchdog")]
+mod watchdog;
+mod wrapper;
+
 mod consume_on_drop {
     use super::Consume;
-    use core::mem::ManuallyDrop;
+    use crate::{SelfConsumer, WithConsumer};
+    #[cfg(feature = "alloc")]
+    use alloc::boxed::Box;
+    use core::mem::{ManuallyDrop, MaybeUninit};
     use core::ops::{Deref, DerefMut};
+    use core::pin::Pin;
+    use core::ptr;
 
     /// A zero-overhead wrapper around `T`. When a [`ConsumeOnDrop<T>`] is dropped,
     /// the underlying `T` is [`Consume::consume`]d.
@@ -47,16 +326,152 @@ mod consume_on_drop {
 
         /// Unwraps the underlying `T`.
         #[inline]
-        pub fn into_inner(slot: Self) -> T {
-            let mut slot = ManuallyDrop::new(slot);
+        pub const fn into_inner(slot: Self) -> T {
+            let slot = ManuallyDrop::new(slot);
             unsafe {
                 // SAFETY: we never use slot after this function is called, since
                 // we take it by value and Self is not Copy. We also don't use slot
                 // again in this function, since we moved it in a ManuallyDrop to prevent
-                // accidentally dropping it.
-                ManuallyDrop::take(&mut slot.inner)
+                // accidentally dropping it. The cast is sound because `ManuallyDrop<Self>`,
+                // `Self`, and `ManuallyDrop<T>` are all `#[repr(transparent)]` around `T`.
+                ptr::read((&slot as *const ManuallyDrop<Self>).cast::<T>())
+            }
+        }
+
+        /// Consumes the underlying `T` now, exactly as dropping `slot` would, but by name.
+        #[inline]
+        pub fn consume_now(slot: Self) {
+            Self::into_inner(slot).consume()
+        }
+
+        /// Provides a reference to the underlying `T`. Unlike [`Deref::deref`], this can be
+        /// used in `const` contexts.
+        #[inline]
+        pub const fn get_ref(slot: &Self) -> &T {
+            // SAFETY: `ManuallyDrop<T>` is a `#[repr(transparent)]` wrapper around `T`.
+            unsafe { &*(&slot.inner as *const ManuallyDrop<T>).cast::<T>() }
+        }
+
+        /// Provides a mutable reference to the underlying `T`. Unlike [`DerefMut::deref_mut`],
+        /// this can be used in `const` contexts.
+        #[inline]
+        pub const fn get_mut(slot: &mut Self) -> &mut T {
+            // SAFETY: `ManuallyDrop<T>` is a `#[repr(transparent)]` wrapper around `T`.
+            unsafe { &mut *(&mut slot.inner as *mut ManuallyDrop<T>).cast::<T>() }
+        }
+
+        /// Converts `slot` into a [`WithConsumer`] which consumes the `T` with
+        /// [`Consume::consume`], like `slot` would.
+        #[inline]
+        pub fn into_with_consumer(slot: Self) -> WithConsumer<T, SelfConsumer> {
+            WithConsumer::new(Self::into_inner(slot), SelfConsumer)
+        }
+
+        /// Attempts to convert the underlying `T` into a `U` with `f`. On success, the `U` is
+        /// returned in a new guard. On failure, `f` hands back the `T` along with an error, and
+        /// the `T` is returned in a guard alongside that error.
+        ///
+        /// If `f` panics, the `T` belongs to `f` and is dropped rather than consumed, unless `f`
+        /// guards it itself.
+        #[inline]
+        pub fn try_map<U: Consume, E>(
+            slot: Self,
+            f: impl FnOnce(T) -> Result<U, (T, E)>,
+        ) -> Result<ConsumeOnDrop<U>, (Self, E)> {
+            match f(Self::into_inner(slot)) {
+                Ok(value) => Ok(ConsumeOnDrop::new(value)),
+                Err((value, error)) => Err((Self::new(value), error)),
             }
         }
+
+        /// Wraps a `T` in a [`ConsumeOnDrop`] and pins it on the heap.
+        ///
+        /// Since [`Consume::consume`] takes the `T` by value, a [`ConsumeOnDrop`] never pins its
+        /// contents structurally: the `T` is moved out when it is consumed. Pinned projections
+        /// are therefore only available when `T: Unpin`. To guard a `!Unpin` future, guard a
+        /// pinned pointer to it instead, e.g. a `Pin<Box<F>>`, or a `Pin<&mut F>` obtained from
+        /// [`core::pin::pin!`].
+        #[cfg(feature = "alloc")]
+        #[inline]
+        pub fn pin(value: T) -> Pin<Box<Self>> {
+            Box::pin(Self::new(value))
+        }
+
+        /// Moves a `T` to the heap and guards the box, which is consumed by consuming the `T`.
+        ///
+        /// Prefer this over [`ConsumeOnDrop::new`] when `T` is large, since the guard can then
+        /// be moved around for the price of a pointer, or when the `T` must stay at a fixed
+        /// address, e.g. because a pointer to it has been handed to foreign code. The `T` is
+        /// moved out of the box only when it is finally consumed. To avoid even the initial
+        /// move, see [`ConsumeOnDrop::new_with_uninit`].
+        #[cfg(feature = "alloc")]
+        #[inline]
+        pub fn boxed(value: T) -> ConsumeOnDrop<Box<T>> {
+            ConsumeOnDrop::new(Box::new(value))
+        }
+
+        /// Projects an uninitialized guard onto its uninitialized `T`, so that a large `T` can
+        /// be written in place instead of being moved through [`ConsumeOnDrop::new`].
+        ///
+        /// Once the `T` is fully initialized, `slot` holds a valid guard, which may be
+        /// obtained with [`MaybeUninit::assume_init_mut`] and friends. Since [`MaybeUninit`]
+        /// never drops its contents, remember that the guard must then be dropped or read out
+        /// explicitly for the `T` to be consumed.
+        #[inline]
+        pub fn uninit_inner(slot: &mut MaybeUninit<Self>) -> &mut MaybeUninit<T> {
+            // SAFETY: `Self` is a `#[repr(transparent)]` wrapper around a `ManuallyDrop<T>`,
+            // which is itself a `#[repr(transparent)]` wrapper around `T`.
+            unsafe { &mut *slot.as_mut_ptr().cast::<MaybeUninit<T>>() }
+        }
+
+        /// Allocates a guard on the heap and initializes its `T` in place with `init`, avoiding
+        /// any copies of the `T` on the stack.
+        ///
+        /// `init` must return the reference it was given, e.g. by finishing with
+        /// [`MaybeUninit::write`] or [`MaybeUninit::assume_init_mut`]. Returning any other
+        /// reference panics. If `init` panics, the allocation is freed and nothing is consumed.
+        #[cfg(feature = "alloc")]
+        pub fn new_with_uninit(init: impl FnOnce(&mut MaybeUninit<T>) -> &mut T) -> Box<Self> {
+            let mut slot = Box::<Self>::new_uninit();
+            let expected = slot.as_mut_ptr().cast::<T>();
+            let initialized: *mut T = init(Self::uninit_inner(&mut slot));
+            assert!(
+                core::ptr::eq(initialized, expected),
+                "`init` must return the reference it was given"
+            );
+            // SAFETY: `init` produced a `&mut T` to the slot, which is only possible once the
+            // `T` has been initialized, and a guard has the same layout as its `T`.
+            unsafe { slot.assume_init() }
+        }
+    }
+
+    /// Moves the only non-zero-sized field out of `wrapper` in a `const fn`, where moving out
+    /// of a generic value which needs dropping is not yet allowed.
+    ///
+    /// # Safety
+    ///
+    /// `W` must be a `#[repr(transparent)]` wrapper around `T` with no [`Drop`] impl of its own.
+    #[inline]
+    pub(crate) const unsafe fn unwrap_transparent<W, T>(wrapper: W) -> T {
+        let wrapper = ManuallyDrop::new(wrapper);
+        // SAFETY: the caller guarantees that `W` has the layout of `T`, and since `W` has no
+        // `Drop` impl of its own, taking the `T` and forgetting the `W` is the same as moving
+        // the field out.
+        unsafe { ptr::read((&wrapper as *const ManuallyDrop<W>).cast::<T>()) }
+    }
+
+    impl<T: Consume + Unpin> ConsumeOnDrop<T> {
+        /// Projects a pinned shared reference to the guard onto the underlying `T`.
+        #[inline]
+        pub fn as_pin_ref(self: Pin<&Self>) -> Pin<&T> {
+            Pin::new(self.get_ref().inner.deref())
+        }
+
+        /// Projects a pinned mutable reference to the guard onto the underlying `T`.
+        #[inline]
+        pub fn as_pin_mut(self: Pin<&mut Self>) -> Pin<&mut T> {
+            Pin::new(self.get_mut().inner.deref_mut())
+        }
     }
 
     impl<T: Consume> Deref for ConsumeOnDrop<T> {
@@ -64,19 +479,20 @@ mod consume_on_drop {
 
         #[inline]
         fn deref(&self) -> &Self::Target {
-            self.inner.deref()
+            Self::get_ref(self)
         }
     }
 
     impl<T: Consume> DerefMut for ConsumeOnDrop<T> {
         #[inline]
         fn deref_mut(&mut self) -> &mut Self::Target {
-            self.inner.deref_mut()
+            Self::get_mut(self)
         }
     }
 
     impl<T: Consume> Drop for ConsumeOnDrop<T> {
-        #[inline]
+        #[cfg_attr(not(feature = "cold-drop"), inline)]
+        #[cfg_attr(feature = "cold-drop", cold, inline(never))]
         fn drop(&mut self) {
             unsafe {
                 // SAFETY: It is impossible to use self.inner again after Drop is called.
@@ -86,33 +502,41 @@ mod consume_on_drop {
     }
 }
 
-// Note: this module doesn't use the "unsafe" keyword. It's purely
-// a safe abstraction on top of the `consume_on_drop` module.
+// Note: this module only uses the "unsafe" keyword to move out of a `WithConsumer`
+// in `const fn`s. Otherwise, it's purely a safe abstraction on top of the `guard` module.
 mod with_consumer {
+    use super::consume_on_drop::unwrap_transparent;
     use super::ConsumeOnDrop;
-    use crate::Consume;
+    use crate::{Closure, Consume, Guard};
+    use core::fmt;
+    use core::mem;
     use core::ops::{Deref, DerefMut};
+    use core::pin::Pin;
 
     /// A type implementing [`Consumer<T>`] is one which can consume a value
-    /// of type `T`. In particular, any `FnOnce(T)` is also a [`Consumer<T>`].
+    /// of type `T`. Any `FnOnce(T)` can be used as a [`Consumer<T>`] by wrapping
+    /// it in a [`Closure`].
     pub trait Consumer<T> {
+        /// Consumes `other`, using up `self` in the process.
         fn consume(self, other: T);
     }
 
-    impl<T, Q: FnOnce(T)> Consumer<T> for Q {
+    impl<T, F: FnOnce(T)> Consumer<T> for Closure<F> {
         #[inline]
         fn consume(self, other: T) {
-            self(other)
+            (self.0)(other)
         }
     }
 
-    #[derive(Default, Debug, Clone)]
-    struct RawWithConsumer<T, Q>(T, Q);
+    /// The [`Consumer<T>`] which consumes a `T: Consume` with [`Consume::consume`]. It is
+    /// zero-sized, so a [`WithConsumer<T, SelfConsumer>`] is as cheap as a [`ConsumeOnDrop<T>`].
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    pub struct SelfConsumer;
 
-    impl<T, Q: Consumer<T>> Consume for RawWithConsumer<T, Q> {
+    impl<T: Consume> Consumer<T> for SelfConsumer {
         #[inline]
-        fn consume(self) {
-            self.1.consume(self.0)
+        fn consume(self, other: T) {
+            other.consume()
         }
     }
 
@@ -127,9 +551,17 @@ mod with_consumer {
     /// could cause some inconvenience. If this is inconvenient, defunctionalize `Q` by implementing
     /// a specific struct, or use [impl Trait in a type alias](https://rust-lang.github.io/impl-trait-initiative/explainer/tait.html)
     /// (currently available only on nightly.
-    #[derive(Default, Debug, Clone)]
+    ///
+    /// Note: the [`Debug`](fmt::Debug) impl only prints the `T`, since consumers are frequently
+    /// closures, which are never [`Debug`](fmt::Debug). Use [`WithConsumer::debug_full`] to print
+    /// the consumer as well.
+    ///
+    /// This is a thin wrapper around a [`Guard<T, Q>`], which implements the functionality
+    /// shared by all guards.
+    #[repr(transparent)]
+    #[derive(Default, Clone)]
     pub struct WithConsumer<T, Q: Consumer<T>> {
-        inner: ConsumeOnDrop<RawWithConsumer<T, Q>>,
+        inner: Guard<T, Q>,
     }
 
     impl<T, Q: Consumer<T>> WithConsumer<T, Q> {
@@ -137,15 +569,15 @@ mod with_consumer {
         #[inline]
         pub const fn new(val: T, cons: Q) -> Self {
             Self {
-                inner: ConsumeOnDrop::new(RawWithConsumer(val, cons)),
+                inner: Guard::new(val, cons),
             }
         }
 
         /// Extracts the underlying `T` and [`Consumer<T>`].
         #[inline]
-        pub fn into_pair(x: Self) -> (T, Q) {
-            let raw = ConsumeOnDrop::into_inner(x.inner);
-            (raw.0, raw.1)
+        pub const fn into_pair(x: Self) -> (T, Q) {
+            // SAFETY: `WithConsumer` is a `#[repr(transparent)]` wrapper around a `Guard`.
+            Guard::into_pair(unsafe { unwrap_transparent(x) })
         }
 
         /// Extracts the underlying `T`, dropping the [`Consumer`]
@@ -154,20 +586,110 @@ mod with_consumer {
             Self::into_pair(x).0
         }
 
+        /// Consumes the `T` with the [`Consumer`] now, exactly as dropping `x` would, but by
+        /// name.
+        #[inline]
+        pub fn consume_now(x: Self) {
+            let (value, consumer) = Self::into_pair(x);
+            consumer.consume(value)
+        }
+
         /// Provides references to both the `T` and the [`Consumer<T>`]
         /// wrapped by `x`.
         #[inline]
-        pub fn as_refs(x: &Self) -> (&T, &Q) {
-            let raw = x.inner.deref();
-            (&raw.0, &raw.1)
+        pub const fn as_refs(x: &Self) -> (&T, &Q) {
+            Guard::as_refs(&x.inner)
         }
 
         /// Provides mutable references to both the `T` and the [`Consumer<T>`]
         /// wrapped by `x`.
         #[inline]
-        pub fn as_muts(x: &mut Self) -> (&mut T, &mut Q) {
-            let raw = x.inner.deref_mut();
-            (&mut raw.0, &mut raw.1)
+        pub const fn as_muts(x: &mut Self) -> (&mut T, &mut Q) {
+            Guard::as_muts(&mut x.inner)
+        }
+
+        /// Exchanges the values wrapped by `a` and `b`, leaving each [`Consumer`] in place.
+        /// The two consumers may have different types. Both values stay guarded throughout.
+        #[inline]
+        pub fn swap_values<R: Consumer<T>>(a: &mut Self, b: &mut WithConsumer<T, R>) {
+            mem::swap(Self::as_muts(a).0, WithConsumer::as_muts(b).0)
+        }
+
+        /// Converts `x` into a [`ConsumeOnDrop`] which consumes the `T` with the [`Consumer`]
+        /// when dropped. The pair stays guarded throughout.
+        #[inline]
+        pub const fn into_consume_on_drop(x: Self) -> ConsumeOnDrop<impl Consume> {
+            // SAFETY: as in `into_pair`.
+            Guard::<T, Q>::into_consume_on_drop(unsafe { unwrap_transparent(x) })
+        }
+
+        /// Unwraps the [`Guard`] underlying `x`. The pair stays guarded throughout.
+        #[inline]
+        pub const fn into_guard(x: Self) -> Guard<T, Q> {
+            // SAFETY: as in `into_pair`.
+            unsafe { unwrap_transparent(x) }
+        }
+
+        /// Formats both the `T` and the [`Consumer<T>`] wrapped by `x`.
+        #[inline]
+        pub fn debug_full(x: &Self) -> impl fmt::Debug + '_
+        where
+            T: fmt::Debug,
+            Q: fmt::Debug,
+        {
+            DebugFull(x)
+        }
+    }
+
+    impl<T: Unpin, Q: Consumer<T> + Unpin> WithConsumer<T, Q> {
+        /// Projects a pinned shared reference to the pair onto the `T` and the [`Consumer<T>`].
+        ///
+        /// As with [`ConsumeOnDrop::as_pin_ref`], the `T` is never pinned structurally, and this
+        /// is only available when `T: Unpin`. Even while the pair is pinned, dropping it moves the
+        /// `T` out to hand it to the consumer by value, so a pinned `!Unpin` `T` would be moved
+        /// without being dropped in place, which [`Pin`] forbids. To guard a `!Unpin` future,
+        /// guard a pinned pointer to it instead, e.g. a `Pin<Box<F>>`, or a `Pin<&mut F>`
+        /// obtained from [`core::pin::pin!`], whose consumer then receives the pointer.
+        #[inline]
+        pub fn as_pin_ref(self: Pin<&Self>) -> (Pin<&T>, &Q) {
+            let (value, consumer) = Self::as_refs(self.get_ref());
+            (Pin::new(value), consumer)
+        }
+
+        /// Projects a pinned mutable reference to the pair onto the `T` and the [`Consumer<T>`],
+        /// so that a future stored as the value can be polled while the consumer can still be
+        /// replaced. See [`WithConsumer::as_pin_ref`].
+        #[inline]
+        pub fn as_pin_mut(self: Pin<&mut Self>) -> (Pin<&mut T>, &mut Q) {
+            let (value, consumer) = Self::as_muts(self.get_mut());
+            (Pin::new(value), consumer)
+        }
+    }
+
+    impl<T, Q: Consumer<T>> From<Guard<T, Q>> for WithConsumer<T, Q> {
+        #[inline]
+        fn from(inner: Guard<T, Q>) -> Self {
+            Self { inner }
+        }
+    }
+
+    struct DebugFull<'a, T, Q: Consumer<T>>(&'a WithConsumer<T, Q>);
+
+    impl<T: fmt::Debug, Q: Consumer<T> + fmt::Debug> fmt::Debug for DebugFull<'_, T, Q> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            let (value, consumer) = WithConsumer::as_refs(self.0);
+            f.debug_struct("WithConsumer")
+                .field("value", value)
+                .field("consumer", consumer)
+                .finish()
+        }
+    }
+
+    impl<T: fmt::Debug, Q: Consumer<T>> fmt::Debug for WithConsumer<T, Q> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("WithConsumer")
+                .field("value", Self::as_refs(self).0)
+                .finish_non_exhaustive()
         }
     }
 
@@ -190,7 +712,7 @@ mod with_consumer {
 
 #[cfg(test)]
 mod tests {
-    use crate::{Consume, ConsumeOnDrop, Consumer, WithConsumer};
+    use crate::{Closure, Consume, ConsumeOnDrop, Consumer, WithConsumer};
     use alloc::string::{String, ToString};
     use alloc::vec::Vec;
     use core::mem::{size_of, size_of_val};
@@ -201,16 +723,20 @@ mod tests {
     fn basic_consume() {
         let mut i = 0;
         {
-            let mut z = ConsumeOnDrop::new(|| i += 1);
-            z.deref_mut()(); // i is now 1
+            let mut z = ConsumeOnDrop::new(Closure(|| i += 1));
+            (z.deref_mut().0)(); // i is now 1
             assert_eq!(size_of_val(&z), size_of::<&mut i32>());
         } // z dropped, i is now 2
         assert_eq!(i, 2);
         {
-            let z = WithConsumer::new((), |()| i += 1);
+            let z = WithConsumer::new((), Closure(|()| i += 1));
             WithConsumer::into_inner(z);
         }
         assert_eq!(i, 2);
+        ConsumeOnDrop::consume_now(ConsumeOnDrop::new(Closure(|| i += 1)));
+        assert_eq!(i, 3);
+        WithConsumer::consume_now(WithConsumer::new(2, Closure(|n| i += n)));
+        assert_eq!(i, 5);
     }
 
     #[test]
@@ -241,6 +767,122 @@ mod tests {
         assert_eq!(&vec2, &["Hello world!".to_string()]);
     }
 
+    #[test]
+    fn conversions() {
+        let count = AtomicUsize::new(0);
+        let bump = || {
+            count.fetch_add(1, Ordering::Relaxed);
+        };
+        let guard = ConsumeOnDrop::into_with_consumer(ConsumeOnDrop::new(Closure(bump)));
+        assert_eq!(size_of_val(&guard), size_of::<&AtomicUsize>());
+        drop(WithConsumer::into_consume_on_drop(guard));
+        let guard = WithConsumer::new(2, Closure(|n| {
+            count.fetch_add(n, Ordering::Relaxed);
+        }));
+        drop(WithConsumer::into_consume_on_drop(guard));
+        assert_eq!(count.load(Ordering::Relaxed), 3);
+    }
+
+    #[test]
+    fn swap_values() {
+        let mut front = Vec::new();
+        let mut back = Vec::new();
+        {
+            let mut a = WithConsumer::new(1, Closure(|x| front.push(x)));
+            let mut b = WithConsumer::new(2, Closure(|x| back.push(x)));
+            WithConsumer::swap_values(&mut a, &mut b);
+            assert_eq!((*a, *b), (2, 1));
+        }
+        assert_eq!((front.as_slice(), back.as_slice()), (&[2][..], &[1][..]));
+    }
+
+    #[test]
+    fn debug() {
+        use alloc::format;
+
+        let guard = WithConsumer::new(3, Closure(|_| ()));
+        assert_eq!(format!("{:?}", guard), "WithConsumer { value: 3, .. }");
+        let guard = WithConsumer::new(3, Closure(drop as fn(i32)));
+        assert!(format!("{:?}", WithConsumer::debug_full(&guard))
+            .starts_with("WithConsumer { value: 3, consumer: Closure("));
+    }
+
+    #[test]
+    fn const_access() {
+        struct Res(u32);
+
+        impl Consume for Res {
+            fn consume(self) {}
+        }
+
+        const RES: Res = {
+            let mut guard = ConsumeOnDrop::new(Res(1));
+            ConsumeOnDrop::get_mut(&mut guard).0 += 1;
+            assert!(ConsumeOnDrop::get_ref(&guard).0 == 2);
+            ConsumeOnDrop::into_inner(guard)
+        };
+        const PAIR: (u32, Closure<fn(u32)>) = {
+            let mut guard = WithConsumer::new(1, Closure(drop as fn(u32)));
+            *WithConsumer::as_muts(&mut guard).0 += 1;
+            assert!(*WithConsumer::as_refs(&guard).0 == 2);
+            WithConsumer::into_pair(guard)
+        };
+        assert_eq!((RES.0, PAIR.0), (2, 2));
+    }
+
+    #[test]
+    fn uninit_inner() {
+        use core::mem::MaybeUninit;
+
+        let count = AtomicUsize::new(0);
+        let mut slot = MaybeUninit::uninit();
+        ConsumeOnDrop::uninit_inner(&mut slot).write(Closure(|| {
+            count.fetch_add(1, Ordering::Relaxed);
+        }));
+        drop(unsafe { slot.assume_init() });
+        assert_eq!(count.load(Ordering::Relaxed), 1);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn new_with_uninit() {
+        struct Buffer<'a>([u8; 4096], &'a AtomicUsize);
+
+        impl Consume for Buffer<'_> {
+            fn consume(self) {
+                self.1.store(self.0.iter().map(|&b| b as usize).sum(), Ordering::Relaxed)
+            }
+        }
+
+        let total = AtomicUsize::new(0);
+        let buffer = ConsumeOnDrop::new_with_uninit(|slot| slot.write(Buffer([1; 4096], &total)));
+        assert_eq!(buffer.0.len(), 4096);
+        drop(buffer);
+        assert_eq!(total.load(Ordering::Relaxed), 4096);
+    }
+
+    #[test]
+    fn try_map() {
+        #[derive(Debug)]
+        struct Conn<'a>(&'a mut Vec<&'static str>, bool);
+
+        impl<'a> Consume for Conn<'a> {
+            fn consume(self) {
+                self.0.push(if self.1 { "closed tls" } else { "closed plain" })
+            }
+        }
+
+        let mut log = Vec::new();
+        let conn = ConsumeOnDrop::new(Conn(&mut log, false));
+        let (conn, error) =
+            ConsumeOnDrop::try_map(conn, |conn| Err::<Conn, _>((conn, "refused"))).unwrap_err();
+        assert_eq!(error, "refused");
+        let conn =
+            ConsumeOnDrop::try_map(conn, |conn| Ok::<_, (Conn, ())>(Conn(conn.0, true))).unwrap();
+        drop(conn);
+        assert_eq!(log, ["closed tls"]);
+    }
+
     /// See [this question](https://stackoverflow.com/questions/53254645/how-can-i-move-a-value-out-of-the-argument-to-dropdrop).
     #[test]
     fn stack_overflow_question_test() {
@@ -310,7 +952,7 @@ mod tests {
 
     #[test]
     fn readme_1() {
-        use super::{Consume, ConsumeOnDrop, WithConsumer};
+        use super::{Closure, Consume, ConsumeOnDrop, WithConsumer};
 
         struct T;
 
@@ -327,19 +969,91 @@ mod tests {
         fn main () {
             let t = ConsumeOnDrop::new(T);  // A thin wrapper around T which calls T::consume on drop
             drop(t);
-            let t = WithConsumer::new(T, consume_t); // Alternately, we can explicitly equip a T with a consumer.
+            let t = WithConsumer::new(T, Closure(consume_t)); // Alternately, we can explicitly equip a T with a consumer.
             drop(t);
         }
         main()
     }
 
+    #[test]
+    fn pinned_future() {
+        use core::future::{pending, Future};
+        use core::pin::pin;
+        use core::task::{Context, Poll, Waker};
+
+        let mut cancelled = false;
+        {
+            // The future itself is pinned on the stack, and the guard holds the `Pin<&mut F>`.
+            let fut = pin!(async { pending::<()>().await });
+            let mut guard = WithConsumer::new(fut, Closure(|_| cancelled = true));
+            let mut cx = Context::from_waker(Waker::noop());
+            assert_eq!(guard.as_mut().poll(&mut cx), Poll::Pending);
+        }
+        assert!(cancelled);
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn pinned_guard() {
+        let mut i = 0;
+        {
+            let mut z = ConsumeOnDrop::pin(Closure(|| i += 1));
+            (z.as_mut().as_pin_mut().get_mut().0)();
+        }
+        assert_eq!(i, 2);
+    }
+
+    #[test]
+    fn pinned_with_consumer() {
+        use core::future::{pending, Future};
+        use core::pin::pin;
+        use core::task::{Context, Poll, Waker};
+
+        let cancelled = &core::cell::Cell::new(0);
+        let cancel = |n| Closure(move |_| cancelled.set(cancelled.get() + n));
+        {
+            let mut guard = pin!(WithConsumer::new(pending::<()>(), cancel(1)));
+            let mut cx = Context::from_waker(Waker::noop());
+            let (fut, consumer) = guard.as_mut().as_pin_mut();
+            assert_eq!(fut.poll(&mut cx), Poll::Pending);
+            *consumer = cancel(10);
+            assert_eq!(size_of_val(&*guard.as_ref().as_pin_ref().0), 0);
+        }
+        assert_eq!(cancelled.get(), 10);
+    }
+
+    #[test]
+    fn pinned_with_consumer_of_unpinned_future() {
+        use core::future::{pending, Future};
+        use core::marker::PhantomPinned;
+        use core::pin::{pin, Pin};
+        use core::task::{Context, Poll, Waker};
+
+        // A future which must not move once polled.
+        let fut = async {
+            let _pinned = PhantomPinned;
+            pending::<()>().await
+        };
+        let fut = pin!(fut);
+        let cancelled = &core::cell::Cell::new(false);
+        {
+            let cancel = Closure(|_: Pin<&mut _>| cancelled.set(true));
+            let mut guard = pin!(WithConsumer::new(fut, cancel));
+            let mut cx = Context::from_waker(Waker::noop());
+            let (fut, _) = guard.as_mut().as_pin_mut();
+            assert_eq!(fut.get_mut().as_mut().poll(&mut cx), Poll::Pending);
+        }
+        assert!(cancelled.get());
+    }
+
     #[test]
     #[should_panic]
+    #[allow(clippy::string_extend_chars)]
     fn readme_3() {
         mod inner {
             use alloc::string::String;
             use core::panic;
-            use super::WithConsumer;
+            use super::{Closure, WithConsumer};
 
             pub struct Data {
                 string: Option<String>,
@@ -364,7 +1078,7 @@ mod tests {
             }
 
             pub fn extend_produce(data: &mut Data) {
-                let mut data = WithConsumer::new(data, Data::poison);
+                let mut data = WithConsumer::new(data, Closure(Data::poison));
                 data.extend(produce_string());
                 WithConsumer::into_inner(data);
             }
@@ -0,0 +1,162 @@
+use crate::{Consume, ConsumeOnDrop};
+use core::fmt;
+use core::ops::{Deref, DerefMut};
+use core::sync::atomic::{compiler_fence, Ordering};
+use std::io;
+
+/// Overwrites `bytes` with zeros in a way the optimizer will not elide, even though the bytes
+/// are about to be freed.
+fn wipe(bytes: &mut [u8]) {
+    for byte in bytes.iter_mut() {
+        // SAFETY: `byte` is a valid, aligned, exclusive reference.
+        unsafe { core::ptr::write_volatile(byte, 0) }
+    }
+    compiler_fence(Ordering::SeqCst);
+}
+
+struct Locked<B: DerefMut<Target = [u8]>> {
+    buffer: B,
+    wipe: bool,
+}
+
+impl<B: DerefMut<Target = [u8]>> Consume for Locked<B> {
+    fn consume(mut self) {
+        if self.wipe {
+            wipe(&mut self.buffer);
+        }
+        // `munlock` only fails for ranges which were never locked, and the pages must be
+        // released regardless, so its result is ignored.
+        // SAFETY: unlocking pages has no effect on memory safety.
+        unsafe {
+            libc::munlock(self.buffer.as_ptr().cast(), self.buffer.len());
+        }
+    }
+}
+
+/// A buffer of secret data, such as key material, whose pages are locked into memory with
+/// `mlock(2)` so that they are never written to swap. When dropped, the buffer is wiped with
+/// zeros, then unlocked with `munlock(2)`, then dropped.
+///
+/// The buffer is only reachable as a `[u8]`, so it cannot be reallocated while locked. `B`
+/// should own a heap allocation, such as a `Box<[u8]>` or a `Vec<u8>`, so that moving the guard
+/// does not move the bytes. Since `mlock` works on whole pages, other data sharing the first
+/// and last pages is locked as well, and unlocked again when the guard is dropped.
+pub struct MlockGuard<B: DerefMut<Target = [u8]>> {
+    inner: ConsumeOnDrop<Locked<B>>,
+}
+
+impl<B: DerefMut<Target = [u8]>> MlockGuard<B> {
+    /// Locks the pages of `buffer` into memory. The buffer will be wiped when the guard is
+    /// dropped.
+    ///
+    /// This fails if the buffer would exceed the process's `RLIMIT_MEMLOCK`, in which case the
+    /// buffer is wiped before it is dropped.
+    pub fn new(mut buffer: B) -> io::Result<Self> {
+        // SAFETY: locking pages has no effect on memory safety.
+        if unsafe { libc::mlock(buffer.as_ptr().cast(), buffer.len()) } == -1 {
+            let error = io::Error::last_os_error();
+            wipe(&mut buffer);
+            return Err(error);
+        }
+        Ok(Self {
+            inner: ConsumeOnDrop::new(Locked { buffer, wipe: true }),
+        })
+    }
+
+    /// Whether the buffer will be wiped when `x` is dropped.
+    #[inline]
+    pub fn wipes(x: &Self) -> bool {
+        x.inner.wipe
+    }
+
+    /// Sets whether the buffer is wiped when `x` is dropped, e.g. to skip wiping a buffer which
+    /// only ever held public data.
+    #[inline]
+    pub fn set_wipe(x: &mut Self, wipe: bool) {
+        x.inner.wipe = wipe
+    }
+
+    /// Unlocks the buffer and returns it without wiping it.
+    pub fn into_inner(x: Self) -> B {
+        let locked = ConsumeOnDrop::into_inner(x.inner);
+        // SAFETY: the buffer stays valid; only its pages are unlocked.
+        unsafe {
+            libc::munlock(locked.buffer.as_ptr().cast(), locked.buffer.len());
+        }
+        locked.buffer
+    }
+}
+
+impl<B: DerefMut<Target = [u8]>> fmt::Debug for MlockGuard<B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // The contents are secret, so only the length is shown.
+        f.debug_struct("MlockGuard")
+            .field("len", &self.inner.buffer.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<B: DerefMut<Target = [u8]>> Deref for MlockGuard<B> {
+    type Target = [u8];
+
+    #[inline]
+    fn deref(&self) -> &[u8] {
+        &self.inner.buffer
+    }
+}
+
+impl<B: DerefMut<Target = [u8]>> DerefMut for MlockGuard<B> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut [u8] {
+        &mut self.inner.buffer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MlockGuard;
+    use core::ops::{Deref, DerefMut};
+    use std::sync::Mutex;
+    use std::vec::Vec;
+
+    static DROPPED: Mutex<Vec<Vec<u8>>> = Mutex::new(Vec::new());
+
+    /// A buffer which records its contents when dropped.
+    struct Recorded(Vec<u8>);
+
+    impl Deref for Recorded {
+        type Target = [u8];
+
+        fn deref(&self) -> &[u8] {
+            &self.0
+        }
+    }
+
+    impl DerefMut for Recorded {
+        fn deref_mut(&mut self) -> &mut [u8] {
+            &mut self.0
+        }
+    }
+
+    impl Drop for Recorded {
+        fn drop(&mut self) {
+            DROPPED.lock().unwrap().push(self.0.clone())
+        }
+    }
+
+    #[test]
+    fn wipes_before_unlocking() {
+        let mut key = MlockGuard::new(Recorded(std::vec![0; 32])).unwrap();
+        key.copy_from_slice(&[0xA5; 32]);
+        drop(key);
+
+        let mut public = MlockGuard::new(Recorded(std::vec![0; 4])).unwrap();
+        public.copy_from_slice(b"salt");
+        MlockGuard::set_wipe(&mut public, false);
+        drop(public);
+        assert_eq!(
+            *DROPPED.lock().unwrap(),
+            [std::vec![0; 32], b"salt".to_vec()]
+        );
+    }
+}
@@ -0,0 +1,117 @@
+use crate::{Consume, ConsumeOnDrop};
+use core::ops::Deref;
+use core::ptr::NonNull;
+use core::slice;
+use std::io;
+
+#[derive(Debug)]
+struct RawMapping {
+    ptr: NonNull<u8>,
+    len: usize,
+}
+
+impl Consume for RawMapping {
+    #[inline]
+    fn consume(self) {
+        // SAFETY: every constructor of `MmapGuard` ensures that this is exactly a region mapped
+        // by `mmap` and owned by the guard, and we only get here once. Like `close`, `munmap` can
+        // only fail here through a broken invariant, so its result is ignored.
+        unsafe {
+            libc::munmap(self.ptr.as_ptr().cast(), self.len);
+        }
+    }
+}
+
+/// An owned region of memory mapped with `mmap(2)`, which is unmapped with `munmap(2)` when
+/// dropped. The mapping can be read as a byte slice.
+#[derive(Debug)]
+pub struct MmapGuard {
+    inner: ConsumeOnDrop<RawMapping>,
+}
+
+// SAFETY: a `MmapGuard` owns its mapping, which may be read and unmapped from any thread.
+unsafe impl Send for MmapGuard {}
+// SAFETY: shared references only allow reading the mapping.
+unsafe impl Sync for MmapGuard {}
+
+impl MmapGuard {
+    /// Takes ownership of the mapping of `len` bytes starting at `ptr`.
+    ///
+    /// # Safety
+    ///
+    /// - `ptr` and `len` must describe exactly a region returned by a successful call to `mmap`,
+    ///   or a whole number of pages from one, with a nonzero `len`.
+    /// - The region must be mapped readable, and must not be unmapped or remapped by anything
+    ///   other than the returned guard.
+    /// - The region must not be written to while the guard exists, except through the guard's
+    ///   own pointer, since [`Deref`] hands out `&[u8]`. Shared mappings of files which other
+    ///   processes may modify are therefore unsound to read through this guard.
+    #[inline]
+    pub unsafe fn from_raw_parts(ptr: NonNull<u8>, len: usize) -> Self {
+        Self {
+            inner: ConsumeOnDrop::new(RawMapping { ptr, len }),
+        }
+    }
+
+    /// Maps `len` bytes of private, zeroed, anonymous memory. `len` is rounded up to a whole
+    /// number of pages by the kernel.
+    pub fn anonymous(len: usize) -> io::Result<Self> {
+        // SAFETY: an anonymous private mapping has no preconditions.
+        let ptr = unsafe {
+            libc::mmap(
+                core::ptr::null_mut(),
+                len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+                -1,
+                0,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: `mmap` succeeded, so `ptr` is a fresh mapping of `len` bytes which only we
+        // know about, and it is never null since we did not ask for a fixed address.
+        Ok(unsafe { Self::from_raw_parts(NonNull::new_unchecked(ptr.cast()), len) })
+    }
+
+    /// Releases ownership of the mapping without unmapping it.
+    #[inline]
+    pub fn into_raw_parts(x: Self) -> (NonNull<u8>, usize) {
+        let raw = ConsumeOnDrop::into_inner(x.inner);
+        (raw.ptr, raw.len)
+    }
+
+    /// A pointer to the start of the mapping.
+    #[inline]
+    pub fn as_ptr(x: &Self) -> *mut u8 {
+        x.inner.ptr.as_ptr()
+    }
+}
+
+impl Deref for MmapGuard {
+    type Target = [u8];
+
+    #[inline]
+    fn deref(&self) -> &[u8] {
+        // SAFETY: the constructors guarantee that the region is mapped readable, is not written
+        // to while borrowed, and stays mapped for as long as `self` exists.
+        unsafe { slice::from_raw_parts(self.inner.ptr.as_ptr(), self.inner.len) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MmapGuard;
+
+    #[test]
+    fn maps_and_unmaps() {
+        let mapping = MmapGuard::anonymous(4096).unwrap();
+        assert_eq!(mapping.len(), 4096);
+        assert!(mapping.iter().all(|&b| b == 0));
+        let (ptr, len) = MmapGuard::into_raw_parts(mapping);
+        let mapping = unsafe { MmapGuard::from_raw_parts(ptr, len) };
+        assert_eq!(MmapGuard::as_ptr(&mapping), ptr.as_ptr());
+        drop(mapping);
+    }
+}
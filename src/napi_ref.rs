@@ -0,0 +1,483 @@
+use crate::{Consumer, WithConsumer};
+use alloc::boxed::Box;
+use core::ffi::{c_void, CStr};
+use core::ptr;
+use napi::sys::{self, ThreadsafeFunctionCallMode, ThreadsafeFunctionReleaseMode};
+use napi::{Env, Error, NapiRaw, NapiValue, Result, Status};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, ThreadId};
+
+type Task = Box<dyn FnOnce(Env) + Send>;
+
+fn check(status: sys::napi_status) -> Result<()> {
+    match status {
+        sys::Status::napi_ok => Ok(()),
+        status => Err(Error::from_status(Status::from(status))),
+    }
+}
+
+/// Whether the threadsafe function has been finalized by the environment, after which it must
+/// not be called or released.
+struct Closed(Mutex<bool>);
+
+impl Closed {
+    fn lock(&self) -> std::sync::MutexGuard<'_, bool> {
+        self.0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Hands `task` to `run` on the JavaScript thread, or to `queue` on any other thread, unless the
+/// threadsafe function has been finalized, in which case `task` is dropped and this returns
+/// `false`. `closed` stays locked while queueing, so that the function cannot be finalized
+/// meanwhile, but not while running, since `task` may run more tasks.
+fn dispatch<T>(
+    closed: &Closed,
+    is_current: bool,
+    task: T,
+    run: impl FnOnce(T),
+    queue: impl FnOnce(T) -> bool,
+) -> bool {
+    let closed = closed.lock();
+    if *closed {
+        return false;
+    }
+    if is_current {
+        drop(closed);
+        run(task);
+        true
+    } else {
+        queue(task)
+    }
+}
+
+unsafe extern "C" fn finalize(_env: sys::napi_env, data: *mut c_void, _hint: *mut c_void) {
+    // SAFETY: `data` is the reference to `Closed` leaked by `JsThread::new`.
+    let closed = unsafe { Arc::from_raw(data.cast::<Closed>()) };
+    *closed.lock() = true;
+}
+
+unsafe extern "C" fn call_js(
+    env: sys::napi_env,
+    _callback: sys::napi_value,
+    _context: *mut c_void,
+    data: *mut c_void,
+) {
+    // SAFETY: `data` is a task boxed by `JsThread::run`, which is passed here exactly once.
+    let task = unsafe { Box::from_raw(data.cast::<Task>()) };
+    // A null environment means the queue is being drained at teardown, when no JavaScript can
+    // run anymore, so the task is dropped instead.
+    if env.is_null() {
+        return;
+    }
+    // SAFETY: `env` is the environment of the current thread.
+    let task = AssertUnwindSafe(move || task(unsafe { Env::from_raw(env) }));
+    if panic::catch_unwind(task).is_err() {
+        // SAFETY: as above. Node reports the exception as uncaught once this returns.
+        unsafe {
+            sys::napi_throw_error(
+                env,
+                ptr::null(),
+                c"a cleanup panicked on the JavaScript thread".as_ptr(),
+            );
+        }
+    }
+}
+
+struct Inner {
+    env: sys::napi_env,
+    thread: ThreadId,
+    tsfn: sys::napi_threadsafe_function,
+    closed: Arc<Closed>,
+}
+
+// SAFETY: `env` is only used on `thread`, and threadsafe functions may be called and released
+// from any thread, which `closed` guards against doing after they are finalized.
+unsafe impl Send for Inner {}
+unsafe impl Sync for Inner {}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        let closed = self.closed.lock();
+        if !*closed {
+            // SAFETY: the function has not been finalized, and this releases the only thread
+            // using it.
+            unsafe {
+                sys::napi_release_threadsafe_function(
+                    self.tsfn,
+                    ThreadsafeFunctionReleaseMode::release,
+                );
+            }
+        }
+    }
+}
+
+/// A handle to the JavaScript thread of a Node.js environment, which runs cleanups on that
+/// thread from any thread.
+///
+/// N-API calls are only allowed on the thread running the environment. A cleanup run on that
+/// thread runs right away, and a cleanup run anywhere else is queued through a threadsafe
+/// function. The threadsafe function does not keep the event loop alive, so cleanups queued
+/// while the environment shuts down are dropped without running, which leaks the resources
+/// they were meant to release. Clones share the same queue.
+#[derive(Clone)]
+pub struct JsThread {
+    inner: Arc<Inner>,
+}
+
+impl JsThread {
+    /// Builds a handle to the thread running `env`, which must be the current thread.
+    pub fn new(env: Env) -> Result<Self> {
+        let env = env.raw();
+        let mut name = ptr::null_mut();
+        let resource = c"consume_on_drop cleanup";
+        // SAFETY: `env` is the environment of the current thread, and `resource` is a string
+        // of the given length.
+        check(unsafe {
+            sys::napi_create_string_utf8(env, resource.as_ptr(), resource.count_bytes(), &mut name)
+        })?;
+        let closed = Arc::new(Closed(Mutex::new(false)));
+        let data = Arc::into_raw(closed.clone()).cast_mut().cast();
+        let mut tsfn = ptr::null_mut();
+        // SAFETY: as above. `finalize` takes back the reference to `closed` leaked as its data,
+        // and `call_js` handles the tasks boxed by `JsThread::run`.
+        let created = check(unsafe {
+            sys::napi_create_threadsafe_function(
+                env,
+                ptr::null_mut(),
+                ptr::null_mut(),
+                name,
+                0,
+                1,
+                data,
+                Some(finalize),
+                ptr::null_mut(),
+                Some(call_js),
+                &mut tsfn,
+            )
+        });
+        if let Err(error) = created {
+            // SAFETY: the function was not created, so `finalize` never takes the reference.
+            drop(unsafe { Arc::from_raw(data.cast::<Closed>()) });
+            return Err(error);
+        }
+        // SAFETY: `tsfn` was just created in `env`.
+        check(unsafe { sys::napi_unref_threadsafe_function(env, tsfn) })?;
+        Ok(Self {
+            inner: Arc::new(Inner {
+                env,
+                thread: thread::current().id(),
+                tsfn,
+                closed,
+            }),
+        })
+    }
+
+    /// Whether the current thread is the JavaScript thread.
+    #[inline]
+    pub fn is_current(&self) -> bool {
+        thread::current().id() == self.inner.thread
+    }
+
+    /// Runs `task` on the JavaScript thread, right away if it is the current thread. Returns
+    /// `false` if the environment is shutting down, in which case `task` is dropped instead.
+    pub fn run(&self, task: impl FnOnce(Env) + Send + 'static) -> bool {
+        let inner = &*self.inner;
+        dispatch(
+            &inner.closed,
+            self.is_current(),
+            task,
+            // SAFETY: `env` is the environment of the current thread, and has not been torn
+            // down since the function has not been finalized.
+            |task| task(unsafe { Env::from_raw(inner.env) }),
+            |task| {
+                let task: *mut Task = Box::into_raw(Box::new(Box::new(task)));
+                // SAFETY: the function has not been finalized while `closed` is locked, and
+                // `call_js` takes ownership of `task` once this succeeds.
+                let queued = unsafe {
+                    sys::napi_call_threadsafe_function(
+                        inner.tsfn,
+                        task.cast(),
+                        ThreadsafeFunctionCallMode::nonblocking,
+                    )
+                } == sys::Status::napi_ok;
+                if !queued {
+                    // SAFETY: the task was not queued, so this is its only owner.
+                    drop(unsafe { Box::from_raw(task) });
+                }
+                queued
+            },
+        )
+    }
+}
+
+impl core::fmt::Debug for JsThread {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("JsThread")
+            .field("thread", &self.inner.thread)
+            .finish_non_exhaustive()
+    }
+}
+
+/// An N-API reference which can be sent to the thread running its environment.
+struct SendRef(sys::napi_ref);
+
+// SAFETY: the reference is only used by tasks run on the JavaScript thread.
+unsafe impl Send for SendRef {}
+
+impl SendRef {
+    fn into_raw(self) -> sys::napi_ref {
+        self.0
+    }
+}
+
+/// A [`Consumer<napi_ref>`](napi::sys::napi_ref) which deletes an N-API reference with
+/// `napi_delete_reference`, on the JavaScript thread.
+///
+/// If the environment is shutting down, the reference is leaked.
+#[derive(Clone, Debug)]
+pub struct DeleteNapiRef {
+    thread: JsThread,
+}
+
+impl DeleteNapiRef {
+    /// Builds a consumer which deletes references created in the environment of `thread`.
+    ///
+    /// # Safety
+    ///
+    /// Any reference consumed by the result must have been created in the environment of
+    /// `thread`, and must not be used again once consumed.
+    #[inline]
+    pub unsafe fn new(thread: &JsThread) -> Self {
+        Self {
+            thread: thread.clone(),
+        }
+    }
+}
+
+impl Consumer<sys::napi_ref> for DeleteNapiRef {
+    fn consume(self, other: sys::napi_ref) {
+        let reference = SendRef(other);
+        self.thread.run(move |env| {
+            // SAFETY: the constructor requires that `reference` was created in `env`, and is
+            // never used again.
+            unsafe { sys::napi_delete_reference(env.raw(), reference.into_raw()) };
+        });
+    }
+}
+
+/// A [`Consumer<napi_ref>`](napi::sys::napi_ref) which calls a method of the referenced
+/// JavaScript object, such as `dispose()`, then deletes the reference, on the JavaScript thread.
+///
+/// Like an exception thrown by a callback, an exception thrown by the method is reported as
+/// uncaught. If the environment is shutting down, the method is not called, and the reference
+/// is leaked.
+#[derive(Clone, Debug)]
+pub struct CallJsDispose {
+    thread: JsThread,
+    method: &'static CStr,
+}
+
+impl CallJsDispose {
+    /// Builds a consumer which calls `method` on objects referenced in the environment of
+    /// `thread`.
+    ///
+    /// # Safety
+    ///
+    /// See [`DeleteNapiRef::new`].
+    #[inline]
+    pub unsafe fn new(thread: &JsThread, method: &'static CStr) -> Self {
+        Self {
+            thread: thread.clone(),
+            method,
+        }
+    }
+
+    /// Builds a consumer which calls `dispose()`.
+    ///
+    /// # Safety
+    ///
+    /// See [`DeleteNapiRef::new`].
+    #[inline]
+    pub unsafe fn dispose(thread: &JsThread) -> Self {
+        unsafe { Self::new(thread, c"dispose") }
+    }
+
+    /// The name of the method this consumer calls.
+    #[inline]
+    pub fn method(&self) -> &'static CStr {
+        self.method
+    }
+}
+
+impl Consumer<sys::napi_ref> for CallJsDispose {
+    fn consume(self, other: sys::napi_ref) {
+        let reference = SendRef(other);
+        let method = self.method;
+        self.thread.run(move |env| {
+            let (env, reference) = (env.raw(), reference.into_raw());
+            // SAFETY: the constructors require that `reference` was created in `env`, and is
+            // never used again. Every value is checked before it is used.
+            unsafe {
+                let mut object = ptr::null_mut();
+                let mut function = ptr::null_mut();
+                let mut result = ptr::null_mut();
+                let called = check(sys::napi_get_reference_value(env, reference, &mut object))
+                    .and_then(|()| {
+                        if object.is_null() {
+                            return Ok(());
+                        }
+                        check(sys::napi_get_named_property(
+                            env,
+                            object,
+                            method.as_ptr(),
+                            &mut function,
+                        ))?;
+                        check(sys::napi_call_function(
+                            env,
+                            object,
+                            function,
+                            0,
+                            ptr::null(),
+                            &mut result,
+                        ))
+                    });
+                let mut pending = false;
+                if called.is_err()
+                    && sys::napi_is_exception_pending(env, &mut pending) == sys::Status::napi_ok
+                    && pending
+                {
+                    let mut exception = ptr::null_mut();
+                    sys::napi_get_and_clear_last_exception(env, &mut exception);
+                    sys::napi_fatal_exception(env, exception);
+                }
+                sys::napi_delete_reference(env, reference);
+            }
+        });
+    }
+}
+
+/// An owned N-API reference to a JavaScript value, which is released on the JavaScript thread
+/// when dropped, from whichever thread drops it. See [`DeleteNapiRef`] and [`CallJsDispose`].
+///
+/// This gives native addons the same drop safety for JavaScript-held resources as the other
+/// FFI guards, without requiring an [`Env`] at the point of the drop.
+#[derive(Debug)]
+pub struct NapiRef<Q: Consumer<sys::napi_ref> = DeleteNapiRef> {
+    inner: WithConsumer<sys::napi_ref, Q>,
+    thread: JsThread,
+}
+
+fn create_ref<V: NapiRaw>(thread: &JsThread, value: &V) -> Result<sys::napi_ref> {
+    if !thread.is_current() {
+        return Err(Error::new(
+            Status::GenericFailure,
+            "references can only be created on the JavaScript thread",
+        ));
+    }
+    let mut reference = ptr::null_mut();
+    // SAFETY: the environment of `thread` is the one of the current thread, to which `value`
+    // belongs.
+    check(unsafe { sys::napi_create_reference(thread.inner.env, value.raw(), 1, &mut reference) })?;
+    Ok(reference)
+}
+
+impl NapiRef {
+    /// Creates a reference to `value`, which is deleted when dropped. This must be called on
+    /// the JavaScript thread.
+    pub fn new<V: NapiRaw>(thread: &JsThread, value: &V) -> Result<Self> {
+        let reference = create_ref(thread, value)?;
+        Ok(Self {
+            // SAFETY: the reference was just created in the environment of `thread`.
+            inner: WithConsumer::new(reference, unsafe { DeleteNapiRef::new(thread) }),
+            thread: thread.clone(),
+        })
+    }
+}
+
+impl NapiRef<CallJsDispose> {
+    /// Creates a reference to the object `value`, whose `method` is called when dropped, before
+    /// the reference is deleted. This must be called on the JavaScript thread.
+    pub fn with_dispose<V: NapiRaw>(
+        thread: &JsThread,
+        value: &V,
+        method: &'static CStr,
+    ) -> Result<Self> {
+        let reference = create_ref(thread, value)?;
+        Ok(Self {
+            // SAFETY: the reference was just created in the environment of `thread`.
+            inner: WithConsumer::new(reference, unsafe { CallJsDispose::new(thread, method) }),
+            thread: thread.clone(),
+        })
+    }
+}
+
+impl<Q: Consumer<sys::napi_ref>> NapiRef<Q> {
+    /// The referenced value, which can only be read on the JavaScript thread.
+    pub fn value<V: NapiValue>(x: &Self) -> Result<V> {
+        if !x.thread.is_current() {
+            return Err(Error::new(
+                Status::GenericFailure,
+                "references can only be read on the JavaScript thread",
+            ));
+        }
+        let env = x.thread.inner.env;
+        let mut value = ptr::null_mut();
+        // SAFETY: the reference was created in `env`, which is the environment of the current
+        // thread.
+        check(unsafe { sys::napi_get_reference_value(env, *x.inner, &mut value) })?;
+        if value.is_null() {
+            return Err(Error::new(
+                Status::GenericFailure,
+                "the referenced value has been collected",
+            ));
+        }
+        // SAFETY: as above.
+        unsafe { V::from_raw(env, value) }
+    }
+
+    /// The underlying reference, which stays owned by `x`.
+    #[inline]
+    pub fn as_raw(x: &Self) -> sys::napi_ref {
+        *x.inner
+    }
+
+    /// Releases ownership of the reference without deleting it.
+    #[inline]
+    pub fn into_raw(x: Self) -> sys::napi_ref {
+        WithConsumer::into_inner(x.inner)
+    }
+}
+
+// SAFETY: references are only used on the JavaScript thread, which `NapiRef::value` checks, and
+// are released there by the consumer.
+unsafe impl<Q: Consumer<sys::napi_ref> + Send> Send for NapiRef<Q> {}
+unsafe impl<Q: Consumer<sys::napi_ref> + Sync> Sync for NapiRef<Q> {}
+
+#[cfg(test)]
+mod tests {
+    use super::{dispatch, Closed};
+    use std::cell::RefCell;
+    use std::sync::Mutex;
+    use std::vec::Vec;
+
+    #[test]
+    fn closed_environment_drops_tasks() {
+        let closed = Closed(Mutex::new(false));
+        let events = RefCell::new(Vec::new());
+        let run = |task| events.borrow_mut().push(("run", task));
+        let queue = |task| {
+            events.borrow_mut().push(("queue", task));
+            true
+        };
+
+        assert!(dispatch(&closed, true, 1, run, queue));
+        assert!(dispatch(&closed, false, 2, run, queue));
+        *closed.lock() = true;
+        assert!(!dispatch(&closed, true, 3, run, queue));
+        assert!(!dispatch(&closed, false, 4, run, queue));
+        assert_eq!(*events.borrow(), [("run", 1), ("queue", 2)]);
+    }
+}
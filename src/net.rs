@@ -0,0 +1,158 @@
+use crate::{Consumer, WithConsumer};
+use std::io::{self, BufWriter, Write};
+use std::net::{Shutdown, TcpStream};
+use std::ops::{Deref, DerefMut};
+
+/// A writer which sits on top of a [`TcpStream`], such as the stream itself or a [`BufWriter`]
+/// around it.
+pub trait AsTcpStream {
+    /// The underlying stream.
+    fn as_tcp_stream(&self) -> &TcpStream;
+}
+
+impl AsTcpStream for TcpStream {
+    #[inline]
+    fn as_tcp_stream(&self) -> &TcpStream {
+        self
+    }
+}
+
+impl<S: AsTcpStream + Write> AsTcpStream for BufWriter<S> {
+    #[inline]
+    fn as_tcp_stream(&self) -> &TcpStream {
+        self.get_ref().as_tcp_stream()
+    }
+}
+
+/// The default error hook used by [`ShutdownTcp`]. It silently ignores the error.
+fn ignore_shutdown_error(_error: io::Error) {}
+
+/// A [`Consumer`] which optionally flushes a writer over a [`TcpStream`] and then shuts down
+/// both directions of the stream, so the peer sees a FIN immediately.
+///
+/// The first error from flushing or shutting down is passed to the hook `F`. By default,
+/// errors are ignored. A failed flush does not prevent the shutdown.
+#[derive(Debug, Clone, Copy)]
+pub struct ShutdownTcp<F = fn(io::Error)> {
+    flush: bool,
+    on_error: F,
+}
+
+impl ShutdownTcp {
+    /// Builds a consumer which shuts the stream down without flushing, ignoring errors.
+    #[inline]
+    pub const fn new() -> Self {
+        Self::with_hook(false, ignore_shutdown_error)
+    }
+
+    /// Builds a consumer which flushes and then shuts the stream down, ignoring errors.
+    #[inline]
+    pub const fn flushing() -> Self {
+        Self::with_hook(true, ignore_shutdown_error)
+    }
+}
+
+impl Default for ShutdownTcp {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<F: FnOnce(io::Error)> ShutdownTcp<F> {
+    /// Builds a consumer which flushes first if `flush` is set, and reports the first error to
+    /// `on_error`.
+    #[inline]
+    pub const fn with_hook(flush: bool, on_error: F) -> Self {
+        Self { flush, on_error }
+    }
+}
+
+impl<S: AsTcpStream + Write, F: FnOnce(io::Error)> Consumer<S> for ShutdownTcp<F> {
+    fn consume(self, mut other: S) {
+        let flushed = if self.flush { other.flush() } else { Ok(()) };
+        let result = flushed.and(other.as_tcp_stream().shutdown(Shutdown::Both));
+        if let Err(error) = result {
+            (self.on_error)(error)
+        }
+    }
+}
+
+/// A writer over a [`TcpStream`] which is shut down when dropped, rather than just closed.
+/// See [`ShutdownTcp`].
+#[derive(Debug)]
+pub struct TcpShutdownGuard<
+    S: AsTcpStream + Write = TcpStream,
+    F: FnOnce(io::Error) = fn(io::Error),
+> {
+    inner: WithConsumer<S, ShutdownTcp<F>>,
+}
+
+impl<S: AsTcpStream + Write> TcpShutdownGuard<S> {
+    /// Guards `stream`, which will be shut down without flushing, ignoring errors.
+    #[inline]
+    pub const fn new(stream: S) -> Self {
+        Self::with_consumer(stream, ShutdownTcp::new())
+    }
+
+    /// Guards `stream`, which will be flushed and then shut down, ignoring errors.
+    #[inline]
+    pub const fn flushing(stream: S) -> Self {
+        Self::with_consumer(stream, ShutdownTcp::flushing())
+    }
+}
+
+impl<S: AsTcpStream + Write, F: FnOnce(io::Error)> TcpShutdownGuard<S, F> {
+    /// Guards `stream`, which will be shut down by `consumer`.
+    #[inline]
+    pub const fn with_consumer(stream: S, consumer: ShutdownTcp<F>) -> Self {
+        Self {
+            inner: WithConsumer::new(stream, consumer),
+        }
+    }
+
+    /// Releases the stream without shutting it down.
+    #[inline]
+    pub fn into_inner(x: Self) -> S {
+        WithConsumer::into_inner(x.inner)
+    }
+}
+
+impl<S: AsTcpStream + Write, F: FnOnce(io::Error)> Deref for TcpShutdownGuard<S, F> {
+    type Target = S;
+
+    #[inline]
+    fn deref(&self) -> &S {
+        &self.inner
+    }
+}
+
+impl<S: AsTcpStream + Write, F: FnOnce(io::Error)> DerefMut for TcpShutdownGuard<S, F> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TcpShutdownGuard;
+    use std::io::{BufWriter, Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::vec::Vec;
+
+    #[test]
+    fn flushes_and_shuts_down() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (mut server, _) = listener.accept().unwrap();
+
+        let mut guard = TcpShutdownGuard::flushing(BufWriter::new(client));
+        guard.write_all(b"bye").unwrap();
+        drop(guard);
+
+        let mut received = Vec::new();
+        server.read_to_end(&mut received).unwrap();
+        assert_eq!(received, b"bye");
+    }
+}
@@ -0,0 +1,118 @@
+use crate::sync::{Arc, Mutex};
+use crate::{Consumer, WithConsumer};
+use core::fmt;
+
+/// A cloneable handle to a [`Consumer`] which fires at most once across all of its clones.
+///
+/// The first clone to consume a value runs the shared consumer on it. Every later value is
+/// simply dropped. This gives "first drop wins" teardown for redundant handles to one
+/// underlying resource: guard each handle with a clone of the same [`OnceConsumer`], and the
+/// resource is torn down exactly once, by whichever guard is dropped first.
+pub struct OnceConsumer<Q> {
+    consumer: Arc<Mutex<Option<Q>>>,
+}
+
+impl<Q> OnceConsumer<Q> {
+    /// Shares `consumer` between the clones of the result.
+    pub fn new(consumer: Q) -> Self {
+        Self {
+            consumer: Arc::new(Mutex::new(Some(consumer))),
+        }
+    }
+
+    /// Whether the shared consumer has already fired.
+    pub fn has_fired(&self) -> bool {
+        self.consumer
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .is_none()
+    }
+
+    /// Guards `value` with a clone of this handle.
+    #[inline]
+    pub fn guard<T>(&self, value: T) -> WithConsumer<T, Self>
+    where
+        Q: Consumer<T>,
+    {
+        WithConsumer::new(value, self.clone())
+    }
+}
+
+impl<T, Q: Consumer<T>> Consumer<T> for OnceConsumer<Q> {
+    fn consume(self, other: T) {
+        // The lock is released before the consumer runs, so that it can't be poisoned.
+        let consumer = self
+            .consumer
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .take();
+        if let Some(consumer) = consumer {
+            consumer.consume(other)
+        }
+    }
+}
+
+impl<Q> Clone for OnceConsumer<Q> {
+    fn clone(&self) -> Self {
+        Self {
+            consumer: self.consumer.clone(),
+        }
+    }
+}
+
+impl<Q> fmt::Debug for OnceConsumer<Q> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OnceConsumer")
+            .field("fired", &self.has_fired())
+            .finish()
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use super::OnceConsumer;
+    use crate::Closure;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn first_drop_wins() {
+        let closed = AtomicUsize::new(0);
+        let close = OnceConsumer::new(Closure(|port: u16| {
+            closed.fetch_add(port as usize, Ordering::Relaxed);
+        }));
+        let a = close.guard(1);
+        let b = close.guard(2);
+        assert!(!close.has_fired());
+        drop(b);
+        drop(a);
+        assert!(close.has_fired());
+        assert_eq!(closed.load(Ordering::Relaxed), 2);
+    }
+}
+
+#[cfg(all(test, loom))]
+mod loom_tests {
+    use super::OnceConsumer;
+    use crate::Closure;
+    use loom::sync::atomic::{AtomicUsize, Ordering};
+    use loom::sync::Arc;
+    use loom::thread;
+
+    #[test]
+    fn fires_once_across_threads() {
+        loom::model(|| {
+            let closed = Arc::new(AtomicUsize::new(0));
+            let close = {
+                let closed = closed.clone();
+                OnceConsumer::new(Closure(move |_: u16| {
+                    closed.fetch_add(1, Ordering::Relaxed);
+                }))
+            };
+            let (a, b) = (close.guard(1), close.guard(2));
+            let dropping = thread::spawn(move || drop(a));
+            drop(b);
+            dropping.join().unwrap();
+            assert_eq!(closed.load(Ordering::Relaxed), 1);
+        });
+    }
+}
@@ -0,0 +1,65 @@
+use crate::{Consume, ConsumeOnDrop};
+use core::fmt;
+
+/// A [`ConsumeOnDrop<T>`] which gives no access to the underlying `T` at all.
+///
+/// The value can only be consumed, either by dropping the guard or with
+/// [`OpaqueGuard::consume_now`], or unwrapped with [`OpaqueGuard::into_inner`]. Use this for values
+/// which must not be observed between construction and consumption, such as one-shot tokens
+/// and commit cookies. Like [`ConsumeOnDrop`], this is a zero-overhead wrapper around `T`.
+#[repr(transparent)]
+pub struct OpaqueGuard<T: Consume> {
+    inner: ConsumeOnDrop<T>,
+}
+
+impl<T: Consume> OpaqueGuard<T> {
+    /// Wraps a `T` in an [`OpaqueGuard`].
+    #[inline]
+    pub const fn new(value: T) -> Self {
+        Self {
+            inner: ConsumeOnDrop::new(value),
+        }
+    }
+
+    /// Consumes the underlying `T` now. This is equivalent to dropping `x`.
+    #[inline]
+    pub fn consume_now(x: Self) {
+        drop(x)
+    }
+
+    /// Unwraps the underlying `T` without consuming it.
+    #[inline]
+    pub fn into_inner(x: Self) -> T {
+        ConsumeOnDrop::into_inner(x.inner)
+    }
+}
+
+impl<T: Consume> From<ConsumeOnDrop<T>> for OpaqueGuard<T> {
+    /// Gives up all access to a [`ConsumeOnDrop`]. The value stays guarded throughout.
+    #[inline]
+    fn from(inner: ConsumeOnDrop<T>) -> Self {
+        Self { inner }
+    }
+}
+
+impl<T: Consume> fmt::Debug for OpaqueGuard<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OpaqueGuard").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::OpaqueGuard;
+    use crate::Closure;
+    use core::cell::Cell;
+
+    #[test]
+    fn consume_now() {
+        let committed = Cell::new(false);
+        let cookie = OpaqueGuard::new(Closure(|| committed.set(true)));
+        assert_eq!(std::format!("{:?}", cookie), "OpaqueGuard { .. }");
+        OpaqueGuard::consume_now(cookie);
+        assert!(committed.get());
+    }
+}
@@ -0,0 +1,115 @@
+use crate::{Consume, ConsumeOnDrop};
+use core::ops::{
+    Add, AddAssign, BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Div, DivAssign,
+    Mul, MulAssign, Neg, Not, Rem, RemAssign, Shl, ShlAssign, Shr, ShrAssign, Sub, SubAssign,
+};
+
+// Compound assignment updates the guarded value in place, so it is forwarded for the guard
+// itself. The plain operators produce a new, unguarded value, so they are only forwarded for
+// shared references, where `&T` implements the operator.
+macro_rules! forward_binary_ops {
+    ($($op:ident::$method:ident, $assign:ident::$assign_method:ident;)*) => {$(
+        impl<T: Consume + $assign<R>, R> $assign<R> for ConsumeOnDrop<T> {
+            #[inline]
+            fn $assign_method(&mut self, rhs: R) {
+                (**self).$assign_method(rhs)
+            }
+        }
+
+        impl<'a, T: Consume, R> $op<R> for &'a ConsumeOnDrop<T>
+        where
+            &'a T: $op<R>,
+        {
+            type Output = <&'a T as $op<R>>::Output;
+
+            #[inline]
+            fn $method(self, rhs: R) -> Self::Output {
+                (&**self).$method(rhs)
+            }
+        }
+    )*};
+}
+
+forward_binary_ops! {
+    Add::add, AddAssign::add_assign;
+    Sub::sub, SubAssign::sub_assign;
+    Mul::mul, MulAssign::mul_assign;
+    Div::div, DivAssign::div_assign;
+    Rem::rem, RemAssign::rem_assign;
+    BitAnd::bitand, BitAndAssign::bitand_assign;
+    BitOr::bitor, BitOrAssign::bitor_assign;
+    BitXor::bitxor, BitXorAssign::bitxor_assign;
+    Shl::shl, ShlAssign::shl_assign;
+    Shr::shr, ShrAssign::shr_assign;
+}
+
+macro_rules! forward_unary_ops {
+    ($($op:ident::$method:ident;)*) => {$(
+        impl<'a, T: Consume> $op for &'a ConsumeOnDrop<T>
+        where
+            &'a T: $op,
+        {
+            type Output = <&'a T as $op>::Output;
+
+            #[inline]
+            fn $method(self) -> Self::Output {
+                (&**self).$method()
+            }
+        }
+    )*};
+}
+
+forward_unary_ops! {
+    Neg::neg;
+    Not::not;
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Consume, ConsumeOnDrop};
+    use core::cell::Cell;
+    use core::ops::{AddAssign, BitOr, Neg};
+
+    #[test]
+    fn forwards_operators() {
+        #[derive(Clone, Copy)]
+        struct Counter<'a>(i64, &'a Cell<i64>);
+
+        impl Consume for Counter<'_> {
+            fn consume(self) {
+                self.1.set(self.0)
+            }
+        }
+
+        impl AddAssign<i64> for Counter<'_> {
+            fn add_assign(&mut self, rhs: i64) {
+                self.0 += rhs
+            }
+        }
+
+        impl BitOr<i64> for &Counter<'_> {
+            type Output = i64;
+
+            fn bitor(self, rhs: i64) -> i64 {
+                self.0 | rhs
+            }
+        }
+
+        impl Neg for &Counter<'_> {
+            type Output = i64;
+
+            fn neg(self) -> i64 {
+                -self.0
+            }
+        }
+
+        let flushed = Cell::new(0);
+        let mut counter = ConsumeOnDrop::new(Counter(0, &flushed));
+        counter += 5;
+        counter += 1;
+        assert_eq!(&counter | 1, 7);
+        assert_eq!(-&counter, -6);
+        drop(counter);
+        assert_eq!(flushed.get(), 6);
+    }
+}
@@ -0,0 +1,254 @@
+use crate::{Consumer, WithConsumer};
+use std::io;
+
+#[cfg(unix)]
+pub use self::unix::*;
+#[cfg(windows)]
+pub use self::windows::*;
+
+/// The default error hook used by the raw handle consumers. It silently ignores
+/// the error, which matches what the standard library does when an owned handle
+/// fails to close.
+fn ignore_close_error<H>(_handle: H, _error: io::Error) {}
+
+#[cfg(unix)]
+mod unix {
+    use super::*;
+    use std::os::fd::{AsRawFd, RawFd};
+
+    /// A [`Consumer<RawFd>`] which closes the descriptor using `close(2)`.
+    ///
+    /// If `close` fails, the descriptor and the resulting [`io::Error`] are passed to
+    /// the hook `F`. By default, errors are ignored.
+    #[derive(Debug, Clone, Copy)]
+    pub struct CloseRawFd<F = fn(RawFd, io::Error)> {
+        on_error: F,
+    }
+
+    impl CloseRawFd {
+        /// Builds a consumer which ignores errors from `close`.
+        ///
+        /// # Safety
+        ///
+        /// Any descriptor consumed by the result must be open and owned by the caller,
+        /// in the sense of [`OwnedFd`](std::os::fd::OwnedFd), and must not be used again
+        /// once consumed.
+        #[inline]
+        pub const unsafe fn new() -> Self {
+            Self {
+                on_error: ignore_close_error,
+            }
+        }
+    }
+
+    impl<F: FnOnce(RawFd, io::Error)> CloseRawFd<F> {
+        /// Builds a consumer which reports errors from `close` to `on_error`.
+        ///
+        /// # Safety
+        ///
+        /// See [`CloseRawFd::new`].
+        #[inline]
+        pub const unsafe fn with_hook(on_error: F) -> Self {
+            Self { on_error }
+        }
+    }
+
+    impl<F: FnOnce(RawFd, io::Error)> Consumer<RawFd> for CloseRawFd<F> {
+        fn consume(self, fd: RawFd) {
+            // SAFETY: the constructors require that `fd` is owned and never used again.
+            if unsafe { libc::close(fd) } == -1 {
+                (self.on_error)(fd, io::Error::last_os_error())
+            }
+        }
+    }
+
+    /// An owned raw file descriptor which is closed with `close(2)` when dropped.
+    ///
+    /// Unlike [`OwnedFd`](std::os::fd::OwnedFd), errors from `close` can be observed
+    /// by supplying a hook.
+    #[derive(Debug)]
+    pub struct RawFdGuard<F: FnOnce(RawFd, io::Error) = fn(RawFd, io::Error)> {
+        inner: WithConsumer<RawFd, CloseRawFd<F>>,
+    }
+
+    impl RawFdGuard {
+        /// Takes ownership of `fd`, ignoring any error when it is closed.
+        ///
+        /// # Safety
+        ///
+        /// `fd` must be open and owned by the caller.
+        #[inline]
+        pub const unsafe fn from_raw_fd(fd: RawFd) -> Self {
+            Self {
+                inner: WithConsumer::new(fd, CloseRawFd::new()),
+            }
+        }
+    }
+
+    impl<F: FnOnce(RawFd, io::Error)> RawFdGuard<F> {
+        /// Takes ownership of `fd`, reporting any error when it is closed to `on_error`.
+        ///
+        /// # Safety
+        ///
+        /// `fd` must be open and owned by the caller.
+        #[inline]
+        pub const unsafe fn with_hook(fd: RawFd, on_error: F) -> Self {
+            Self {
+                inner: WithConsumer::new(fd, CloseRawFd::with_hook(on_error)),
+            }
+        }
+
+        /// Releases ownership of the descriptor without closing it.
+        #[inline]
+        pub fn into_raw_fd(x: Self) -> RawFd {
+            WithConsumer::into_inner(x.inner)
+        }
+    }
+
+    impl<F: FnOnce(RawFd, io::Error)> AsRawFd for RawFdGuard<F> {
+        #[inline]
+        fn as_raw_fd(&self) -> RawFd {
+            *self.inner
+        }
+    }
+}
+
+#[cfg(windows)]
+mod windows {
+    use super::*;
+    use std::os::windows::io::{AsRawHandle, RawHandle};
+
+    mod sys {
+        use std::os::windows::io::RawHandle;
+
+        #[link(name = "kernel32")]
+        extern "system" {
+            pub fn CloseHandle(handle: RawHandle) -> i32;
+        }
+    }
+
+    /// A [`Consumer<RawHandle>`] which closes the handle using `CloseHandle`.
+    ///
+    /// If `CloseHandle` fails, the handle and the resulting [`io::Error`] are passed to
+    /// the hook `F`. By default, errors are ignored.
+    #[derive(Debug, Clone, Copy)]
+    pub struct CloseRawHandle<F = fn(RawHandle, io::Error)> {
+        on_error: F,
+    }
+
+    impl CloseRawHandle {
+        /// Builds a consumer which ignores errors from `CloseHandle`.
+        ///
+        /// # Safety
+        ///
+        /// Any handle consumed by the result must be open and owned by the caller,
+        /// in the sense of [`OwnedHandle`](std::os::windows::io::OwnedHandle), and must
+        /// not be used again once consumed.
+        #[inline]
+        pub const unsafe fn new() -> Self {
+            Self {
+                on_error: ignore_close_error,
+            }
+        }
+    }
+
+    impl<F: FnOnce(RawHandle, io::Error)> CloseRawHandle<F> {
+        /// Builds a consumer which reports errors from `CloseHandle` to `on_error`.
+        ///
+        /// # Safety
+        ///
+        /// See [`CloseRawHandle::new`].
+        #[inline]
+        pub const unsafe fn with_hook(on_error: F) -> Self {
+            Self { on_error }
+        }
+    }
+
+    impl<F: FnOnce(RawHandle, io::Error)> Consumer<RawHandle> for CloseRawHandle<F> {
+        fn consume(self, handle: RawHandle) {
+            // SAFETY: the constructors require that `handle` is owned and never used again.
+            if unsafe { sys::CloseHandle(handle) } == 0 {
+                (self.on_error)(handle, io::Error::last_os_error())
+            }
+        }
+    }
+
+    /// An owned raw handle which is closed with `CloseHandle` when dropped.
+    ///
+    /// Unlike [`OwnedHandle`](std::os::windows::io::OwnedHandle), errors from
+    /// `CloseHandle` can be observed by supplying a hook.
+    #[derive(Debug)]
+    pub struct RawHandleGuard<F: FnOnce(RawHandle, io::Error) = fn(RawHandle, io::Error)> {
+        inner: WithConsumer<RawHandle, CloseRawHandle<F>>,
+    }
+
+    impl RawHandleGuard {
+        /// Takes ownership of `handle`, ignoring any error when it is closed.
+        ///
+        /// # Safety
+        ///
+        /// `handle` must be open and owned by the caller.
+        #[inline]
+        pub const unsafe fn from_raw_handle(handle: RawHandle) -> Self {
+            Self {
+                inner: WithConsumer::new(handle, CloseRawHandle::new()),
+            }
+        }
+    }
+
+    impl<F: FnOnce(RawHandle, io::Error)> RawHandleGuard<F> {
+        /// Takes ownership of `handle`, reporting any error when it is closed to `on_error`.
+        ///
+        /// # Safety
+        ///
+        /// `handle` must be open and owned by the caller.
+        #[inline]
+        pub const unsafe fn with_hook(handle: RawHandle, on_error: F) -> Self {
+            Self {
+                inner: WithConsumer::new(handle, CloseRawHandle::with_hook(on_error)),
+            }
+        }
+
+        /// Releases ownership of the handle without closing it.
+        #[inline]
+        pub fn into_raw_handle(x: Self) -> RawHandle {
+            WithConsumer::into_inner(x.inner)
+        }
+    }
+
+    impl<F: FnOnce(RawHandle, io::Error)> AsRawHandle for RawHandleGuard<F> {
+        #[inline]
+        fn as_raw_handle(&self) -> RawHandle {
+            *self.inner
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::RawFdGuard;
+    use std::io::{self, Read};
+    use std::os::fd::IntoRawFd;
+    use std::os::unix::net::UnixStream;
+
+    #[test]
+    fn closes_descriptor() {
+        let (ours, mut theirs) = UnixStream::pair().unwrap();
+        let guard = unsafe { RawFdGuard::from_raw_fd(ours.into_raw_fd()) };
+        drop(guard);
+        // The other end sees EOF once our end is closed.
+        let mut buf = [0; 1];
+        assert_eq!(theirs.read(&mut buf).unwrap(), 0);
+    }
+
+    #[test]
+    fn reports_close_errors() {
+        let mut reported = None;
+        drop(unsafe {
+            RawFdGuard::with_hook(-1, |fd, err: io::Error| {
+                reported = Some((fd, err.raw_os_error()))
+            })
+        });
+        assert_eq!(reported, Some((-1, Some(libc::EBADF))));
+    }
+}
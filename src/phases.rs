@@ -0,0 +1,170 @@
+use crate::composite::consume_all;
+use crate::{Closure, Consume, ConsumeOnDrop};
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+type Cleanup<'a> = Closure<Box<dyn FnOnce() + 'a>>;
+type Barrier<'a, P> = Box<dyn FnMut(&P) + 'a>;
+
+/// The cleanups which have not run yet, grouped by phase.
+struct Pending<'a, P>(BTreeMap<P, Vec<Cleanup<'a>>>);
+
+impl<P> Consume for Pending<'_, P> {
+    #[inline]
+    fn consume(self) {
+        consume_all(
+            self.0
+                .into_values()
+                .rev()
+                .flat_map(|cleanups| cleanups.into_iter().rev()),
+        )
+    }
+}
+
+/// A registry of cleanups grouped into ordered shutdown phases.
+///
+/// When a [`Phases`] is dropped, its phases run one at a time, starting with the greatest phase.
+/// Every cleanup in a phase is consumed before any cleanup in the next phase starts, and the
+/// cleanups within a phase are consumed in reverse registration order. For example, with
+/// `enum Phase { Logging, Sockets, Databases }` deriving [`Ord`], databases are shut down first and
+/// logging last, no matter in which order they were registered.
+///
+/// A barrier set with [`Phases::with_barrier`] runs after each phase, before the next one
+/// starts, e.g. to wait for work which the cleanups of that phase handed off to other threads.
+///
+/// If a cleanup panics, the remaining cleanups are still consumed while unwinding, in the same
+/// order, but the barrier is not called anymore.
+pub struct Phases<'a, P: Ord> {
+    phases: ConsumeOnDrop<Pending<'a, P>>,
+    barrier: Option<Barrier<'a, P>>,
+}
+
+impl<'a, P: Ord> Phases<'a, P> {
+    /// Builds an empty [`Phases`].
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            phases: ConsumeOnDrop::new(Pending(BTreeMap::new())),
+            barrier: None,
+        }
+    }
+
+    /// Builds an empty [`Phases`] which calls `barrier` with each phase once all of its
+    /// cleanups have run.
+    #[inline]
+    pub fn with_barrier(barrier: impl FnMut(&P) + 'a) -> Self {
+        Self {
+            phases: ConsumeOnDrop::new(Pending(BTreeMap::new())),
+            barrier: Some(Box::new(barrier)),
+        }
+    }
+
+    /// Registers `value` to be consumed during `phase`.
+    pub fn register(&mut self, phase: P, value: impl Consume + 'a) {
+        let cleanup = Closure(Box::new(move || value.consume()) as Box<_>);
+        self.phases.0.entry(phase).or_default().push(cleanup);
+    }
+
+    /// The total number of cleanups waiting to run.
+    pub fn len(&self) -> usize {
+        self.phases.0.values().map(Vec::len).sum()
+    }
+
+    /// Whether there are no cleanups waiting to run.
+    pub fn is_empty(&self) -> bool {
+        self.phases.0.is_empty()
+    }
+
+    /// Runs every cleanup in the greatest remaining phase, then the barrier, returning that
+    /// phase, or `None` if there is nothing left to run. This lets the caller do its own work
+    /// between phases.
+    pub fn run_phase(&mut self) -> Option<P> {
+        let (phase, cleanups) = self.phases.0.pop_last()?;
+        consume_all(cleanups.into_iter().rev());
+        if let Some(barrier) = &mut self.barrier {
+            barrier(&phase)
+        }
+        Some(phase)
+    }
+
+    /// Runs every phase now. This is equivalent to dropping `phases`.
+    #[inline]
+    pub fn run(phases: Self) {
+        drop(phases)
+    }
+}
+
+impl<'a, P: Ord> Default for Phases<'a, P> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a, P: Ord> Drop for Phases<'a, P> {
+    fn drop(&mut self) {
+        // If a cleanup panics, `self.phases` consumes the rest while unwinding.
+        while self.run_phase().is_some() {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Phases;
+    use crate::Closure;
+    use alloc::vec::Vec;
+    use core::cell::RefCell;
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+    enum Phase {
+        Logging,
+        Sockets,
+        Databases,
+    }
+
+    #[test]
+    fn phases_run_greatest_first() {
+        let log = RefCell::new(Vec::new());
+        let log = &log;
+        let record = |name| Closure(move || log.borrow_mut().push(name));
+
+        let mut phases = Phases::new();
+        phases.register(Phase::Logging, record("logger"));
+        phases.register(Phase::Databases, record("db 1"));
+        phases.register(Phase::Sockets, record("socket"));
+        phases.register(Phase::Databases, record("db 2"));
+        assert_eq!(phases.len(), 4);
+
+        assert_eq!(phases.run_phase(), Some(Phase::Databases));
+        assert_eq!(*log.borrow(), ["db 2", "db 1"]);
+        Phases::run(phases);
+        assert_eq!(*log.borrow(), ["db 2", "db 1", "socket", "logger"]);
+    }
+
+    #[test]
+    fn barriers_and_panics() {
+        let log = RefCell::new(Vec::new());
+        let log = &log;
+        let record = |name| Closure(move || log.borrow_mut().push(name));
+
+        let mut phases = Phases::with_barrier(|phase: &Phase| {
+            log.borrow_mut().push(match phase {
+                Phase::Logging => "after logging",
+                Phase::Sockets => "after sockets",
+                Phase::Databases => "after databases",
+            })
+        });
+        phases.register(Phase::Logging, record("logger"));
+        phases.register(Phase::Sockets, record("socket 1"));
+        phases.register(Phase::Sockets, Closure(|| panic!("socket 2")));
+        phases.register(Phase::Sockets, record("socket 3"));
+        phases.register(Phase::Databases, record("db"));
+        assert!(catch_unwind(AssertUnwindSafe(|| Phases::run(phases))).is_err());
+        assert_eq!(
+            *log.borrow(),
+            ["db", "after databases", "socket 3", "socket 1", "logger"]
+        );
+    }
+}
@@ -0,0 +1,157 @@
+use crate::{Consume, Consumer, SelfConsumer, WithConsumer};
+use core::fmt;
+use core::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// The error returned by [`PoisonToken::check`] once the token has been poisoned.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Poisoned;
+
+impl fmt::Display for Poisoned {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a related guard was consumed during a panic")
+    }
+}
+
+impl std::error::Error for Poisoned {}
+
+/// A shared flag, like the poison flag of a [`Mutex`](std::sync::Mutex), which is set when a
+/// guard watching it is consumed because of a panic.
+///
+/// Coordinated rollback across several objects needs one signal that something went wrong.
+/// Guard each object with a consumer from [`PoisonToken::watch`], or with
+/// [`PoisonToken::guard`], and give clones of the token to the components which must check it
+/// before trusting the related state.
+#[derive(Clone, Default)]
+pub struct PoisonToken {
+    poisoned: Arc<AtomicBool>,
+}
+
+impl PoisonToken {
+    /// Builds a token which is not poisoned.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the token has been poisoned.
+    #[inline]
+    pub fn is_poisoned(&self) -> bool {
+        self.poisoned.load(Ordering::Acquire)
+    }
+
+    /// Returns [`Poisoned`] if the token has been poisoned.
+    #[inline]
+    pub fn check(&self) -> Result<(), Poisoned> {
+        if self.is_poisoned() {
+            Err(Poisoned)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Poisons the token by hand.
+    #[inline]
+    pub fn poison(&self) {
+        self.poisoned.store(true, Ordering::Release)
+    }
+
+    /// Clears the poison, e.g. once the related state has been repaired.
+    #[inline]
+    pub fn clear_poison(&self) {
+        self.poisoned.store(false, Ordering::Release)
+    }
+
+    /// Wraps `consumer` so that it poisons this token if it runs during a panic, or panics
+    /// itself.
+    #[inline]
+    pub fn watch<Q>(&self, consumer: Q) -> PoisonOnUnwind<Q> {
+        PoisonOnUnwind {
+            consumer,
+            token: self.clone(),
+        }
+    }
+
+    /// Guards `value`, which is consumed with [`Consume::consume`] when dropped, poisoning this
+    /// token if that happens during a panic.
+    #[inline]
+    pub fn guard<T: Consume>(&self, value: T) -> PoisonGuard<T> {
+        WithConsumer::new(value, self.watch(SelfConsumer))
+    }
+}
+
+impl fmt::Debug for PoisonToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PoisonToken")
+            .field("poisoned", &self.is_poisoned())
+            .finish()
+    }
+}
+
+/// Poisons a token if dropped while the thread is panicking.
+struct Sentinel<'a>(&'a PoisonToken);
+
+impl Drop for Sentinel<'_> {
+    fn drop(&mut self) {
+        if std::thread::panicking() {
+            self.0.poison()
+        }
+    }
+}
+
+/// A consumer which runs `Q`, and poisons a [`PoisonToken`] if it runs while the thread is
+/// panicking, or if `Q` panics. See [`PoisonToken::watch`].
+#[derive(Clone, Debug)]
+pub struct PoisonOnUnwind<Q> {
+    consumer: Q,
+    token: PoisonToken,
+}
+
+impl<Q> PoisonOnUnwind<Q> {
+    /// The token this consumer poisons.
+    #[inline]
+    pub fn token(&self) -> &PoisonToken {
+        &self.token
+    }
+}
+
+impl<T, Q: Consumer<T>> Consumer<T> for PoisonOnUnwind<Q> {
+    fn consume(self, other: T) {
+        let sentinel = Sentinel(&self.token);
+        self.consumer.consume(other);
+        drop(sentinel)
+    }
+}
+
+/// A value which poisons a [`PoisonToken`] if it is consumed during a panic.
+/// See [`PoisonToken::guard`].
+pub type PoisonGuard<T, Q = SelfConsumer> = WithConsumer<T, PoisonOnUnwind<Q>>;
+
+#[cfg(test)]
+mod tests {
+    use super::{PoisonToken, Poisoned};
+    use crate::Closure;
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    #[test]
+    fn poisons_on_unwind() {
+        let token = PoisonToken::new();
+        drop(token.guard(Closure(|| {})));
+        assert_eq!(token.check(), Ok(()));
+
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            let _rollback = token.guard(Closure(|| {}));
+            panic!("transfer failed")
+        }));
+        assert!(result.is_err());
+        assert_eq!(token.clone().check(), Err(Poisoned));
+
+        token.clear_poison();
+        let failing = token.watch(Closure(|()| panic!("rollback failed")));
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            drop(crate::WithConsumer::new((), failing))
+        }));
+        assert!(result.is_err());
+        assert!(token.is_poisoned());
+    }
+}
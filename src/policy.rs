@@ -0,0 +1,214 @@
+use crate::{Consume, ConsumeOnDrop};
+use core::mem;
+use core::ops::{Deref, DerefMut};
+
+/// What a [`PolicyGuard`] does with its value when dropped.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ConsumePolicy {
+    /// The value is consumed with [`Consume::consume`].
+    #[default]
+    Consume,
+    /// The value is dropped normally, without being consumed.
+    Drop,
+    /// The value is leaked with [`mem::forget`], e.g. because ownership of the underlying
+    /// resource has been handed to foreign code.
+    Leak,
+}
+
+#[derive(Clone, Debug, Default)]
+struct WithPolicy<T> {
+    value: T,
+    policy: ConsumePolicy,
+}
+
+impl<T: Consume> Consume for WithPolicy<T> {
+    #[inline]
+    fn consume(self) {
+        match self.policy {
+            ConsumePolicy::Consume => self.value.consume(),
+            ConsumePolicy::Drop => drop(self.value),
+            ConsumePolicy::Leak => mem::forget(self.value),
+        }
+    }
+}
+
+/// A guard whose behavior when dropped is chosen by a [`ConsumePolicy`], which can be changed at
+/// any time. This suits frameworks which only decide late whether a resource should be
+/// destroyed or handed off.
+#[derive(Clone, Debug, Default)]
+pub struct PolicyGuard<T: Consume> {
+    inner: ConsumeOnDrop<WithPolicy<T>>,
+}
+
+impl<T: Consume> PolicyGuard<T> {
+    /// Guards `value`, which will be consumed unless the policy is changed.
+    #[inline]
+    pub const fn new(value: T) -> Self {
+        Self::with_policy(value, ConsumePolicy::Consume)
+    }
+
+    /// Guards `value` with the given initial policy.
+    #[inline]
+    pub const fn with_policy(value: T, policy: ConsumePolicy) -> Self {
+        Self {
+            inner: ConsumeOnDrop::new(WithPolicy { value, policy }),
+        }
+    }
+
+    /// The current policy.
+    #[inline]
+    pub fn policy(x: &Self) -> ConsumePolicy {
+        x.inner.policy
+    }
+
+    /// Changes what happens when `x` is dropped.
+    #[inline]
+    pub fn set_policy(x: &mut Self, policy: ConsumePolicy) {
+        x.inner.policy = policy
+    }
+
+    /// Unwraps the value without consuming it, regardless of the policy.
+    #[inline]
+    pub fn into_inner(x: Self) -> T {
+        ConsumeOnDrop::into_inner(x.inner).value
+    }
+}
+
+impl<T: Consume> Deref for PolicyGuard<T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        &self.inner.value
+    }
+}
+
+impl<T: Consume> DerefMut for PolicyGuard<T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.inner.value
+    }
+}
+
+/// A guard which consumes its value when dropped while armed, and drops it normally while
+/// disarmed.
+///
+/// Unlike [`ConsumeOnDrop::into_inner`], disarming is not final: the guard keeps the value, and
+/// can be rearmed any number of times, which suits commit and abort flows where the decision
+/// changes along the way. It is a [`PolicyGuard`] restricted to [`ConsumePolicy::Consume`] and
+/// [`ConsumePolicy::Drop`].
+#[derive(Clone, Debug, Default)]
+pub struct ArmedGuard<T: Consume>(PolicyGuard<T>);
+
+impl<T: Consume> ArmedGuard<T> {
+    /// Guards `value`, armed.
+    #[inline]
+    pub const fn new(value: T) -> Self {
+        Self(PolicyGuard::new(value))
+    }
+
+    /// Guards `value`, disarmed.
+    #[inline]
+    pub const fn disarmed(value: T) -> Self {
+        Self(PolicyGuard::with_policy(value, ConsumePolicy::Drop))
+    }
+
+    /// Whether `x` will consume its value when dropped.
+    #[inline]
+    pub fn is_armed(x: &Self) -> bool {
+        PolicyGuard::policy(&x.0) == ConsumePolicy::Consume
+    }
+
+    /// Makes `x` drop its value normally when dropped, until it is rearmed.
+    #[inline]
+    pub fn disarm(x: &mut Self) {
+        PolicyGuard::set_policy(&mut x.0, ConsumePolicy::Drop)
+    }
+
+    /// Makes `x` consume its value when dropped again.
+    #[inline]
+    pub fn rearm(x: &mut Self) {
+        PolicyGuard::set_policy(&mut x.0, ConsumePolicy::Consume)
+    }
+
+    /// Unwraps the value without consuming it, whether or not `x` is armed.
+    #[inline]
+    pub fn into_inner(x: Self) -> T {
+        PolicyGuard::into_inner(x.0)
+    }
+}
+
+impl<T: Consume> Deref for ArmedGuard<T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: Consume> DerefMut for ArmedGuard<T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ArmedGuard, ConsumePolicy, PolicyGuard};
+    use crate::Consume;
+    use core::cell::Cell;
+
+    #[test]
+    fn policies() {
+        struct Tracked<'a>(&'a Cell<(u32, u32)>);
+
+        impl Consume for Tracked<'_> {
+            fn consume(self) {
+                let (consumed, dropped) = self.0.get();
+                self.0.set((consumed + 1, dropped));
+                core::mem::forget(self);
+            }
+        }
+
+        impl Drop for Tracked<'_> {
+            fn drop(&mut self) {
+                let (consumed, dropped) = self.0.get();
+                self.0.set((consumed, dropped + 1));
+            }
+        }
+
+        let counts = Cell::new((0, 0));
+        drop(PolicyGuard::new(Tracked(&counts)));
+        assert_eq!(counts.get(), (1, 0));
+
+        let mut guard = PolicyGuard::new(Tracked(&counts));
+        PolicyGuard::set_policy(&mut guard, ConsumePolicy::Drop);
+        drop(guard);
+        assert_eq!(counts.get(), (1, 1));
+
+        let guard = PolicyGuard::with_policy(Tracked(&counts), ConsumePolicy::Leak);
+        assert_eq!(PolicyGuard::policy(&guard), ConsumePolicy::Leak);
+        drop(guard);
+        assert_eq!(counts.get(), (1, 1));
+    }
+
+    #[test]
+    fn disarm_and_rearm() {
+        let consumed = Cell::new(0);
+        let mut guard = ArmedGuard::new(crate::Closure(|| consumed.set(consumed.get() + 1)));
+        ArmedGuard::disarm(&mut guard);
+        ArmedGuard::rearm(&mut guard);
+        assert!(ArmedGuard::is_armed(&guard));
+        drop(guard);
+        assert_eq!(consumed.get(), 1);
+
+        let mut guard = ArmedGuard::disarmed(crate::Closure(|| consumed.set(consumed.get() + 1)));
+        assert!(!ArmedGuard::is_armed(&guard));
+        ArmedGuard::rearm(&mut guard);
+        ArmedGuard::disarm(&mut guard);
+        drop(guard);
+        assert_eq!(consumed.get(), 1);
+    }
+}
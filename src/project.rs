@@ -0,0 +1,110 @@
+/// Splits a [`ConsumeOnDrop`](crate::ConsumeOnDrop) around a struct into one guard per field.
+///
+/// `project_guard!(guard => Path { a, b, .. })` unwraps `guard` without consuming the struct,
+/// and evaluates to a tuple `(ConsumeOnDrop<A>, ConsumeOnDrop<B>)` guarding the named fields,
+/// in the order they are listed. Each field type must implement [`Consume`](crate::Consume).
+/// The guards can then be moved to different owners, and each field is consumed exactly once,
+/// when its own guard is dropped. The struct's own [`Consume::consume`](crate::Consume::consume)
+/// never runs.
+///
+/// Destructuring by hand leaves a window in which the fields are unguarded. Here, no code runs
+/// between unwrapping the struct and guarding its fields. Fields omitted with a trailing `..`
+/// are dropped normally, after the guards have been built, so a panic while dropping them still
+/// consumes the guarded fields.
+///
+/// ```
+/// use consume_on_drop::{project_guard, Closure, ConsumeOnDrop, Consume};
+///
+/// struct Session<R, W> {
+///     reader: R,
+///     writer: W,
+///     id: u64,
+/// }
+///
+/// impl<R: Consume, W: Consume> Consume for Session<R, W> {
+///     fn consume(self) {
+///         self.writer.consume();
+///         self.reader.consume();
+///     }
+/// }
+///
+/// let session = ConsumeOnDrop::new(Session {
+///     reader: Closure(|| println!("reader closed")),
+///     writer: Closure(|| println!("writer flushed")),
+///     id: 7,
+/// });
+/// let (reader, writer) = project_guard!(session => Session { reader, writer, .. });
+/// drop(writer); // the reader stays open
+/// # drop(reader);
+/// ```
+///
+/// Types which implement [`Drop`] cannot be moved out of, so they cannot be projected:
+///
+/// ```compile_fail
+/// use consume_on_drop::{project_guard, ConsumeOnDrop, Consume};
+///
+/// struct Token;
+///
+/// impl Consume for Token {
+///     fn consume(self) {}
+/// }
+///
+/// struct Pair {
+///     first: Token,
+/// }
+///
+/// impl Consume for Pair {
+///     fn consume(self) {}
+/// }
+///
+/// impl Drop for Pair {
+///     fn drop(&mut self) {}
+/// }
+///
+/// let pair = ConsumeOnDrop::new(Pair { first: Token });
+/// let (first,) = project_guard!(pair => Pair { first });
+/// ```
+#[macro_export]
+macro_rules! project_guard {
+    ($guard:expr => $path:path { $($field:ident),+ $(,)? }) => {{
+        let $path { $($field),+ } = $crate::ConsumeOnDrop::into_inner($guard);
+        ($($crate::ConsumeOnDrop::new($field),)+)
+    }};
+    ($guard:expr => $path:path { $($field:ident,)+ .. }) => {{
+        let value = $crate::ConsumeOnDrop::into_inner($guard);
+        let $path { $($field,)+ .. } = value;
+        ($($crate::ConsumeOnDrop::new($field),)+)
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Closure, Consume, ConsumeOnDrop};
+    use alloc::vec::Vec;
+    use core::cell::RefCell;
+
+    struct Pair<A, B> {
+        first: A,
+        second: B,
+    }
+
+    impl<A: Consume, B: Consume> Consume for Pair<A, B> {
+        fn consume(self) {
+            unreachable!("a projected guard never consumes the whole")
+        }
+    }
+
+    #[test]
+    fn fields_consumed_separately() {
+        let log = RefCell::new(Vec::new());
+        let log = &log;
+        let pair = ConsumeOnDrop::new(Pair {
+            first: Closure(|| log.borrow_mut().push(1)),
+            second: Closure(|| log.borrow_mut().push(2)),
+        });
+        let (first, second) = project_guard!(pair => Pair { first, second });
+        drop(second);
+        drop(first);
+        assert_eq!(*log.borrow(), [2, 1]);
+    }
+}
@@ -0,0 +1,147 @@
+use crate::{Consumer, WithConsumer};
+use core::mem;
+use pyo3::{ffi, Py, PyAny, Python};
+
+/// What a Python consumer does with an object which is dropped while the interpreter is not
+/// running, e.g. during finalization, when the GIL can no longer be acquired.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Finalizing {
+    /// Leak the reference. This is always sound, and the interpreter reclaims the object, if
+    /// at all, as it shuts down.
+    #[default]
+    Leak,
+    /// Hand the reference to `pyo3`, which releases it the next time the GIL is acquired.
+    Defer,
+}
+
+fn interpreter_running() -> bool {
+    // SAFETY: `Py_IsInitialized` may be called at any time, with or without the GIL.
+    unsafe { ffi::Py_IsInitialized() != 0 }
+}
+
+fn not_running<T>(object: Py<T>, finalizing: Finalizing) {
+    match finalizing {
+        Finalizing::Leak => mem::forget(object),
+        Finalizing::Defer => drop(object),
+    }
+}
+
+/// A [`Consumer<Py<T>>`] which acquires the GIL and releases the reference right away.
+///
+/// Dropping a [`Py<T>`] without holding the GIL only queues the release until some thread next
+/// acquires the GIL, and acquiring the GIL is not possible at all while the interpreter is
+/// finalizing. This consumer releases the reference deterministically, and applies a
+/// [`Finalizing`] policy when the interpreter is not running.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ReleasePy {
+    finalizing: Finalizing,
+}
+
+impl ReleasePy {
+    /// Builds a consumer which leaks references dropped while the interpreter is not running.
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            finalizing: Finalizing::Leak,
+        }
+    }
+
+    /// Sets the policy for references dropped while the interpreter is not running.
+    #[inline]
+    pub const fn with_finalizing(self, finalizing: Finalizing) -> Self {
+        Self { finalizing }
+    }
+}
+
+impl<T> Consumer<Py<T>> for ReleasePy {
+    fn consume(self, other: Py<T>) {
+        if interpreter_running() {
+            Python::with_gil(|_| drop(other))
+        } else {
+            not_running(other, self.finalizing)
+        }
+    }
+}
+
+/// A [`Consumer<Py<T>>`] which acquires the GIL and calls a method on the object, such as
+/// `close()`, before releasing the reference.
+///
+/// Like an exception raised by `__del__`, an exception raised by the method is reported with
+/// `sys.unraisablehook`. If the interpreter is not running, the method is not called, and the
+/// [`Finalizing`] policy is applied instead.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct CallPyMethod {
+    method: &'static str,
+    finalizing: Finalizing,
+}
+
+impl CallPyMethod {
+    /// Builds a consumer which calls `method`.
+    #[inline]
+    pub const fn new(method: &'static str) -> Self {
+        Self {
+            method,
+            finalizing: Finalizing::Leak,
+        }
+    }
+
+    /// Builds a consumer which calls `close()`.
+    #[inline]
+    pub const fn close() -> Self {
+        Self::new("close")
+    }
+
+    /// Sets the policy for objects dropped while the interpreter is not running.
+    #[inline]
+    pub const fn with_finalizing(self, finalizing: Finalizing) -> Self {
+        Self { finalizing, ..self }
+    }
+
+    /// The name of the method this consumer calls.
+    #[inline]
+    pub const fn method(&self) -> &'static str {
+        self.method
+    }
+}
+
+impl<T> Consumer<Py<T>> for CallPyMethod {
+    fn consume(self, other: Py<T>) {
+        if !interpreter_running() {
+            return not_running(other, self.finalizing);
+        }
+        Python::with_gil(|py| {
+            let object: Py<PyAny> = other.into_any();
+            if let Err(error) = object.call_method0(py, self.method) {
+                error.write_unraisable(py, Some(object.bind(py)))
+            }
+        })
+    }
+}
+
+/// A Python object which is released with the GIL held when dropped. See [`ReleasePy`] and
+/// [`CallPyMethod`].
+pub type PyGuard<T = PyAny, Q = ReleasePy> = WithConsumer<Py<T>, Q>;
+
+#[cfg(test)]
+mod tests {
+    use super::{CallPyMethod, PyGuard, ReleasePy};
+    use pyo3::types::PyAnyMethods;
+    use pyo3::{Py, PyAny, Python};
+
+    #[test]
+    fn closes_and_releases() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let io = py.import("io").unwrap();
+            let stream: Py<PyAny> = io.call_method0("StringIO").unwrap().unbind();
+            let refs = stream.get_refcnt(py);
+            drop(PyGuard::new(stream.clone_ref(py), ReleasePy::new()));
+            assert_eq!(stream.get_refcnt(py), refs);
+
+            drop(PyGuard::new(stream.clone_ref(py), CallPyMethod::close()));
+            let closed = stream.getattr(py, "closed").unwrap();
+            assert!(closed.extract::<bool>(py).unwrap());
+        })
+    }
+}
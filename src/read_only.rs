@@ -0,0 +1,77 @@
+use crate::{Consume, ConsumeOnDrop};
+use core::ops::Deref;
+
+/// A [`ConsumeOnDrop<T>`] which only gives out shared references to the underlying `T`.
+///
+/// Use this when the invariants `T` relies on at consumption time must not be disturbed
+/// after construction. Like [`ConsumeOnDrop`], this is a zero-overhead wrapper around `T`.
+#[repr(transparent)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ReadOnlyConsumeOnDrop<T: Consume> {
+    inner: ConsumeOnDrop<T>,
+}
+
+impl<T: Consume> ReadOnlyConsumeOnDrop<T> {
+    /// Wraps a `T` in a [`ReadOnlyConsumeOnDrop`].
+    #[inline]
+    pub const fn new(value: T) -> Self {
+        Self {
+            inner: ConsumeOnDrop::new(value),
+        }
+    }
+
+    /// Unwraps the underlying `T`.
+    #[inline]
+    pub fn into_inner(slot: Self) -> T {
+        ConsumeOnDrop::into_inner(slot.inner)
+    }
+}
+
+impl<T: Consume> From<ConsumeOnDrop<T>> for ReadOnlyConsumeOnDrop<T> {
+    /// Gives up mutable access to a [`ConsumeOnDrop`]. The value stays guarded throughout.
+    #[inline]
+    fn from(inner: ConsumeOnDrop<T>) -> Self {
+        Self { inner }
+    }
+}
+
+impl<T: Consume> Deref for ReadOnlyConsumeOnDrop<T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        self.inner.deref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ReadOnlyConsumeOnDrop;
+    use crate::{Consume, ConsumeOnDrop};
+    use core::cell::Cell;
+    use core::mem::size_of;
+
+    #[test]
+    fn read_only_consume() {
+        struct Checked<'a> {
+            expected: u32,
+            seen: &'a Cell<Option<u32>>,
+        }
+
+        impl<'a> Consume for Checked<'a> {
+            fn consume(self) {
+                self.seen.set(Some(self.expected))
+            }
+        }
+
+        let seen = Cell::new(None);
+        let guard = ReadOnlyConsumeOnDrop::from(ConsumeOnDrop::new(Checked {
+            expected: 7,
+            seen: &seen,
+        }));
+        assert_eq!(guard.expected, 7);
+        assert_eq!(size_of::<ReadOnlyConsumeOnDrop<Checked>>(), size_of::<Checked>());
+        drop(guard);
+        assert_eq!(seen.get(), Some(7));
+    }
+}
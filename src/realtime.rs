@@ -0,0 +1,186 @@
+use crate::{Consume, Consumer};
+
+/// Marks a [`Consume`] or [`Consumer`] as safe to run on a realtime thread: it must not
+/// allocate, free, lock, or otherwise block.
+///
+/// Without the `realtime-debug` feature, this is a zero-cost wrapper which simply forwards to
+/// the wrapped value. With it, consumption runs in a realtime section, and any violation
+/// detected while the section runs makes consumption panic once the wrapped value returns.
+/// Allocations are detected by installing [`RealtimeAllocator`] as the global allocator,
+/// and blocking operations by calling [`realtime_violation`] from them.
+#[repr(transparent)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Realtime<Q>(pub Q);
+
+impl<Q: Consume> Consume for Realtime<Q> {
+    #[inline]
+    fn consume(self) {
+        run_realtime(|| self.0.consume())
+    }
+}
+
+impl<T, Q: Consumer<T>> Consumer<T> for Realtime<Q> {
+    #[inline]
+    fn consume(self, other: T) {
+        run_realtime(|| self.0.consume(other))
+    }
+}
+
+#[cfg(not(feature = "realtime-debug"))]
+#[inline(always)]
+fn run_realtime(f: impl FnOnce()) {
+    f()
+}
+
+#[cfg(feature = "realtime-debug")]
+use self::detect::run_realtime;
+#[cfg(feature = "realtime-debug")]
+pub use self::detect::{realtime_violation, RealtimeAllocator};
+
+#[cfg(feature = "realtime-debug")]
+mod detect {
+    use core::alloc::{GlobalAlloc, Layout};
+    use core::cell::Cell;
+
+    std::thread_local! {
+        /// The nesting depth of realtime sections on this thread, and the first violation seen
+        /// in the outermost one.
+        static SECTION: Cell<(usize, Option<&'static str>)> = const { Cell::new((0, None)) };
+    }
+
+    /// Records that the operation `what` happened on this thread. If a
+    /// [`Realtime`](crate::Realtime) consumer is running, it will panic with a message naming
+    /// `what` once it returns; otherwise, this does nothing.
+    ///
+    /// Call this from blocking operations, such as lock wrappers, which realtime code must avoid.
+    pub fn realtime_violation(what: &'static str) {
+        // The thread-local may already be destroyed if this is called during thread exit.
+        let _ = SECTION.try_with(|section| match section.get() {
+            (depth, None) if depth > 0 => section.set((depth, Some(what))),
+            _ => {}
+        });
+    }
+
+    /// Leaves a realtime section, even when unwinding. Leaving the outermost one forgets its
+    /// violation, so that it cannot leak into the next section on this thread.
+    struct Exit;
+
+    impl Drop for Exit {
+        fn drop(&mut self) {
+            SECTION.with(|section| {
+                let (depth, violation) = section.get();
+                let depth = depth - 1;
+                section.set((depth, if depth == 0 { None } else { violation }));
+            })
+        }
+    }
+
+    pub(super) fn run_realtime(f: impl FnOnce()) {
+        SECTION.with(|section| {
+            let (depth, violation) = section.get();
+            section.set((depth + 1, violation));
+        });
+        let exit = Exit;
+        f();
+        let violation = SECTION.with(|section| match section.get() {
+            (1, violation) => violation,
+            _ => None,
+        });
+        drop(exit);
+        if let Some(what) = violation {
+            panic!("a realtime consumer {}", what)
+        }
+    }
+
+    /// A global allocator which forwards to `A`, reporting every allocation and deallocation made
+    /// while a [`Realtime`](crate::Realtime) consumer runs with [`realtime_violation`].
+    ///
+    /// Install it in test binaries:
+    ///
+    /// ```ignore
+    /// #[global_allocator]
+    /// static ALLOC: RealtimeAllocator<std::alloc::System> = RealtimeAllocator(std::alloc::System);
+    /// ```
+    #[derive(Clone, Copy, Debug, Default)]
+    pub struct RealtimeAllocator<A>(pub A);
+
+    // SAFETY: every method forwards to `A` unchanged.
+    unsafe impl<A: GlobalAlloc> GlobalAlloc for RealtimeAllocator<A> {
+        #[inline]
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            realtime_violation("allocated");
+            self.0.alloc(layout)
+        }
+
+        #[inline]
+        unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+            realtime_violation("allocated");
+            self.0.alloc_zeroed(layout)
+        }
+
+        #[inline]
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            realtime_violation("freed memory");
+            self.0.dealloc(ptr, layout)
+        }
+
+        #[inline]
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            realtime_violation("reallocated");
+            self.0.realloc(ptr, layout, new_size)
+        }
+    }
+}
+
+#[cfg(all(test, feature = "realtime-debug"))]
+mod tests {
+    use super::{realtime_violation, Realtime, RealtimeAllocator};
+    use crate::{Closure, ConsumeOnDrop, WithConsumer};
+    use std::panic::catch_unwind;
+    use std::string::String;
+    use std::vec::Vec;
+
+    #[global_allocator]
+    static ALLOC: RealtimeAllocator<std::alloc::System> = RealtimeAllocator(std::alloc::System);
+
+    #[test]
+    fn detects_violations() {
+        let mut total = 0;
+        drop(WithConsumer::new(5, Realtime(Closure(|x| total += x))));
+        assert_eq!(total, 5);
+
+        let mut log = Vec::new();
+        let panic = catch_unwind(move || {
+            drop(WithConsumer::new(5, Realtime(Closure(|x| log.push(x)))));
+        })
+        .unwrap_err();
+        assert_eq!(
+            panic.downcast_ref::<String>().unwrap(),
+            "a realtime consumer allocated"
+        );
+
+        let panic = catch_unwind(|| {
+            drop(ConsumeOnDrop::new(Realtime(Closure(|| {
+                realtime_violation("locked a mutex")
+            }))));
+        })
+        .unwrap_err();
+        assert_eq!(
+            panic.downcast_ref::<String>().unwrap(),
+            "a realtime consumer locked a mutex"
+        );
+    }
+
+    #[test]
+    fn panics_do_not_leak_violations() {
+        let panic = catch_unwind(|| {
+            drop(ConsumeOnDrop::new(Realtime(Closure(|| panic!("boom")))));
+        })
+        .unwrap_err();
+        assert_eq!(*panic.downcast_ref::<&str>().unwrap(), "boom");
+
+        let mut total = 0;
+        drop(WithConsumer::new(5, Realtime(Closure(|x| total += x))));
+        assert_eq!(total, 5);
+    }
+}
@@ -0,0 +1,203 @@
+use crate::{Consumer, WithConsumer};
+use core::fmt;
+use core::mem;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::string::String;
+use std::sync::{Arc, RwLock};
+
+type Registered<T> = Arc<dyn Fn(T) + Send + Sync>;
+
+/// A program-wide table of consumers for `T`, registered under string keys at run time.
+///
+/// Host applications whose resource types are provided by plugins cannot name the consumer
+/// types at compile time. Instead, each plugin registers its consumers by name at startup, and
+/// guards built with [`ConsumerRegistry::guard`] look up their consumer by key when they are
+/// dropped. [`ConsumerRegistry::new`] is `const`, so a registry can be a `static`.
+pub struct ConsumerRegistry<T> {
+    consumers: RwLock<BTreeMap<String, Registered<T>>>,
+}
+
+impl<T> ConsumerRegistry<T> {
+    /// Builds an empty registry.
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            consumers: RwLock::new(BTreeMap::new()),
+        }
+    }
+
+    /// Registers `consumer` under `key`, returning whether it replaced an earlier consumer.
+    pub fn register(
+        &self,
+        key: impl Into<String>,
+        consumer: impl Fn(T) + Send + Sync + 'static,
+    ) -> bool {
+        self.consumers
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .insert(key.into(), Arc::new(consumer))
+            .is_some()
+    }
+
+    /// Removes the consumer registered under `key`, returning whether there was one.
+    pub fn unregister(&self, key: &str) -> bool {
+        self.consumers
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .remove(key)
+            .is_some()
+    }
+
+    /// Whether a consumer is registered under `key`.
+    pub fn contains(&self, key: &str) -> bool {
+        self.lookup(key).is_some()
+    }
+
+    fn lookup(&self, key: &str) -> Option<Registered<T>> {
+        self.consumers
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .get(key)
+            .cloned()
+    }
+
+    /// Consumes `value` with the consumer registered under `key`, or hands it back if there is
+    /// none. The consumer runs without holding any lock, so it may use the registry itself.
+    pub fn consume(&self, key: &str, value: T) -> Result<(), T> {
+        match self.lookup(key) {
+            Some(consumer) => {
+                consumer(value);
+                Ok(())
+            }
+            None => Err(value),
+        }
+    }
+
+    /// Guards `value` with the consumer registered under `key` at the time the guard is
+    /// dropped. If there is none by then, the value is dropped.
+    #[inline]
+    pub fn guard(&self, key: impl Into<Cow<'static, str>>, value: T) -> KeyedGuard<'_, T> {
+        WithConsumer::new(value, ByKey::new(self, key))
+    }
+}
+
+impl<T> Default for ConsumerRegistry<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> fmt::Debug for ConsumerRegistry<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let consumers = self
+            .consumers
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        f.debug_struct("ConsumerRegistry")
+            .field("keys", &consumers.keys().collect::<std::vec::Vec<_>>())
+            .finish()
+    }
+}
+
+/// What a [`ByKey`] consumer does with a value when no consumer is registered under its key.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum MissingConsumer {
+    /// Drop the value normally.
+    #[default]
+    Drop,
+    /// Leak the value with [`mem::forget`].
+    Leak,
+    /// Panic, naming the missing key.
+    Panic,
+}
+
+/// A [`Consumer<T>`] which resolves the consumer registered under a key in a
+/// [`ConsumerRegistry`] when it is used.
+#[derive(Debug)]
+pub struct ByKey<'r, T> {
+    registry: &'r ConsumerRegistry<T>,
+    key: Cow<'static, str>,
+    missing: MissingConsumer,
+}
+
+impl<'r, T> ByKey<'r, T> {
+    /// Builds a consumer which uses the consumer registered under `key` in `registry`, and
+    /// drops values if there is none.
+    #[inline]
+    pub fn new(registry: &'r ConsumerRegistry<T>, key: impl Into<Cow<'static, str>>) -> Self {
+        Self {
+            registry,
+            key: key.into(),
+            missing: MissingConsumer::default(),
+        }
+    }
+
+    /// Sets what happens to values when no consumer is registered under the key.
+    #[inline]
+    pub fn with_missing(self, missing: MissingConsumer) -> Self {
+        Self { missing, ..self }
+    }
+
+    /// The key this consumer resolves.
+    #[inline]
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+}
+
+impl<T> Clone for ByKey<'_, T> {
+    fn clone(&self) -> Self {
+        Self {
+            registry: self.registry,
+            key: self.key.clone(),
+            missing: self.missing,
+        }
+    }
+}
+
+impl<T> Consumer<T> for ByKey<'_, T> {
+    fn consume(self, other: T) {
+        if let Err(value) = self.registry.consume(&self.key, other) {
+            match self.missing {
+                MissingConsumer::Drop => drop(value),
+                MissingConsumer::Leak => mem::forget(value),
+                MissingConsumer::Panic => {
+                    panic!("no consumer is registered under `{}`", self.key)
+                }
+            }
+        }
+    }
+}
+
+/// A value which is consumed by the consumer registered under a key when dropped.
+/// See [`ConsumerRegistry::guard`].
+pub type KeyedGuard<'r, T> = WithConsumer<T, ByKey<'r, T>>;
+
+#[cfg(test)]
+mod tests {
+    use super::{ByKey, ConsumerRegistry, MissingConsumer};
+    use crate::WithConsumer;
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+    use std::sync::Mutex;
+    use std::vec::Vec;
+
+    static CLOSED: Mutex<Vec<(&str, u32)>> = Mutex::new(Vec::new());
+    static HANDLES: ConsumerRegistry<u32> = ConsumerRegistry::new();
+
+    #[test]
+    fn resolves_at_drop_time() {
+        let guard = HANDLES.guard("texture", 7);
+        assert!(!HANDLES.register("texture", |id| CLOSED.lock().unwrap().push(("texture", id))));
+        drop(guard);
+        drop(HANDLES.guard("mesh", 8));
+        assert_eq!(*CLOSED.lock().unwrap(), [("texture", 7)]);
+
+        assert!(HANDLES.unregister("texture"));
+        let strict = ByKey::new(&HANDLES, "texture").with_missing(MissingConsumer::Panic);
+        let result = catch_unwind(AssertUnwindSafe(|| drop(WithConsumer::new(9, strict))));
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,165 @@
+use crate::{Consumer, WithConsumer};
+use alloc::collections::VecDeque;
+use alloc::rc::Rc;
+use core::cell::RefCell;
+use core::fmt;
+use core::ops::{Deref, DerefMut};
+
+/// A queue of jobs which unfinished jobs can be pushed back onto, such as the injector of a
+/// work-stealing scheduler.
+pub trait JobQueue<J> {
+    /// Pushes `job` back onto the queue, to be picked up again.
+    fn requeue(&self, job: J);
+}
+
+impl<J, W: JobQueue<J> + ?Sized> JobQueue<J> for &W {
+    #[inline]
+    fn requeue(&self, job: J) {
+        (**self).requeue(job)
+    }
+}
+
+impl<J, W: JobQueue<J> + ?Sized> JobQueue<J> for Rc<W> {
+    #[inline]
+    fn requeue(&self, job: J) {
+        (**self).requeue(job)
+    }
+}
+
+impl<J> JobQueue<J> for RefCell<VecDeque<J>> {
+    #[inline]
+    fn requeue(&self, job: J) {
+        self.borrow_mut().push_back(job)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<J, W: JobQueue<J> + ?Sized> JobQueue<J> for std::sync::Arc<W> {
+    #[inline]
+    fn requeue(&self, job: J) {
+        (**self).requeue(job)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<J> JobQueue<J> for std::sync::Mutex<VecDeque<J>> {
+    #[inline]
+    fn requeue(&self, job: J) {
+        self.lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .push_back(job)
+    }
+}
+
+/// If the receiver is gone, nobody could process the job anyway, so it is dropped.
+#[cfg(feature = "std")]
+impl<J> JobQueue<J> for std::sync::mpsc::Sender<J> {
+    #[inline]
+    fn requeue(&self, job: J) {
+        let _ = self.send(job);
+    }
+}
+
+#[cfg(feature = "crossbeam-deque")]
+impl<J> JobQueue<J> for crossbeam_deque::Injector<J> {
+    #[inline]
+    fn requeue(&self, job: J) {
+        self.push(job)
+    }
+}
+
+#[cfg(feature = "crossbeam-deque")]
+impl<J> JobQueue<J> for crossbeam_deque::Worker<J> {
+    #[inline]
+    fn requeue(&self, job: J) {
+        self.push(job)
+    }
+}
+
+/// A [`Consumer<J>`] which pushes jobs back onto the [`JobQueue`] `W`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Requeue<W>(pub W);
+
+impl<J, W: JobQueue<J>> Consumer<J> for Requeue<W> {
+    #[inline]
+    fn consume(self, other: J) {
+        self.0.requeue(other)
+    }
+}
+
+/// A job taken from a [`JobQueue`], which is pushed back onto the queue when dropped, unless it
+/// was marked as done with [`RequeueGuard::complete`].
+///
+/// This gives at-least-once processing: if a worker returns early or panics while processing
+/// the job, the job is requeued for another attempt rather than lost.
+pub struct RequeueGuard<J, W: JobQueue<J>> {
+    inner: WithConsumer<J, Requeue<W>>,
+}
+
+impl<J, W: JobQueue<J>> RequeueGuard<J, W> {
+    /// Guards `job`, which will be pushed back onto `queue` unless completed.
+    #[inline]
+    pub const fn new(job: J, queue: W) -> Self {
+        Self {
+            inner: WithConsumer::new(job, Requeue(queue)),
+        }
+    }
+
+    /// The queue the job would be pushed back onto.
+    #[inline]
+    pub fn queue(x: &Self) -> &W {
+        &WithConsumer::as_refs(&x.inner).1 .0
+    }
+
+    /// Marks the job as done, returning it instead of requeueing it.
+    #[inline]
+    pub fn complete(x: Self) -> J {
+        WithConsumer::into_inner(x.inner)
+    }
+}
+
+impl<J: fmt::Debug, W: JobQueue<J>> fmt::Debug for RequeueGuard<J, W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("RequeueGuard").field(&*self.inner).finish()
+    }
+}
+
+impl<J, W: JobQueue<J>> Deref for RequeueGuard<J, W> {
+    type Target = J;
+
+    #[inline]
+    fn deref(&self) -> &J {
+        &self.inner
+    }
+}
+
+impl<J, W: JobQueue<J>> DerefMut for RequeueGuard<J, W> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut J {
+        &mut self.inner
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RequeueGuard;
+    use alloc::collections::VecDeque;
+    use core::cell::RefCell;
+
+    #[test]
+    fn requeues_unless_completed() {
+        let queue = RefCell::new(VecDeque::from([1, 2, 3]));
+        let mut done = alloc::vec::Vec::new();
+        for _ in 0..4 {
+            let job = queue.borrow_mut().pop_front().unwrap();
+            let job = RequeueGuard::new(job, &queue);
+            if *job % 2 == 0 {
+                // The worker gives up, so the job is requeued.
+                continue;
+            }
+            done.push(RequeueGuard::complete(job));
+        }
+        assert_eq!(done, [1, 3]);
+        assert_eq!(*queue.borrow(), [2]);
+    }
+}
@@ -0,0 +1,313 @@
+use crate::composite::consume_all;
+use crate::sync::{AtomicUsize, UnsafeCell};
+use crate::{Consume, Consumer, WithConsumer};
+use alloc::boxed::Box;
+use core::fmt;
+use core::mem::MaybeUninit;
+use core::sync::atomic::Ordering;
+
+struct Slot<T> {
+    /// Equal to the slot's position when it is ready to be written, and to its position plus one
+    /// when it holds a value ready to be read.
+    sequence: AtomicUsize,
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+/// A fixed-capacity, lock-free queue of values waiting to be consumed, for threads which must
+/// never block, allocate, or run expensive cleanup, such as audio callbacks and control loops.
+///
+/// All memory is allocated up front by [`RetireQueue::with_capacity`]. Realtime threads hand
+/// values over with [`RetireQueue::try_push`], or by dropping a [`RetireGuard`], and a collector
+/// thread consumes them with [`RetireQueue::drain`]. Both operations are lock-free, and any
+/// number of threads may push and drain concurrently. Values still queued when the queue is
+/// dropped are consumed then.
+///
+/// This is a bounded multi-producer, multi-consumer queue in the style of Dmitry Vyukov.
+pub struct RetireQueue<T: Consume> {
+    slots: Box<[Slot<T>]>,
+    enqueue_pos: AtomicUsize,
+    dequeue_pos: AtomicUsize,
+    overflows: AtomicUsize,
+}
+
+// SAFETY: values are moved into the queue on one thread and out of it on another, and every
+// slot is accessed by a single thread at a time, as arbitrated by its sequence number.
+unsafe impl<T: Consume + Send> Send for RetireQueue<T> {}
+// SAFETY: see above.
+unsafe impl<T: Consume + Send> Sync for RetireQueue<T> {}
+
+impl<T: Consume> RetireQueue<T> {
+    /// Allocates a queue which can hold at least `capacity` values. The capacity is rounded up
+    /// to a power of two, and is at least 2.
+    pub fn with_capacity(capacity: usize) -> Self {
+        let capacity = capacity.max(2).next_power_of_two();
+        Self {
+            slots: (0..capacity)
+                .map(|i| Slot {
+                    sequence: AtomicUsize::new(i),
+                    value: UnsafeCell::new(MaybeUninit::uninit()),
+                })
+                .collect(),
+            enqueue_pos: AtomicUsize::new(0),
+            dequeue_pos: AtomicUsize::new(0),
+            overflows: AtomicUsize::new(0),
+        }
+    }
+
+    /// The maximum number of values the queue can hold.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Queues `value` for consumption, handing it back if the queue is full. This never blocks
+    /// or allocates.
+    pub fn try_push(&self, value: T) -> Result<(), T> {
+        let mask = self.slots.len() - 1;
+        let mut pos = self.enqueue_pos.load(Ordering::Relaxed);
+        loop {
+            let slot = &self.slots[pos & mask];
+            let sequence = slot.sequence.load(Ordering::Acquire);
+            match (sequence.wrapping_sub(pos) as isize).cmp(&0) {
+                core::cmp::Ordering::Equal => {
+                    match self.enqueue_pos.compare_exchange_weak(
+                        pos,
+                        pos.wrapping_add(1),
+                        Ordering::Relaxed,
+                        Ordering::Relaxed,
+                    ) {
+                        Ok(_) => {
+                            // SAFETY: winning the exchange gives us exclusive access to the
+                            // slot until we publish it by bumping its sequence number.
+                            slot.value.with_mut(|slot| unsafe { (*slot).write(value) });
+                            slot.sequence.store(pos.wrapping_add(1), Ordering::Release);
+                            return Ok(());
+                        }
+                        Err(current) => pos = current,
+                    }
+                }
+                // The slot still holds a value from the previous lap: the queue is full.
+                core::cmp::Ordering::Less => return Err(value),
+                core::cmp::Ordering::Greater => pos = self.enqueue_pos.load(Ordering::Relaxed),
+            }
+        }
+    }
+
+    /// Removes the oldest queued value without consuming it, or returns `None` if the queue is
+    /// empty.
+    pub fn pop(&self) -> Option<T> {
+        let mask = self.slots.len() - 1;
+        let mut pos = self.dequeue_pos.load(Ordering::Relaxed);
+        loop {
+            let slot = &self.slots[pos & mask];
+            let sequence = slot.sequence.load(Ordering::Acquire);
+            match (sequence.wrapping_sub(pos.wrapping_add(1)) as isize).cmp(&0) {
+                core::cmp::Ordering::Equal => {
+                    match self.dequeue_pos.compare_exchange_weak(
+                        pos,
+                        pos.wrapping_add(1),
+                        Ordering::Relaxed,
+                        Ordering::Relaxed,
+                    ) {
+                        Ok(_) => {
+                            // SAFETY: the sequence number shows the slot was written, and winning
+                            // the exchange gives us exclusive access to it until we release it
+                            // for the next lap.
+                            let value = slot
+                                .value
+                                .with_mut(|slot| unsafe { (*slot).assume_init_read() });
+                            slot.sequence
+                                .store(pos.wrapping_add(mask + 1), Ordering::Release);
+                            return Some(value);
+                        }
+                        Err(current) => pos = current,
+                    }
+                }
+                // The slot has not been written yet: the queue is empty.
+                core::cmp::Ordering::Less => return None,
+                core::cmp::Ordering::Greater => pos = self.dequeue_pos.load(Ordering::Relaxed),
+            }
+        }
+    }
+
+    /// Consumes every value currently in the queue on the calling thread, returning how many
+    /// were consumed. Values pushed while draining may or may not be consumed by this call.
+    ///
+    /// If consuming a value panics, the rest of the queue is still drained while unwinding.
+    pub fn drain(&self) -> usize {
+        let mut count = 0;
+        consume_all(core::iter::from_fn(|| self.pop()).inspect(|_| count += 1));
+        count
+    }
+
+    /// Guards `value` so that it is pushed onto this queue when dropped.
+    #[inline]
+    pub fn guard(&self, value: T) -> RetireGuard<'_, T> {
+        WithConsumer::new(value, Retire(self))
+    }
+
+    /// The number of values which [`Retire`] had to consume inline because the queue was full.
+    /// A nonzero count means the queue is too small for its workload.
+    #[inline]
+    pub fn overflows(&self) -> usize {
+        self.overflows.load(Ordering::Relaxed)
+    }
+}
+
+impl<T: Consume> fmt::Debug for RetireQueue<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RetireQueue")
+            .field("capacity", &self.capacity())
+            .field("overflows", &self.overflows())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T: Consume> Drop for RetireQueue<T> {
+    fn drop(&mut self) {
+        consume_all(core::iter::from_fn(|| self.pop()))
+    }
+}
+
+/// A [`Consumer`] which pushes values onto a [`RetireQueue`] instead of consuming them.
+///
+/// If the queue is full, the value is consumed inline after all, and the overflow is counted
+/// in [`RetireQueue::overflows`]. Size the queue for the worst case, and check the count in
+/// testing.
+#[derive(Debug)]
+pub struct Retire<'q, T: Consume>(pub &'q RetireQueue<T>);
+
+impl<T: Consume> Clone for Retire<'_, T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: Consume> Copy for Retire<'_, T> {}
+
+impl<T: Consume> Consumer<T> for Retire<'_, T> {
+    #[inline]
+    fn consume(self, other: T) {
+        if let Err(value) = self.0.try_push(other) {
+            self.0.overflows.fetch_add(1, Ordering::Relaxed);
+            value.consume()
+        }
+    }
+}
+
+/// A value which is pushed onto a [`RetireQueue`] when dropped.
+pub type RetireGuard<'q, T> = WithConsumer<T, Retire<'q, T>>;
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use super::RetireQueue;
+    use crate::Consume;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    struct Counted<'a>(usize, &'a AtomicUsize);
+
+    impl Consume for Counted<'_> {
+        fn consume(self) {
+            self.1.fetch_add(self.0, Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn retires_in_order_and_overflows_inline() {
+        let total = AtomicUsize::new(0);
+        let queue = RetireQueue::with_capacity(2);
+        drop(queue.guard(Counted(1, &total)));
+        drop(queue.guard(Counted(2, &total)));
+        assert_eq!(total.load(Ordering::Relaxed), 0);
+        drop(queue.guard(Counted(4, &total)));
+        assert_eq!((total.load(Ordering::Relaxed), queue.overflows()), (4, 1));
+        let first = queue.pop().unwrap();
+        assert_eq!(first.0, 1);
+        assert_eq!(queue.drain(), 1);
+        assert_eq!(total.load(Ordering::Relaxed), 6);
+        first.consume();
+        assert_eq!(total.load(Ordering::Relaxed), 7);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn concurrent_producers() {
+        let total = AtomicUsize::new(0);
+        let finished = AtomicUsize::new(0);
+        let queue = RetireQueue::with_capacity(64);
+        std::thread::scope(|s| {
+            for thread in 0..4 {
+                let (queue, total, finished) = (&queue, &total, &finished);
+                s.spawn(move || {
+                    for i in 0..1000 {
+                        drop(queue.guard(Counted(thread * 1000 + i, total)));
+                    }
+                    finished.fetch_add(1, Ordering::Release);
+                });
+            }
+            while finished.load(Ordering::Acquire) < 4 {
+                if queue.drain() == 0 {
+                    std::thread::yield_now();
+                }
+            }
+        });
+        queue.drain();
+        assert_eq!(total.load(Ordering::Relaxed), (0..4000).sum::<usize>());
+    }
+
+    #[test]
+    fn panicking_consume_still_drains_on_drop() {
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+
+        struct Checked<'a>(usize, &'a AtomicUsize);
+
+        impl Consume for Checked<'_> {
+            fn consume(self) {
+                assert_ne!(self.0, 0, "retired a bad value");
+                self.1.fetch_add(self.0, Ordering::Relaxed);
+            }
+        }
+
+        let total = AtomicUsize::new(0);
+        let queue = RetireQueue::with_capacity(4);
+        for value in [0, 1, 2] {
+            drop(queue.guard(Checked(value, &total)));
+        }
+        assert!(catch_unwind(AssertUnwindSafe(|| drop(queue))).is_err());
+        assert_eq!(total.load(Ordering::Relaxed), 3);
+    }
+}
+
+#[cfg(all(test, loom))]
+mod loom_tests {
+    use super::RetireQueue;
+    use crate::Closure;
+    use loom::sync::atomic::{AtomicUsize, Ordering};
+    use loom::sync::Arc;
+    use loom::thread;
+
+    #[test]
+    fn concurrent_push_and_drain() {
+        loom::model(|| {
+            let total = Arc::new(AtomicUsize::new(0));
+            let queue = Arc::new(RetireQueue::with_capacity(2));
+            let producers: alloc::vec::Vec<_> = (1..=2)
+                .map(|n| {
+                    let (queue, total) = (queue.clone(), total.clone());
+                    thread::spawn(move || {
+                        drop(queue.guard(Closure(move || {
+                            total.fetch_add(n, Ordering::Relaxed);
+                        })))
+                    })
+                })
+                .collect();
+            queue.drain();
+            for producer in producers {
+                producer.join().unwrap();
+            }
+            queue.drain();
+            assert_eq!(total.load(Ordering::Relaxed), 3);
+        });
+    }
+}
@@ -0,0 +1,180 @@
+use crate::{Clock, Consume, SystemClock};
+use core::fmt;
+use std::sync::{Arc, Mutex, MutexGuard, Weak};
+use std::thread::{self, JoinHandle};
+use std::time::Instant;
+
+type Lease<T> = Mutex<Option<T>>;
+
+fn lock<T>(lease: &Lease<T>) -> MutexGuard<'_, Option<T>> {
+    lease
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Consumes the value in `lease`, if it is still there. The lock is released first, so that
+/// a slow or panicking [`Consume::consume`] never blocks or poisons the lease.
+fn revoke<T: Consume>(lease: &Lease<T>) -> bool {
+    let value = lock(lease).take();
+    value.map(Consume::consume).is_some()
+}
+
+/// The error returned when accessing a [`RevocableGuard`] whose value has been revoked.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Revoked;
+
+impl fmt::Display for Revoked {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("the guarded value has been revoked")
+    }
+}
+
+impl std::error::Error for Revoked {}
+
+/// A guard whose value can be consumed by a [`Revoker`] while the guard is still alive.
+///
+/// Lease-style resources, such as tokens, licenses, and sandbox handles, need cleanup which the
+/// holder cannot postpone. Once revoked, the value has already been consumed, and every access
+/// through the guard returns [`Revoked`]. Otherwise, the value is consumed when the guard is
+/// dropped, like a [`ConsumeOnDrop`](crate::ConsumeOnDrop).
+///
+/// Access goes through [`RevocableGuard::with`], which holds a lock for the duration of the
+/// call, so a value is never revoked while it is in use.
+pub struct RevocableGuard<T: Consume> {
+    lease: Arc<Lease<T>>,
+}
+
+impl<T: Consume> RevocableGuard<T> {
+    /// Wraps `value` in a [`RevocableGuard`], and returns a [`Revoker`] for it.
+    pub fn new(value: T) -> (Self, Revoker<T>) {
+        let guard = Self {
+            lease: Arc::new(Mutex::new(Some(value))),
+        };
+        let revoker = Self::revoker(&guard);
+        (guard, revoker)
+    }
+
+    /// Returns another [`Revoker`] for the value in `x`.
+    #[inline]
+    pub fn revoker(x: &Self) -> Revoker<T> {
+        Revoker {
+            lease: Arc::downgrade(&x.lease),
+        }
+    }
+
+    /// Calls `f` with the value, unless it has been revoked.
+    pub fn with<R>(x: &Self, f: impl FnOnce(&mut T) -> R) -> Result<R, Revoked> {
+        lock(&x.lease).as_mut().map(f).ok_or(Revoked)
+    }
+
+    /// Whether the value in `x` has been revoked.
+    pub fn is_revoked(x: &Self) -> bool {
+        lock(&x.lease).is_none()
+    }
+
+    /// Extracts the value without consuming it, unless it has been revoked.
+    pub fn into_inner(x: Self) -> Result<T, Revoked> {
+        lock(&x.lease).take().ok_or(Revoked)
+    }
+}
+
+impl<T: Consume> Drop for RevocableGuard<T> {
+    fn drop(&mut self) {
+        revoke(&self.lease);
+    }
+}
+
+impl<T: Consume + fmt::Debug> fmt::Debug for RevocableGuard<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &*lock(&self.lease) {
+            Some(value) => f.debug_tuple("RevocableGuard").field(value).finish(),
+            None => f.debug_tuple("RevocableGuard").field(&Revoked).finish(),
+        }
+    }
+}
+
+/// A handle which consumes the value in a [`RevocableGuard`] early.
+///
+/// A revoker does not keep the value alive. Once the guard is dropped or its value revoked,
+/// revoking again does nothing.
+pub struct Revoker<T: Consume> {
+    lease: Weak<Lease<T>>,
+}
+
+impl<T: Consume> Revoker<T> {
+    /// Consumes the guarded value now, waiting for any access in progress to finish. Returns
+    /// whether this call consumed it.
+    pub fn revoke(&self) -> bool {
+        self.lease.upgrade().is_some_and(|lease| revoke(&lease))
+    }
+
+    /// Spawns a thread which revokes the value at `deadline`, or right away if the deadline
+    /// has passed. The thread's result is that of [`Revoker::revoke`].
+    #[inline]
+    pub fn revoke_at(self, deadline: Instant) -> JoinHandle<bool>
+    where
+        T: Send + 'static,
+    {
+        self.revoke_at_with(deadline, SystemClock)
+    }
+
+    /// Like [`Revoker::revoke_at`], but the deadline is measured by `clock`, e.g. a
+    /// [`MockClock`](crate::MockClock) in tests.
+    pub fn revoke_at_with(self, deadline: Instant, clock: impl Clock + 'static) -> JoinHandle<bool>
+    where
+        T: Send + 'static,
+    {
+        thread::spawn(move || {
+            clock.sleep_until(deadline);
+            self.revoke()
+        })
+    }
+}
+
+impl<T: Consume> Clone for Revoker<T> {
+    fn clone(&self) -> Self {
+        Self {
+            lease: self.lease.clone(),
+        }
+    }
+}
+
+impl<T: Consume> fmt::Debug for Revoker<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Revoker").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RevocableGuard, Revoked};
+    use crate::Closure;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Instant;
+
+    static REVOKED: AtomicUsize = AtomicUsize::new(0);
+
+    fn lease() -> Closure<impl FnOnce() + Send> {
+        Closure(|| {
+            REVOKED.fetch_add(1, Ordering::Relaxed);
+        })
+    }
+
+    #[test]
+    fn revoke_while_held() {
+        let (guard, revoker) = RevocableGuard::new(lease());
+        assert_eq!(RevocableGuard::with(&guard, |_| 1), Ok(1));
+        assert!(revoker.revoke());
+        assert_eq!(REVOKED.load(Ordering::Relaxed), 1);
+        assert_eq!(RevocableGuard::with(&guard, |_| 1), Err(Revoked));
+        assert!(!revoker.revoke());
+        drop(guard);
+        assert_eq!(REVOKED.load(Ordering::Relaxed), 1);
+
+        let (guard, revoker) = RevocableGuard::new(lease());
+        assert!(revoker.revoke_at(Instant::now()).join().unwrap());
+        assert!(RevocableGuard::is_revoked(&guard));
+        assert!(RevocableGuard::into_inner(guard).is_err());
+        assert_eq!(REVOKED.load(Ordering::Relaxed), 2);
+    }
+}
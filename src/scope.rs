@@ -0,0 +1,78 @@
+#[cfg(feature = "alloc")]
+use crate::DynGuard;
+
+/// Runs the given statements when the enclosing scope exits, whether normally, by an early
+/// return, or by unwinding.
+///
+/// `defer! { ... }` guards a closure running the statements in a
+/// [`ConsumeOnDrop`](crate::ConsumeOnDrop) which lives until the end of the scope, so the
+/// statements run after any values declared later in the scope have been dropped. Several
+/// deferred blocks run in reverse order. The closure borrows whatever it uses until then, so to
+/// keep using a value in the meantime, share it through a [`Cell`](core::cell::Cell) or a
+/// [`RefCell`](core::cell::RefCell). The guard is hidden in the scope, so the closure is not
+/// boxed. With the `alloc` feature, `defer()` builds a `DynGuard` instead,
+/// which can be stored or cancelled.
+///
+/// ```
+/// use consume_on_drop::defer;
+/// use std::cell::RefCell;
+///
+/// let log = RefCell::new(Vec::new());
+/// {
+///     defer! { log.borrow_mut().push("second"); }
+///     defer! { log.borrow_mut().push("first"); }
+/// }
+/// assert_eq!(*log.borrow(), ["first", "second"]);
+/// ```
+#[macro_export]
+macro_rules! defer {
+    ($($body:tt)*) => {
+        let _deferred = $crate::ConsumeOnDrop::new($crate::Closure(|| {
+            $($body)*
+        }));
+    };
+}
+
+/// Defers `f` until the returned [`DynGuard`] is dropped, unless it is cancelled with
+/// [`DynGuard::cancel`]. For scope-exit code, see the [`defer!`] macro.
+#[cfg(feature = "alloc")]
+#[inline]
+#[must_use = "the closure runs as soon as the guard is dropped"]
+pub fn defer<'a>(f: impl FnOnce() + 'a) -> DynGuard<'a> {
+    DynGuard::new(f)
+}
+
+#[cfg(test)]
+mod tests {
+    use core::cell::Cell;
+
+    #[test]
+    fn deferred_at_scope_exit() {
+        let runs = Cell::new(0);
+        {
+            defer! {
+                assert_eq!(runs.get(), 1);
+                runs.set(10);
+            }
+            defer!(runs.set(1));
+        }
+        assert_eq!(runs.get(), 10);
+
+        #[cfg(feature = "alloc")]
+        {
+            use super::defer;
+            use crate::DynGuard;
+
+            struct Holder<'a> {
+                _cleanup: DynGuard<'a>,
+            }
+
+            drop(Holder {
+                _cleanup: defer(|| runs.set(runs.get() + 1)),
+            });
+            DynGuard::cancel(defer(|| runs.set(0)));
+            DynGuard::run(defer(|| runs.set(runs.get() + 1)));
+            assert_eq!(runs.get(), 12);
+        }
+    }
+}
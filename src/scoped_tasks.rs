@@ -0,0 +1,428 @@
+use crate::{Consume, ConsumeOnDrop};
+use core::any::Any;
+use core::fmt;
+use std::boxed::Box;
+use std::panic;
+use std::thread::{self, JoinHandle};
+use std::vec::Vec;
+
+#[cfg(feature = "tokio")]
+pub use self::tokio_tasks::*;
+
+/// The panics of one or more tasks in a scope, in the order the tasks were registered.
+pub struct TaskPanics {
+    payloads: Vec<Box<dyn Any + Send>>,
+}
+
+impl TaskPanics {
+    /// The number of tasks which panicked.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.payloads.len()
+    }
+
+    /// Whether no task panicked. This is never the case for a [`TaskPanics`] produced by this
+    /// crate.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.payloads.is_empty()
+    }
+
+    /// The panic payloads, e.g. for passing one to [`std::panic::resume_unwind`].
+    #[inline]
+    pub fn into_payloads(self) -> Vec<Box<dyn Any + Send>> {
+        self.payloads
+    }
+
+    /// The messages of the panics whose payload is a string, as produced by `panic!`.
+    pub fn messages(&self) -> impl Iterator<Item = &str> {
+        self.payloads.iter().filter_map(|payload| {
+            payload
+                .downcast_ref::<&str>()
+                .copied()
+                .or_else(|| payload.downcast_ref::<std::string::String>().map(|s| &**s))
+        })
+    }
+}
+
+impl fmt::Debug for TaskPanics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TaskPanics")
+            .field("messages", &self.messages().collect::<Vec<_>>())
+            .finish_non_exhaustive()
+    }
+}
+
+impl fmt::Display for TaskPanics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} scoped task(s) panicked", self.len())?;
+        for message in self.messages() {
+            write!(f, "; {}", message)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for TaskPanics {}
+
+/// Collects the results of tasks, and their panics if any.
+fn collect<T>(
+    results: impl IntoIterator<Item = Result<T, Box<dyn Any + Send>>>,
+) -> Result<Vec<T>, TaskPanics> {
+    let mut values = Vec::new();
+    let mut payloads = Vec::new();
+    for result in results {
+        match result {
+            Ok(value) => values.push(value),
+            Err(payload) => payloads.push(payload),
+        }
+    }
+    if payloads.is_empty() {
+        Ok(values)
+    } else {
+        Err(TaskPanics { payloads })
+    }
+}
+
+/// Reports the panics of tasks joined while dropping a scope. Panicking while the thread is
+/// already unwinding would abort the process, so in that case the panics are discarded.
+fn propagate(panics: TaskPanics) {
+    if !thread::panicking() {
+        panic::panic_any(panics)
+    }
+}
+
+struct Threads<T> {
+    handles: Vec<JoinHandle<T>>,
+}
+
+impl<T> Threads<T> {
+    fn join(self) -> Result<Vec<T>, TaskPanics> {
+        collect(self.handles.into_iter().map(JoinHandle::join))
+    }
+}
+
+impl<T> Consume for Threads<T> {
+    #[inline]
+    fn consume(self) {
+        if let Err(panics) = self.join() {
+            propagate(panics)
+        }
+    }
+}
+
+/// A scope which owns spawned threads, and joins all of them when dropped.
+///
+/// Unlike [`std::thread::scope`], the scope is a value: it can be stored in a struct or
+/// returned from a function, and tasks can be registered from anywhere it is reachable. The
+/// threads are `'static`, so they may not borrow from the stack.
+///
+/// [`ScopedTasks::join`] waits for every thread and returns their results, or the aggregated
+/// [`TaskPanics`] if any of them panicked. Dropping the scope also joins every thread, and then
+/// panics with the [`TaskPanics`] as payload, unless the thread is already unwinding.
+pub struct ScopedTasks<T = ()> {
+    inner: ConsumeOnDrop<Threads<T>>,
+}
+
+impl<T> ScopedTasks<T> {
+    /// Builds an empty scope.
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            inner: ConsumeOnDrop::new(Threads {
+                handles: Vec::new(),
+            }),
+        }
+    }
+
+    /// Registers a thread spawned elsewhere, which will be joined with the rest of the scope.
+    #[inline]
+    pub fn register(&mut self, handle: JoinHandle<T>) {
+        self.inner.handles.push(handle)
+    }
+
+    /// The number of threads registered with the scope.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.inner.handles.len()
+    }
+
+    /// Whether no thread has been registered with the scope.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.inner.handles.is_empty()
+    }
+
+    /// Waits for every thread, returning their results in registration order, or the panics of
+    /// those which panicked.
+    #[inline]
+    pub fn join(self) -> Result<Vec<T>, TaskPanics> {
+        ConsumeOnDrop::into_inner(self.inner).join()
+    }
+}
+
+impl<T: Send + 'static> ScopedTasks<T> {
+    /// Spawns a thread running `f`, which will be joined with the rest of the scope.
+    pub fn spawn(&mut self, f: impl FnOnce() -> T + Send + 'static) {
+        self.register(thread::spawn(f))
+    }
+}
+
+impl<T> Default for ScopedTasks<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> fmt::Debug for ScopedTasks<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ScopedTasks")
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+#[cfg(feature = "tokio")]
+mod tokio_tasks {
+    use super::{collect, propagate, TaskPanics};
+    use crate::{Consume, ConsumeOnDrop};
+    use core::any::Any;
+    use core::fmt;
+    use core::future::Future;
+    use std::boxed::Box;
+    use std::vec::Vec;
+    use tokio::task::{JoinError, JoinHandle};
+
+    fn payload<T>(result: Result<T, JoinError>) -> Result<Option<T>, Box<dyn Any + Send>> {
+        match result {
+            Ok(value) => Ok(Some(value)),
+            Err(error) if error.is_panic() => Err(error.into_panic()),
+            // Cancelled tasks have no result and did not fail.
+            Err(_) => Ok(None),
+        }
+    }
+
+    struct Tasks<T> {
+        handles: Vec<JoinHandle<T>>,
+    }
+
+    impl<T> Tasks<T> {
+        /// Awaits every task. The handles stay in the guard until all tasks have finished, so
+        /// that the remaining tasks are cancelled if the returned future is dropped.
+        async fn join(tasks: &mut ConsumeOnDrop<Self>) -> Result<Vec<Option<T>>, TaskPanics> {
+            let mut results = Vec::with_capacity(tasks.handles.len());
+            for handle in tasks.handles.iter_mut() {
+                results.push(payload(handle.await));
+            }
+            tasks.handles.clear();
+            collect(results)
+        }
+    }
+
+    impl<T> Consume for Tasks<T> {
+        #[inline]
+        fn consume(self) {
+            // Dropping cannot wait, so the tasks are only cancelled.
+            for handle in self.handles {
+                handle.abort()
+            }
+        }
+    }
+
+    /// A scope which owns spawned `tokio` tasks, and cancels all of them when dropped.
+    ///
+    /// This is the asynchronous counterpart of [`ScopedTasks`](super::ScopedTasks).
+    /// [`ScopedTokioTasks::join`] awaits every task, and [`ScopedTokioTasks::cancel`] cancels
+    /// them and then awaits them, so that no task outlives the call. Since dropping cannot
+    /// await, a scope which is dropped instead only requests cancellation, which takes effect
+    /// the next time each task yields. The same goes for the tasks not yet awaited when a
+    /// [`ScopedTokioTasks::join`] or [`ScopedTokioTasks::cancel`] future is dropped.
+    pub struct ScopedTokioTasks<T = ()> {
+        inner: ConsumeOnDrop<Tasks<T>>,
+    }
+
+    impl<T> ScopedTokioTasks<T> {
+        /// Builds an empty scope.
+        #[inline]
+        pub const fn new() -> Self {
+            Self {
+                inner: ConsumeOnDrop::new(Tasks {
+                    handles: Vec::new(),
+                }),
+            }
+        }
+
+        /// Registers a task spawned elsewhere, which will be joined or cancelled with the rest
+        /// of the scope.
+        #[inline]
+        pub fn register(&mut self, handle: JoinHandle<T>) {
+            self.inner.handles.push(handle)
+        }
+
+        /// The number of tasks registered with the scope.
+        #[inline]
+        pub fn len(&self) -> usize {
+            self.inner.handles.len()
+        }
+
+        /// Whether no task has been registered with the scope.
+        #[inline]
+        pub fn is_empty(&self) -> bool {
+            self.inner.handles.is_empty()
+        }
+
+        /// Awaits every task, returning their results in registration order, or the panics of
+        /// those which panicked. The result of a task cancelled from elsewhere is `None`, so
+        /// that every result stays at the index of its task.
+        pub async fn join(mut self) -> Result<Vec<Option<T>>, TaskPanics> {
+            Tasks::join(&mut self.inner).await
+        }
+
+        /// Cancels every task, and awaits them so that none is still running when this
+        /// returns. Panics of tasks which finished before being cancelled are still reported.
+        pub async fn cancel(mut self) -> Result<(), TaskPanics> {
+            for handle in &self.inner.handles {
+                handle.abort()
+            }
+            Tasks::join(&mut self.inner).await.map(drop)
+        }
+
+        /// Cancels every task, then awaits them, and panics with the [`TaskPanics`] if any
+        /// task panicked, like dropping a [`ScopedTasks`](super::ScopedTasks) does.
+        pub async fn close(self) {
+            if let Err(panics) = self.cancel().await {
+                propagate(panics)
+            }
+        }
+    }
+
+    impl<T: Send + 'static> ScopedTokioTasks<T> {
+        /// Spawns `future` on the current `tokio` runtime, as a task belonging to the scope.
+        ///
+        /// Panics if called outside of a `tokio` runtime.
+        pub fn spawn(&mut self, future: impl Future<Output = T> + Send + 'static) {
+            self.register(tokio::spawn(future))
+        }
+    }
+
+    impl<T> Default for ScopedTokioTasks<T> {
+        #[inline]
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl<T> fmt::Debug for ScopedTokioTasks<T> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("ScopedTokioTasks")
+                .field("len", &self.len())
+                .finish()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ScopedTasks;
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn joins_on_drop_and_aggregates_panics() {
+        let done = Arc::new(AtomicUsize::new(0));
+        let mut scope = ScopedTasks::new();
+        for _ in 0..3 {
+            let done = done.clone();
+            scope.spawn(move || {
+                done.fetch_add(1, Ordering::Relaxed);
+            });
+        }
+        drop(scope);
+        assert_eq!(done.load(Ordering::Relaxed), 3);
+
+        let mut scope = ScopedTasks::new();
+        scope.spawn(|| 1);
+        scope.spawn(|| panic!("worker failed"));
+        let panics = scope.join().unwrap_err();
+        assert_eq!(
+            panics.messages().collect::<std::vec::Vec<_>>(),
+            ["worker failed"]
+        );
+
+        let mut scope = ScopedTasks::new();
+        scope.spawn(|| panic!("worker failed"));
+        assert!(catch_unwind(AssertUnwindSafe(|| drop(scope))).is_err());
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn cancels_tokio_tasks() {
+        use super::ScopedTokioTasks;
+        use core::future::pending;
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let mut scope = ScopedTokioTasks::new();
+            scope.spawn(async { 1 });
+            scope.spawn(async { 2 });
+            let cancelled = tokio::spawn(pending());
+            cancelled.abort();
+            scope.register(cancelled);
+            scope.spawn(async { 3 });
+            assert_eq!(
+                scope.join().await.unwrap(),
+                [Some(1), Some(2), None, Some(3)]
+            );
+
+            let mut scope = ScopedTokioTasks::new();
+            scope.spawn(pending::<()>());
+            scope.cancel().await.unwrap();
+        });
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn cancelled_join_cancels_tasks() {
+        use super::ScopedTokioTasks;
+        use core::future::{pending, poll_fn, Future};
+        use core::pin::pin;
+        use core::task::Poll;
+
+        struct SetOnDrop(Arc<AtomicUsize>);
+
+        impl Drop for SetOnDrop {
+            fn drop(&mut self) {
+                self.0.store(1, Ordering::Relaxed)
+            }
+        }
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let dropped = Arc::new(AtomicUsize::new(0));
+            let mut scope = ScopedTokioTasks::new();
+            let flag = SetOnDrop(dropped.clone());
+            scope.spawn(async move {
+                let _flag = flag;
+                pending::<()>().await
+            });
+            {
+                let mut join = pin!(scope.join());
+                poll_fn(|cx| {
+                    assert!(join.as_mut().poll(cx).is_pending());
+                    Poll::Ready(())
+                })
+                .await;
+            }
+            for _ in 0..10 {
+                tokio::task::yield_now().await;
+            }
+            assert_eq!(dropped.load(Ordering::Relaxed), 1);
+        });
+    }
+}
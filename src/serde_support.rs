@@ -0,0 +1,101 @@
+use crate::{Consumer, WithConsumer};
+use core::marker::PhantomData;
+use serde::de::{Deserialize, DeserializeSeed, Deserializer};
+use serde::ser::{Serialize, Serializer};
+
+/// A [`DeserializeSeed`] which carries a [`Consumer`] and deserializes directly into a
+/// [`WithConsumer`], so the deserialized value is guarded from the moment it exists.
+///
+/// The inner seed `S` produces the value. Use [`WithConsumerSeed::new`] for types implementing
+/// [`Deserialize`](serde::Deserialize), or [`WithConsumerSeed::with_seed`] to wrap another seed.
+#[derive(Debug, Clone, Copy)]
+pub struct WithConsumerSeed<S, Q> {
+    seed: S,
+    consumer: Q,
+}
+
+impl<T, Q: Consumer<T>> WithConsumerSeed<PhantomData<T>, Q> {
+    /// Builds a seed which deserializes a `T` and pairs it with `consumer`.
+    #[inline]
+    pub const fn new(consumer: Q) -> Self {
+        Self::with_seed(PhantomData, consumer)
+    }
+}
+
+impl<S, Q> WithConsumerSeed<S, Q> {
+    /// Builds a seed which deserializes a value with `seed` and pairs it with `consumer`.
+    #[inline]
+    pub const fn with_seed(seed: S, consumer: Q) -> Self {
+        Self { seed, consumer }
+    }
+}
+
+impl<'de, S, Q> DeserializeSeed<'de> for WithConsumerSeed<S, Q>
+where
+    S: DeserializeSeed<'de>,
+    Q: Consumer<S::Value>,
+{
+    type Value = WithConsumer<S::Value, Q>;
+
+    #[inline]
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        let value = self.seed.deserialize(deserializer)?;
+        Ok(WithConsumer::new(value, self.consumer))
+    }
+}
+
+/// Serializes only the value. The consumer is not part of the serialized form.
+impl<T: Serialize, Q: Consumer<T>> Serialize for WithConsumer<T, Q> {
+    #[inline]
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        (**self).serialize(serializer)
+    }
+}
+
+/// Deserializes the value and pairs it with `Q::default()`. To supply a consumer which has no
+/// default, such as one holding a handle to its runtime, use [`WithConsumerSeed`].
+impl<'de, T: Deserialize<'de>, Q: Consumer<T> + Default> Deserialize<'de> for WithConsumer<T, Q> {
+    #[inline]
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        WithConsumerSeed::new(Q::default()).deserialize(deserializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WithConsumerSeed;
+    use crate::{Closure, Consumer, WithConsumer};
+    use serde::de::DeserializeSeed;
+
+    #[test]
+    fn deserializes_guarded() {
+        let mut released = None;
+        let mut deserializer = serde_json::Deserializer::from_str("42");
+        let guard = WithConsumerSeed::new(Closure(|handle: u32| released = Some(handle)))
+            .deserialize(&mut deserializer)
+            .unwrap();
+        assert_eq!(*guard, 42);
+        drop(guard);
+        assert_eq!(released, Some(42));
+
+        let mut deserializer = serde_json::Deserializer::from_str("\"oops\"");
+        let error = WithConsumerSeed::new(Closure(|_: u32| ())).deserialize(&mut deserializer);
+        assert!(error.map(WithConsumer::into_inner).is_err());
+    }
+
+    #[derive(Default)]
+    struct Discard;
+
+    impl<T> Consumer<T> for Discard {
+        fn consume(self, _other: T) {}
+    }
+
+    #[test]
+    fn round_trips_value_only() {
+        let session = WithConsumer::new([1, 2, 3], Discard);
+        let json = serde_json::to_string(&session).unwrap();
+        assert_eq!(json, "[1,2,3]");
+        let restored: WithConsumer<[u8; 3], Discard> = serde_json::from_str(&json).unwrap();
+        assert_eq!(*restored, [1, 2, 3]);
+    }
+}
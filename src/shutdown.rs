@@ -0,0 +1,241 @@
+use crate::TryConsume;
+use core::fmt;
+use std::collections::VecDeque;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+use std::vec::Vec;
+
+/// How consuming one entry of a batch ended, as sent back by the workers.
+enum Outcome<E> {
+    Consumed,
+    Failed(E),
+    Panicked,
+}
+
+struct Batch<T> {
+    queue: Mutex<VecDeque<(usize, T)>>,
+    expired: AtomicBool,
+}
+
+impl<T> Batch<T> {
+    fn next(&self) -> Option<(usize, T)> {
+        if self.expired.load(Ordering::Relaxed) {
+            return None;
+        }
+        self.queue
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .pop_front()
+    }
+}
+
+/// Consumes `items` concurrently on up to `workers` threads, giving up at `deadline`.
+///
+/// Sequential teardown of many slow resources, such as network sessions, takes as long as all
+/// of them combined. This spreads them over a bounded number of threads and returns by
+/// `deadline` at the latest, with a [`ShutdownReport`] of which entries were consumed, which
+/// failed or panicked, and which timed out. Entries are identified by their index in `items`,
+/// and are started in order.
+///
+/// Threads cannot be interrupted, so entries still being consumed at the deadline keep running
+/// in the background, and are reported as [`ShutdownReport::timed_out`]. Entries which were not
+/// started by then are handed back in [`ShutdownReport::unstarted`].
+pub fn shutdown_concurrently<T>(
+    items: impl IntoIterator<Item = T>,
+    workers: usize,
+    deadline: Instant,
+) -> ShutdownReport<T>
+where
+    T: TryConsume + Send + 'static,
+    T::Error: Send + 'static,
+{
+    let queue: VecDeque<_> = items.into_iter().enumerate().collect();
+    let total = queue.len();
+    let batch = Arc::new(Batch {
+        queue: Mutex::new(queue),
+        expired: AtomicBool::new(false),
+    });
+    let (sender, receiver) = mpsc::channel();
+    for _ in 0..workers.max(1).min(total) {
+        let batch = batch.clone();
+        let sender = sender.clone();
+        thread::spawn(move || {
+            while let Some((index, item)) = batch.next() {
+                let outcome = match catch_unwind(AssertUnwindSafe(|| item.try_consume())) {
+                    Ok(Ok(())) => Outcome::Consumed,
+                    Ok(Err(error)) => Outcome::Failed(error),
+                    Err(_) => Outcome::Panicked,
+                };
+                if sender.send((index, outcome)).is_err() {
+                    // The deadline passed, and nobody is listening any more.
+                    return;
+                }
+            }
+        });
+    }
+    drop(sender);
+
+    let mut report = ShutdownReport {
+        consumed: Vec::new(),
+        failed: Vec::new(),
+        panicked: Vec::new(),
+        timed_out: Vec::new(),
+        unstarted: Vec::new(),
+    };
+    let mut finished = std::vec![false; total];
+    loop {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        match receiver.recv_timeout(timeout) {
+            Ok((index, outcome)) => {
+                finished[index] = true;
+                report.record(index, outcome);
+            }
+            // Every worker has exited, so every entry has been reported.
+            Err(RecvTimeoutError::Disconnected) => break,
+            Err(RecvTimeoutError::Timeout) => {
+                batch.expired.store(true, Ordering::Relaxed);
+                report.unstarted = batch
+                    .queue
+                    .lock()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner())
+                    .drain(..)
+                    .collect();
+                for &(index, _) in &report.unstarted {
+                    finished[index] = true;
+                }
+                // Collect what finished in the meantime, without waiting.
+                while let Ok((index, outcome)) = receiver.try_recv() {
+                    finished[index] = true;
+                    report.record(index, outcome);
+                }
+                report.timed_out = (0..total).filter(|&index| !finished[index]).collect();
+                break;
+            }
+        }
+    }
+    report.consumed.sort_unstable();
+    report.failed.sort_unstable_by_key(|&(index, _)| index);
+    report.panicked.sort_unstable();
+    report
+}
+
+/// The outcome of [`shutdown_concurrently`]. Every entry appears in exactly one of the lists,
+/// each of which is sorted by index.
+pub struct ShutdownReport<T: TryConsume> {
+    consumed: Vec<usize>,
+    failed: Vec<(usize, T::Error)>,
+    panicked: Vec<usize>,
+    timed_out: Vec<usize>,
+    unstarted: Vec<(usize, T)>,
+}
+
+impl<T: TryConsume> ShutdownReport<T> {
+    fn record(&mut self, index: usize, outcome: Outcome<T::Error>) {
+        match outcome {
+            Outcome::Consumed => self.consumed.push(index),
+            Outcome::Failed(error) => self.failed.push((index, error)),
+            Outcome::Panicked => self.panicked.push(index),
+        }
+    }
+
+    /// The entries which were consumed successfully.
+    #[inline]
+    pub fn consumed(&self) -> &[usize] {
+        &self.consumed
+    }
+
+    /// The entries whose consumption failed, with their errors.
+    #[inline]
+    pub fn failed(&self) -> &[(usize, T::Error)] {
+        &self.failed
+    }
+
+    /// The entries whose consumption panicked.
+    #[inline]
+    pub fn panicked(&self) -> &[usize] {
+        &self.panicked
+    }
+
+    /// The entries which were still being consumed at the deadline. Their consumption carries
+    /// on in the background.
+    #[inline]
+    pub fn timed_out(&self) -> &[usize] {
+        &self.timed_out
+    }
+
+    /// The entries which were not started before the deadline, with their values.
+    #[inline]
+    pub fn unstarted(&self) -> &[(usize, T)] {
+        &self.unstarted
+    }
+
+    /// Whether every entry was consumed successfully before the deadline.
+    #[inline]
+    pub fn is_complete(&self) -> bool {
+        self.failed.is_empty()
+            && self.panicked.is_empty()
+            && self.timed_out.is_empty()
+            && self.unstarted.is_empty()
+    }
+
+    /// Extracts the entries which were not started, e.g. to force them closed.
+    #[inline]
+    pub fn into_unstarted(self) -> Vec<(usize, T)> {
+        self.unstarted
+    }
+}
+
+impl<T: TryConsume + fmt::Debug> fmt::Debug for ShutdownReport<T>
+where
+    T::Error: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ShutdownReport")
+            .field("consumed", &self.consumed)
+            .field("failed", &self.failed)
+            .field("panicked", &self.panicked)
+            .field("timed_out", &self.timed_out)
+            .field("unstarted", &self.unstarted)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::shutdown_concurrently;
+    use crate::Closure;
+    use std::sync::mpsc;
+    use std::time::{Duration, Instant};
+    use std::vec::Vec;
+
+    #[test]
+    fn reports_every_entry() {
+        let (release, stuck) = mpsc::channel::<()>();
+        let mut stuck = Some(stuck);
+        let sessions: Vec<_> = (0..4)
+            .map(|id| {
+                let stuck = if id == 2 { stuck.take() } else { None };
+                Closure(move || match (id, stuck) {
+                    (1, _) => Err("reset by peer"),
+                    (_, Some(stuck)) => {
+                        let _ = stuck.recv();
+                        Ok(())
+                    }
+                    _ => Ok(()),
+                })
+            })
+            .collect();
+        let deadline = Instant::now() + Duration::from_millis(200);
+        let report = shutdown_concurrently(sessions, 4, deadline);
+        assert_eq!(report.consumed(), [0, 3]);
+        assert_eq!(report.failed(), [(1, "reset by peer")]);
+        assert_eq!(report.timed_out(), [2]);
+        assert!(report.unstarted().is_empty());
+        assert!(!report.is_complete());
+        drop(release);
+    }
+}
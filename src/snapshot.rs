@@ -0,0 +1,105 @@
+use crate::{Consumer, WithConsumer};
+use core::ops::{Deref, DerefMut};
+
+/// The [`Consumer`] used by [`Snapshot`]: writes a saved value back through a mutable reference.
+#[derive(Debug)]
+struct Restore<T>(T);
+
+impl<'a, T> Consumer<&'a mut T> for Restore<T> {
+    #[inline]
+    fn consume(self, target: &'a mut T) {
+        *target = self.0
+    }
+}
+
+/// A mutable borrow of a `T` which is rolled back to a snapshot when dropped, unless
+/// [`Snapshot::commit`] is called first.
+///
+/// The snapshot is taken at construction, so edits made through the [`Snapshot`] are undone if
+/// the scope is left early or unwinds.
+#[derive(Debug)]
+pub struct Snapshot<'a, T> {
+    inner: WithConsumer<&'a mut T, Restore<T>>,
+}
+
+impl<'a, T: Clone> Snapshot<'a, T> {
+    /// Borrows `target`, saving a clone of its current value.
+    #[inline]
+    pub fn new(target: &'a mut T) -> Self {
+        Self::with_snapshot(target, T::clone)
+    }
+}
+
+impl<'a, T> Snapshot<'a, T> {
+    /// Borrows `target`, saving the value produced by `snapshot`. This is useful when `T` is not
+    /// [`Clone`], or when only part of its state needs to be restored.
+    #[inline]
+    pub fn with_snapshot(target: &'a mut T, snapshot: impl FnOnce(&T) -> T) -> Self {
+        let saved = snapshot(target);
+        Self {
+            inner: WithConsumer::new(target, Restore(saved)),
+        }
+    }
+
+    /// Provides a reference to the saved value.
+    #[inline]
+    pub fn saved(x: &Self) -> &T {
+        &WithConsumer::as_refs(&x.inner).1 .0
+    }
+
+    /// Keeps the edits, discarding the snapshot.
+    #[inline]
+    pub fn commit(x: Self) -> &'a mut T {
+        WithConsumer::into_inner(x.inner)
+    }
+
+    /// Restores the snapshot now, returning the borrow.
+    #[inline]
+    pub fn rollback(x: Self) -> &'a mut T {
+        let (target, Restore(saved)) = WithConsumer::into_pair(x.inner);
+        *target = saved;
+        target
+    }
+}
+
+impl<T> Deref for Snapshot<'_, T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        &self.inner
+    }
+}
+
+impl<T> DerefMut for Snapshot<'_, T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Snapshot;
+
+    #[test]
+    fn rolls_back_unless_committed() {
+        let mut balance = [10, 20];
+        {
+            let mut edit = Snapshot::new(&mut balance);
+            edit[0] -= 5;
+            edit[1] += 5;
+            assert_eq!(*Snapshot::saved(&edit), [10, 20]);
+        }
+        assert_eq!(balance, [10, 20]);
+
+        let mut edit = Snapshot::new(&mut balance);
+        edit[0] -= 5;
+        *Snapshot::commit(edit) = [1, 2];
+        assert_eq!(balance, [1, 2]);
+
+        let mut edit = Snapshot::with_snapshot(&mut balance, |b| [b[0], 0]);
+        edit[0] = 7;
+        assert_eq!(*Snapshot::rollback(edit), [1, 0]);
+    }
+}
@@ -0,0 +1,231 @@
+use crate::label::observer::consume_labeled;
+use crate::{Closure, Consume, ConsumeOnDrop, Consumer, TryConsume, TryConsumer};
+use core::fmt;
+use core::ops::{Deref, DerefMut};
+use tracing_error::SpanTrace;
+
+/// A value paired with the [`SpanTrace`] of the code which built it, so that a failure to
+/// consume it can be traced back to where the resource was created, not just where it died.
+///
+/// Consuming it with [`TryConsume::try_consume`] wraps any error in a [`TracedError`]. If
+/// consuming it with [`Consume::consume`] panics, the span trace is passed as the label to the
+/// [`GuardObserver`](crate::GuardObserver), or reported on standard error. Build one with
+/// [`ConsumeOnDrop::new_traced`].
+///
+/// Spans are only recorded while a subscriber with a
+/// [`tracing_error::ErrorLayer`] is installed.
+#[derive(Clone, Debug)]
+pub struct Traced<T> {
+    value: T,
+    span_trace: SpanTrace,
+}
+
+impl<T> Traced<T> {
+    /// Pairs `value` with the span trace of the current span.
+    #[inline]
+    pub fn new(value: T) -> Self {
+        Self {
+            value,
+            span_trace: SpanTrace::capture(),
+        }
+    }
+
+    /// The span trace captured when `x` was built.
+    #[inline]
+    pub fn span_trace(x: &Self) -> &SpanTrace {
+        &x.span_trace
+    }
+
+    /// Separates the value from its span trace.
+    #[inline]
+    pub fn into_parts(x: Self) -> (T, SpanTrace) {
+        (x.value, x.span_trace)
+    }
+
+    /// Extracts the value, dropping the span trace.
+    #[inline]
+    pub fn into_inner(x: Self) -> T {
+        x.value
+    }
+}
+
+impl<T: Consume> Consume for Traced<T> {
+    #[inline]
+    fn consume(self) {
+        consume_labeled(self.value, &self.span_trace)
+    }
+}
+
+impl<T: TryConsume> TryConsume for Traced<T> {
+    type Error = TracedError<T::Error>;
+
+    fn try_consume(self) -> Result<(), Self::Error> {
+        let span_trace = self.span_trace;
+        self.value
+            .try_consume()
+            .map_err(|error| TracedError { error, span_trace })
+    }
+}
+
+impl<T> Deref for Traced<T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T> DerefMut for Traced<T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
+
+impl<T: Consume> ConsumeOnDrop<T> {
+    /// Wraps a `T` in a [`ConsumeOnDrop`] along with the span trace of the current span. See
+    /// [`Traced`].
+    #[inline]
+    pub fn new_traced(value: T) -> ConsumeOnDrop<Traced<T>> {
+        ConsumeOnDrop::new(Traced::new(value))
+    }
+}
+
+/// A [`Consumer<T>`] or [`TryConsumer<T>`] which passes values on to the consumer `Q`, and
+/// traces failures back to the [`SpanTrace`] captured when it was built, like [`Traced`] does
+/// for self-consuming values.
+#[derive(Clone, Debug)]
+pub struct TracedConsumer<Q> {
+    inner: Q,
+    span_trace: SpanTrace,
+}
+
+impl<Q> TracedConsumer<Q> {
+    /// Wraps `inner`, capturing the span trace of the current span.
+    #[inline]
+    pub fn new(inner: Q) -> Self {
+        Self {
+            inner,
+            span_trace: SpanTrace::capture(),
+        }
+    }
+
+    /// The span trace captured when this consumer was built.
+    #[inline]
+    pub fn span_trace(&self) -> &SpanTrace {
+        &self.span_trace
+    }
+}
+
+impl<T, Q: Consumer<T>> Consumer<T> for TracedConsumer<Q> {
+    #[inline]
+    fn consume(self, other: T) {
+        let inner = self.inner;
+        consume_labeled(Closure(move || inner.consume(other)), &self.span_trace)
+    }
+}
+
+impl<T, Q: TryConsumer<T>> TryConsumer<T> for TracedConsumer<Q> {
+    type Error = TracedError<Q::Error>;
+
+    fn try_consume(self, other: T) -> Result<(), Self::Error> {
+        let span_trace = self.span_trace;
+        self.inner
+            .try_consume(other)
+            .map_err(|error| TracedError { error, span_trace })
+    }
+}
+
+/// An error from a fallible cleanup, along with the [`SpanTrace`] of the code which built the
+/// guarded resource.
+///
+/// It displays as the error alone, and as the error followed by the span trace in the
+/// alternate form (`{:#}`).
+#[derive(Clone, Debug)]
+pub struct TracedError<E> {
+    error: E,
+    span_trace: SpanTrace,
+}
+
+impl<E> TracedError<E> {
+    /// The error returned by the cleanup.
+    #[inline]
+    pub fn error(&self) -> &E {
+        &self.error
+    }
+
+    /// The span trace captured when the resource was built.
+    #[inline]
+    pub fn span_trace(&self) -> &SpanTrace {
+        &self.span_trace
+    }
+
+    /// Separates the error from its span trace.
+    #[inline]
+    pub fn into_parts(self) -> (E, SpanTrace) {
+        (self.error, self.span_trace)
+    }
+
+    /// Extracts the error, dropping the span trace.
+    #[inline]
+    pub fn into_inner(self) -> E {
+        self.error
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for TracedError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.error, f)?;
+        if f.alternate() {
+            write!(f, "\nresource created at:\n{}", self.span_trace)?;
+        }
+        Ok(())
+    }
+}
+
+impl<E: std::error::Error + 'static> std::error::Error for TracedError<E> {
+    #[inline]
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Traced, TracedConsumer};
+    use crate::{Closure, TryConsume, TryConsumer};
+    use alloc::string::ToString;
+    use tracing_error::{ErrorLayer, SpanTraceStatus};
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[test]
+    fn traces_errors_to_construction() {
+        let subscriber = tracing_subscriber::registry().with(ErrorLayer::default());
+        tracing::subscriber::with_default(subscriber, || {
+            let (lease, consumer) = tracing::info_span!("open_lease").in_scope(|| {
+                (
+                    Traced::new(Closure(|| Err::<(), _>("lease expired"))),
+                    TracedConsumer::new(Closure(|_: u8| Err::<(), _>("bad handle"))),
+                )
+            });
+
+            let error = tracing::info_span!("shutdown")
+                .in_scope(|| lease.try_consume())
+                .unwrap_err();
+            assert_eq!(*error.error(), "lease expired");
+            assert_eq!(error.span_trace().status(), SpanTraceStatus::CAPTURED);
+            let mut spans = alloc::vec::Vec::new();
+            error.span_trace().with_spans(|span, _| {
+                spans.push(span.name());
+                true
+            });
+            assert_eq!(spans, ["open_lease"]);
+            assert!(std::format!("{:#}", error).contains("open_lease"));
+            assert_eq!(error.to_string(), "lease expired");
+
+            let error = consumer.try_consume(3).unwrap_err();
+            assert_eq!(error.into_inner(), "bad handle");
+        })
+    }
+}
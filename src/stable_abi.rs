@@ -0,0 +1,210 @@
+use crate::Consumer;
+use alloc::boxed::Box;
+use core::ffi::c_void;
+use core::fmt;
+use core::mem::ManuallyDrop;
+use core::ops::{Deref, DerefMut};
+use core::ptr;
+
+/// A type-erased [`Consumer<T>`] with a stable, `#[repr(C)]` layout, for passing guarded
+/// resources between a host and plugins which were compiled separately.
+///
+/// A [`StableConsumer`] is a context pointer and two `extern "C"` function pointers. The
+/// functions are always those of the binary which built the consumer, so the consumer may be
+/// used from another binary without relying on matching layouts, vtables, or allocators. `T`
+/// itself must be FFI-safe, e.g. a primitive, a raw pointer, or a `#[repr(C)]` type.
+///
+/// Unwinding cannot cross an `extern "C"` function, so a consumer erased with
+/// [`StableConsumer::from_consumer`] aborts the process if it panics.
+#[repr(C)]
+pub struct StableConsumer<T> {
+    context: *mut c_void,
+    consume: unsafe extern "C" fn(context: *mut c_void, value: T),
+    release: Option<unsafe extern "C" fn(context: *mut c_void)>,
+}
+
+// SAFETY: `from_consumer` only accepts `Send` consumers, and `from_raw_parts` requires that the
+// context may be used from any thread.
+unsafe impl<T: Send> Send for StableConsumer<T> {}
+
+unsafe extern "C" fn consume_boxed<T, Q: Consumer<T>>(context: *mut c_void, value: T) {
+    // SAFETY: `context` was produced by `Box::into_raw` in `from_consumer`, and is used once.
+    let consumer = unsafe { Box::from_raw(context.cast::<Q>()) };
+    consumer.consume(value)
+}
+
+unsafe extern "C" fn release_boxed<Q>(context: *mut c_void) {
+    // SAFETY: as in `consume_boxed`.
+    drop(unsafe { Box::from_raw(context.cast::<Q>()) })
+}
+
+impl<T> StableConsumer<T> {
+    /// Erases `consumer`, which is moved to the heap.
+    ///
+    /// If the consumer is later dropped without being used, e.g. because the guard was
+    /// unwrapped, `consumer` is dropped by this binary, whichever binary drops the result.
+    pub fn from_consumer<Q: Consumer<T> + Send + 'static>(consumer: Q) -> Self {
+        Self {
+            context: Box::into_raw(Box::new(consumer)).cast(),
+            consume: consume_boxed::<T, Q>,
+            release: Some(release_boxed::<Q>),
+        }
+    }
+
+    /// Assembles a consumer from its parts. `consume` is called with `context` to consume a
+    /// value, or else `release`, if any, is called with `context` when the consumer is dropped.
+    ///
+    /// # Safety
+    ///
+    /// Calling `consume` or `release` with `context` once, from any thread, must be sound.
+    #[inline]
+    pub const unsafe fn from_raw_parts(
+        context: *mut c_void,
+        consume: unsafe extern "C" fn(context: *mut c_void, value: T),
+        release: Option<unsafe extern "C" fn(context: *mut c_void)>,
+    ) -> Self {
+        Self {
+            context,
+            consume,
+            release,
+        }
+    }
+}
+
+impl<T> Consumer<T> for StableConsumer<T> {
+    #[inline]
+    fn consume(self, other: T) {
+        let this = ManuallyDrop::new(self);
+        // SAFETY: the constructors guarantee that this call is sound, and `this` is never
+        // dropped, so `release` won't be called as well.
+        unsafe { (this.consume)(this.context, other) }
+    }
+}
+
+impl<T> Drop for StableConsumer<T> {
+    fn drop(&mut self) {
+        if let Some(release) = self.release {
+            // SAFETY: the constructors guarantee that this call is sound.
+            unsafe { release(self.context) }
+        }
+    }
+}
+
+impl<T> fmt::Debug for StableConsumer<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StableConsumer")
+            .field("context", &self.context)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A `T` paired with a [`StableConsumer<T>`], with a stable, `#[repr(C)]` layout. When this pair
+/// is dropped, the `T` is consumed by the consumer.
+///
+/// This is the counterpart of [`WithConsumer`](crate::WithConsumer) for values which cross a
+/// `cdylib` boundary: the value is stored first, followed by the consumer, and a foreign binary
+/// can drop the guard without knowing anything about the consumer's original type.
+#[repr(C)]
+pub struct StableGuard<T> {
+    value: ManuallyDrop<T>,
+    consumer: ManuallyDrop<StableConsumer<T>>,
+}
+
+impl<T> StableGuard<T> {
+    /// Builds a [`StableGuard`] from a value and a consumer.
+    #[inline]
+    pub const fn new(value: T, consumer: StableConsumer<T>) -> Self {
+        Self {
+            value: ManuallyDrop::new(value),
+            consumer: ManuallyDrop::new(consumer),
+        }
+    }
+
+    /// Extracts the underlying `T` and [`StableConsumer<T>`].
+    #[inline]
+    pub fn into_pair(x: Self) -> (T, StableConsumer<T>) {
+        let x = ManuallyDrop::new(x);
+        // SAFETY: each field is read exactly once, and `x` is never dropped.
+        unsafe { (ptr::read(&*x.value), ptr::read(&*x.consumer)) }
+    }
+
+    /// Extracts the underlying `T`, releasing the consumer without using it.
+    #[inline]
+    pub fn into_inner(x: Self) -> T {
+        Self::into_pair(x).0
+    }
+}
+
+impl<T> Drop for StableGuard<T> {
+    fn drop(&mut self) {
+        // SAFETY: it is impossible to use `self.value` or `self.consumer` after this.
+        let (value, consumer) = unsafe {
+            (
+                ManuallyDrop::take(&mut self.value),
+                ManuallyDrop::take(&mut self.consumer),
+            )
+        };
+        consumer.consume(value)
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for StableGuard<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StableGuard")
+            .field("value", &*self.value)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T> Deref for StableGuard<T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T> DerefMut for StableGuard<T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{StableConsumer, StableGuard};
+    use crate::Closure;
+    use core::ffi::c_void;
+    use core::sync::atomic::{AtomicU32, Ordering};
+
+    static CLOSED: AtomicU32 = AtomicU32::new(0);
+    static RELEASED: AtomicU32 = AtomicU32::new(0);
+
+    unsafe extern "C" fn close(_context: *mut c_void, handle: u32) {
+        CLOSED.fetch_add(handle, Ordering::Relaxed);
+    }
+
+    unsafe extern "C" fn release(_context: *mut c_void) {
+        RELEASED.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[test]
+    fn consumes_across_erasure() {
+        let consumer =
+            unsafe { StableConsumer::from_raw_parts(core::ptr::null_mut(), close, Some(release)) };
+        drop(StableGuard::new(3, consumer));
+        let consumer = StableConsumer::from_consumer(Closure(|handle: u32| {
+            CLOSED.fetch_add(handle * 10, Ordering::Relaxed);
+        }));
+        drop(StableGuard::new(4, consumer));
+        assert_eq!(CLOSED.load(Ordering::Relaxed), 43);
+
+        let consumer =
+            unsafe { StableConsumer::from_raw_parts(core::ptr::null_mut(), close, Some(release)) };
+        assert_eq!(StableGuard::into_inner(StableGuard::new(5, consumer)), 5);
+        assert_eq!(CLOSED.load(Ordering::Relaxed), 43);
+        assert_eq!(RELEASED.load(Ordering::Relaxed), 1);
+    }
+}
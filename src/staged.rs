@@ -0,0 +1,164 @@
+use crate::{Consume, ConsumeOnDrop};
+use core::ops::{Deref, DerefMut};
+
+/// A two-phase counterpart to [`Consume`], for types torn down by a "flush then close" protocol.
+///
+/// [`StagedConsume::prepare`] is fallible and may run early, while the value is still in use;
+/// [`StagedConsume::finish`] consumes the value and cannot fail. Databases and file formats
+/// which must be flushed before being closed fit this pattern.
+pub trait StagedConsume {
+    /// The error produced when preparation fails.
+    type Error;
+
+    /// Prepares `self` to be finished, e.g. by flushing buffered writes.
+    fn prepare(&mut self) -> Result<(), Self::Error>;
+
+    /// Consumes `self`. Called once, after [`StagedConsume::prepare`] has been attempted.
+    fn finish(self);
+}
+
+#[derive(Debug)]
+struct Staged<T> {
+    value: T,
+    prepared: bool,
+}
+
+impl<T: StagedConsume> Consume for Staged<T> {
+    fn consume(mut self) {
+        if !self.prepared {
+            let _ = self.value.prepare();
+        }
+        self.value.finish()
+    }
+}
+
+/// A guard which tears down a [`StagedConsume`] value in two phases.
+///
+/// The value can be prepared early with [`StagedGuard::prepare`]. Modifying it through
+/// [`DerefMut`] marks it as unprepared again. When the guard is dropped, the value is prepared
+/// if needed, ignoring any error, and then finished. Use [`StagedGuard::finish`] to observe the
+/// error instead.
+#[derive(Debug)]
+pub struct StagedGuard<T: StagedConsume> {
+    inner: ConsumeOnDrop<Staged<T>>,
+}
+
+impl<T: StagedConsume> StagedGuard<T> {
+    /// Wraps an unprepared `T` in a [`StagedGuard`].
+    #[inline]
+    pub const fn new(value: T) -> Self {
+        Self {
+            inner: ConsumeOnDrop::new(Staged {
+                value,
+                prepared: false,
+            }),
+        }
+    }
+
+    /// Prepares the value now, unless it already is. If preparation fails, the value remains
+    /// unprepared, and is prepared again when it is finished.
+    pub fn prepare(x: &mut Self) -> Result<(), T::Error> {
+        let staged = &mut *x.inner;
+        if !staged.prepared {
+            staged.value.prepare()?;
+            staged.prepared = true;
+        }
+        Ok(())
+    }
+
+    /// Whether the value has been prepared since it was last modified.
+    #[inline]
+    pub fn is_prepared(x: &Self) -> bool {
+        x.inner.prepared
+    }
+
+    /// Prepares the value if needed, then finishes it. The value is finished even if
+    /// preparation fails, in which case the error is returned.
+    pub fn finish(mut x: Self) -> Result<(), T::Error> {
+        let result = Self::prepare(&mut x);
+        let Staged { value, .. } = ConsumeOnDrop::into_inner(x.inner);
+        value.finish();
+        result
+    }
+
+    /// Extracts the value without preparing or finishing it.
+    #[inline]
+    pub fn into_inner(x: Self) -> T {
+        ConsumeOnDrop::into_inner(x.inner).value
+    }
+}
+
+impl<T: StagedConsume> Deref for StagedGuard<T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        &self.inner.value
+    }
+}
+
+impl<T: StagedConsume> DerefMut for StagedGuard<T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T {
+        let staged = &mut *self.inner;
+        staged.prepared = false;
+        &mut staged.value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{StagedConsume, StagedGuard};
+    use std::vec::Vec;
+
+    struct Table<'a> {
+        pending: Vec<u32>,
+        log: &'a mut Vec<&'static str>,
+        fail: bool,
+    }
+
+    impl StagedConsume for Table<'_> {
+        type Error = &'static str;
+
+        fn prepare(&mut self) -> Result<(), &'static str> {
+            if self.fail {
+                return Err("disk full");
+            }
+            self.pending.clear();
+            self.log.push("flush");
+            Ok(())
+        }
+
+        fn finish(self) {
+            self.log.push("close")
+        }
+    }
+
+    #[test]
+    fn flush_then_close() {
+        let mut log = Vec::new();
+        {
+            let mut table = StagedGuard::new(Table {
+                pending: Vec::new(),
+                log: &mut log,
+                fail: false,
+            });
+            table.pending.push(1);
+            StagedGuard::prepare(&mut table).unwrap();
+            StagedGuard::prepare(&mut table).unwrap();
+            assert!(StagedGuard::is_prepared(&table));
+            table.pending.push(2);
+            assert!(!StagedGuard::is_prepared(&table));
+        }
+        assert_eq!(log, ["flush", "flush", "close"]);
+
+        log.clear();
+        let table = StagedGuard::new(Table {
+            pending: Vec::new(),
+            log: &mut log,
+            fail: true,
+        });
+        assert_eq!(StagedGuard::finish(table), Err("disk full"));
+        assert_eq!(log, ["close"]);
+    }
+}
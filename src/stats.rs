@@ -0,0 +1,201 @@
+use crate::label::observer::consume_labeled;
+use crate::{Consume, ConsumeOnDrop};
+use core::fmt;
+use core::ops::{Deref, DerefMut};
+use core::sync::atomic::{AtomicU64, Ordering};
+use std::collections::BTreeMap;
+use std::string::{String, ToString};
+use std::sync::{Arc, Mutex};
+use std::vec::Vec;
+
+#[derive(Default)]
+struct Counters {
+    live: AtomicU64,
+    consumed: AtomicU64,
+}
+
+static COUNTERS: Mutex<BTreeMap<String, Arc<Counters>>> = Mutex::new(BTreeMap::new());
+
+fn counters() -> std::sync::MutexGuard<'static, BTreeMap<String, Arc<Counters>>> {
+    COUNTERS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Counts a [`Counted`] value as live for as long as it exists.
+struct Ticket(Arc<Counters>);
+
+impl Ticket {
+    fn register(label: String) -> Self {
+        let entry = counters().entry(label).or_default().clone();
+        entry.live.fetch_add(1, Ordering::Relaxed);
+        Self(entry)
+    }
+}
+
+impl Drop for Ticket {
+    fn drop(&mut self) {
+        self.0.live.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// A labeled value which is counted in program-wide statistics, queryable at any time with
+/// [`stats`].
+///
+/// The value is counted as live from construction until it is consumed or unwrapped, and as
+/// consumed once it is consumed. Values sharing a label share their counts, so a label can name
+/// a type, such as with [`Counted::by_type`], or any other category, such as `"session"`.
+/// Otherwise, it behaves like a [`Labeled`](crate::Labeled) value. Build one with
+/// [`ConsumeOnDrop::new_counted`].
+pub struct Counted<T, L = &'static str> {
+    value: T,
+    label: L,
+    ticket: Ticket,
+}
+
+impl<T, L: fmt::Display> Counted<T, L> {
+    /// Pairs `value` with `label`, and counts it as live.
+    pub fn new(value: T, label: L) -> Self {
+        let ticket = Ticket::register(label.to_string());
+        Self {
+            value,
+            label,
+            ticket,
+        }
+    }
+}
+
+impl<T> Counted<T> {
+    /// Pairs `value` with the name of its type as a label, and counts it as live.
+    #[inline]
+    pub fn by_type(value: T) -> Self {
+        Self::new(value, core::any::type_name::<T>())
+    }
+}
+
+impl<T, L> Counted<T, L> {
+    /// The label attached to `x`.
+    #[inline]
+    pub fn label(x: &Self) -> &L {
+        &x.label
+    }
+
+    /// Separates the value from its label, and stops counting it as live.
+    #[inline]
+    pub fn into_parts(x: Self) -> (T, L) {
+        (x.value, x.label)
+    }
+
+    /// Extracts the value, dropping the label, and stops counting it as live.
+    #[inline]
+    pub fn into_inner(x: Self) -> T {
+        x.value
+    }
+}
+
+impl<T: Consume, L: fmt::Display> Consume for Counted<T, L> {
+    fn consume(self) {
+        self.ticket.0.consumed.fetch_add(1, Ordering::Relaxed);
+        drop(self.ticket);
+        consume_labeled(self.value, &self.label)
+    }
+}
+
+impl<T: fmt::Debug, L: fmt::Debug> fmt::Debug for Counted<T, L> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Counted")
+            .field("value", &self.value)
+            .field("label", &self.label)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T, L> Deref for Counted<T, L> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T, L> DerefMut for Counted<T, L> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
+
+impl<T: Consume> ConsumeOnDrop<T> {
+    /// Wraps a `T` in a [`ConsumeOnDrop`] along with a label, and counts it in the statistics
+    /// returned by [`stats`]. See [`Counted`].
+    #[inline]
+    pub fn new_counted<L: fmt::Display>(value: T, label: L) -> ConsumeOnDrop<Counted<T, L>> {
+        ConsumeOnDrop::new(Counted::new(value, label))
+    }
+}
+
+/// The counts of the [`Counted`] values sharing a label, as returned by [`stats`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct GuardStats {
+    /// The label shared by the values.
+    pub label: String,
+    /// The number of values which currently exist.
+    pub live: u64,
+    /// The number of values consumed so far.
+    pub consumed: u64,
+}
+
+/// Lists the counts of the [`Counted`] values for every label which has been used so far, in
+/// order of their labels.
+///
+/// The counts are read one label at a time while other threads may be building and consuming
+/// values, so they are only a snapshot, suitable for reporting a gauge.
+pub fn stats() -> Vec<GuardStats> {
+    counters()
+        .iter()
+        .map(|(label, counters)| GuardStats {
+            label: label.clone(),
+            live: counters.live.load(Ordering::Relaxed),
+            consumed: counters.consumed.load(Ordering::Relaxed),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{stats, Counted, GuardStats};
+    use crate::{Closure, ConsumeOnDrop};
+    use core::cell::Cell;
+
+    #[test]
+    fn counts_live_and_consumed() {
+        let closed = Cell::new(0);
+        let counts = |label| stats().into_iter().find(|stats| stats.label == label);
+
+        let first =
+            ConsumeOnDrop::new_counted(Closure(|| closed.set(closed.get() + 1)), "stats-session");
+        let second =
+            ConsumeOnDrop::new_counted(Closure(|| closed.set(closed.get() + 1)), "stats-session");
+        let counted = counts("stats-session").unwrap();
+        assert_eq!((counted.live, counted.consumed), (2, 0));
+
+        drop(first);
+        Counted::into_inner(ConsumeOnDrop::into_inner(second));
+        let counted = counts("stats-session").unwrap();
+        assert_eq!((counted.live, counted.consumed), (0, 1));
+        assert_eq!(closed.get(), 1);
+
+        let by_type = Counted::by_type(7u8);
+        assert_eq!(
+            counts("u8"),
+            Some(GuardStats {
+                label: "u8".into(),
+                live: 1,
+                consumed: 0
+            })
+        );
+        drop(by_type);
+    }
+}
@@ -0,0 +1,107 @@
+use crate::{Consumer, WithConsumer};
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use futures_core::{FusedStream, Stream};
+
+/// Extension methods for guarding the items of a [`Stream`].
+pub trait StreamGuardExt: Stream + Sized {
+    /// Wraps every item yielded by the stream in a [`WithConsumer`] with a clone of `consumer`,
+    /// so items which are pulled from the stream but abandoned later in the pipeline are still
+    /// cleaned up. Items which complete the pipeline can be released with
+    /// [`WithConsumer::into_inner`].
+    #[inline]
+    fn guard_items<Q>(self, consumer: Q) -> GuardItems<Self, Q>
+    where
+        Q: Consumer<Self::Item> + Clone,
+    {
+        GuardItems {
+            stream: self,
+            consumer,
+        }
+    }
+}
+
+impl<S: Stream> StreamGuardExt for S {}
+
+/// The stream returned by [`StreamGuardExt::guard_items`].
+#[derive(Debug, Clone)]
+#[must_use = "streams do nothing unless polled"]
+pub struct GuardItems<S, Q> {
+    stream: S,
+    consumer: Q,
+}
+
+impl<S, Q> GuardItems<S, Q> {
+    /// Unwraps the underlying stream and consumer.
+    #[inline]
+    pub fn into_parts(x: Self) -> (S, Q) {
+        (x.stream, x.consumer)
+    }
+}
+
+impl<S: Stream, Q: Consumer<S::Item> + Clone> Stream for GuardItems<S, Q> {
+    type Item = WithConsumer<S::Item, Q>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        // SAFETY: `stream` is pinned structurally: it is never moved out of a pinned
+        // `GuardItems`, which has no `Drop` impl and is only `Unpin` when `S` is. `consumer` is
+        // not pinned, and only a shared reference to it is used.
+        let (stream, consumer) = unsafe {
+            let this = self.get_unchecked_mut();
+            (Pin::new_unchecked(&mut this.stream), &this.consumer)
+        };
+        stream
+            .poll_next(cx)
+            .map(|item| item.map(|item| WithConsumer::new(item, consumer.clone())))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.stream.size_hint()
+    }
+}
+
+impl<S: FusedStream, Q: Consumer<S::Item> + Clone> FusedStream for GuardItems<S, Q> {
+    #[inline]
+    fn is_terminated(&self) -> bool {
+        self.stream.is_terminated()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StreamGuardExt;
+    use crate::{Closure, WithConsumer};
+    use core::cell::RefCell;
+    use core::pin::{pin, Pin};
+    use core::task::{Context, Poll, Waker};
+    use futures_core::Stream;
+
+    struct Iter<I>(I);
+
+    impl<I: Iterator + Unpin> Stream for Iter<I> {
+        type Item = I::Item;
+
+        fn poll_next(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Option<I::Item>> {
+            Poll::Ready(self.0.next())
+        }
+    }
+
+    #[test]
+    fn abandoned_items_are_consumed() {
+        let abandoned = RefCell::new(0);
+        let release = |n| *abandoned.borrow_mut() += n;
+        let mut stream = pin!(Iter(1..=3).guard_items(Closure(&release)));
+        let mut cx = Context::from_waker(Waker::noop());
+
+        let Poll::Ready(Some(first)) = stream.as_mut().poll_next(&mut cx) else {
+            panic!()
+        };
+        assert_eq!(WithConsumer::into_inner(first), 1);
+        let Poll::Ready(Some(second)) = stream.as_mut().poll_next(&mut cx) else {
+            panic!()
+        };
+        drop(second);
+        assert_eq!(*abandoned.borrow(), 2);
+    }
+}
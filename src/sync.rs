@@ -0,0 +1,38 @@
+//! The synchronization primitives of the concurrent guard types, such as [`RetireQueue`] and
+//! [`ConsumeBarrier`]. When the crate's own tests are built with `RUSTFLAGS="--cfg loom"`, they
+//! are `loom`'s instrumented versions, so that those types can be model-checked inside
+//! `loom::model`. Every other build uses the real ones.
+//!
+//! [`RetireQueue`]: crate::RetireQueue
+//! [`ConsumeBarrier`]: crate::ConsumeBarrier
+
+#[cfg(not(all(test, loom)))]
+pub(crate) use core::sync::atomic::AtomicUsize;
+#[cfg(all(test, loom))]
+pub(crate) use loom::sync::atomic::AtomicUsize;
+
+#[cfg(all(test, loom))]
+pub(crate) use loom::sync::{Arc, Condvar, Mutex, MutexGuard};
+#[cfg(all(feature = "std", not(all(test, loom))))]
+pub(crate) use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+
+#[cfg(all(test, loom))]
+pub(crate) use loom::cell::UnsafeCell;
+
+/// An [`UnsafeCell`](core::cell::UnsafeCell) with the closure-based interface of `loom`'s, which
+/// tracks every access.
+#[cfg(not(all(test, loom)))]
+pub(crate) struct UnsafeCell<T>(core::cell::UnsafeCell<T>);
+
+#[cfg(not(all(test, loom)))]
+impl<T> UnsafeCell<T> {
+    #[inline]
+    pub(crate) const fn new(value: T) -> Self {
+        Self(core::cell::UnsafeCell::new(value))
+    }
+
+    #[inline]
+    pub(crate) fn with_mut<R>(&self, f: impl FnOnce(*mut T) -> R) -> R {
+        f(self.0.get())
+    }
+}
@@ -0,0 +1,130 @@
+use crate::{Consume, ConsumeOnDrop};
+use core::fmt;
+use core::mem::MaybeUninit;
+use std::io;
+use std::os::fd::{AsRawFd, RawFd};
+
+struct SavedMode {
+    fd: RawFd,
+    mode: libc::termios,
+}
+
+impl Consume for SavedMode {
+    #[inline]
+    fn consume(self) {
+        // Nobody can act on a failure while the guard is dropped, least of all while the
+        // thread is panicking, so it is ignored.
+        // SAFETY: `mode` was filled in by `tcgetattr`.
+        unsafe {
+            libc::tcsetattr(self.fd, libc::TCSAFLUSH, &self.mode);
+        }
+    }
+}
+
+fn get_mode(fd: RawFd) -> io::Result<libc::termios> {
+    let mut mode = MaybeUninit::uninit();
+    // SAFETY: `tcgetattr` fills in `mode` when it succeeds.
+    if unsafe { libc::tcgetattr(fd, mode.as_mut_ptr()) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    // SAFETY: `tcgetattr` succeeded.
+    Ok(unsafe { mode.assume_init() })
+}
+
+/// Saves the mode of a terminal, and restores it with `tcsetattr(3)` when dropped, including
+/// while unwinding.
+///
+/// A program which switches its terminal to raw mode must switch it back however it exits, or
+/// it leaves the user's shell unusable. [`TerminalModeGuard::raw`] does both at once.
+///
+/// The guard refers to the terminal by its descriptor, which must stay open while the guard
+/// exists. Pending input is discarded when the mode is restored.
+pub struct TerminalModeGuard {
+    inner: ConsumeOnDrop<SavedMode>,
+}
+
+impl TerminalModeGuard {
+    /// Saves the current mode of the terminal `fd`, e.g. before changing it by other means.
+    pub fn capture(fd: &impl AsRawFd) -> io::Result<Self> {
+        let fd = fd.as_raw_fd();
+        Ok(Self {
+            inner: ConsumeOnDrop::new(SavedMode {
+                fd,
+                mode: get_mode(fd)?,
+            }),
+        })
+    }
+
+    /// Saves the current mode of the terminal `fd`, then switches it to raw mode, as set up by
+    /// `cfmakeraw(3)`: input is available byte by byte, without echo or signal keys.
+    pub fn raw(fd: &impl AsRawFd) -> io::Result<Self> {
+        let guard = Self::capture(fd)?;
+        let mut raw = guard.inner.mode;
+        // SAFETY: `raw` is a valid `termios`, copied from the saved mode.
+        unsafe { libc::cfmakeraw(&mut raw) };
+        // SAFETY: as above.
+        if unsafe { libc::tcsetattr(guard.inner.fd, libc::TCSAFLUSH, &raw) } == -1 {
+            let error = io::Error::last_os_error();
+            Self::dismiss(guard);
+            return Err(error);
+        }
+        Ok(guard)
+    }
+
+    /// The mode which will be restored.
+    #[inline]
+    pub fn saved_mode(x: &Self) -> &libc::termios {
+        &x.inner.mode
+    }
+
+    /// Drops the guard without restoring the saved mode.
+    #[inline]
+    pub fn dismiss(x: Self) {
+        let _ = ConsumeOnDrop::into_inner(x.inner);
+    }
+}
+
+impl fmt::Debug for TerminalModeGuard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TerminalModeGuard")
+            .field("fd", &self.inner.fd)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{get_mode, TerminalModeGuard};
+    use std::fs::File;
+    use std::os::fd::{AsRawFd, FromRawFd};
+
+    #[test]
+    fn restores_mode() {
+        let (mut leader, mut follower) = (0, 0);
+        let opened = unsafe {
+            libc::openpty(
+                &mut leader,
+                &mut follower,
+                core::ptr::null_mut(),
+                core::ptr::null(),
+                core::ptr::null(),
+            )
+        };
+        assert_eq!(opened, 0);
+        let (_leader, follower) =
+            unsafe { (File::from_raw_fd(leader), File::from_raw_fd(follower)) };
+
+        let cooked = get_mode(follower.as_raw_fd()).unwrap();
+        assert_ne!(cooked.c_lflag & libc::ECHO, 0);
+        let guard = TerminalModeGuard::raw(&follower).unwrap();
+        assert_eq!(
+            get_mode(follower.as_raw_fd()).unwrap().c_lflag & libc::ECHO,
+            0
+        );
+        drop(guard);
+        assert_eq!(
+            get_mode(follower.as_raw_fd()).unwrap().c_lflag,
+            cooked.c_lflag
+        );
+    }
+}
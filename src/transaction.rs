@@ -0,0 +1,435 @@
+use crate::composite::consume_all;
+use crate::{Consume, ConsumeOnDrop};
+use alloc::boxed::Box;
+use alloc::vec::{self, Vec};
+use core::cell::RefCell;
+use core::fmt;
+use core::ops::{Deref, DerefMut};
+
+/// A change to a resource which is applied in two phases, or undone.
+///
+/// [`Transactional::prepare`] does everything which can fail, such as writing a temporary file,
+/// without making the change visible. [`Transactional::commit`] then makes it visible, such as by
+/// renaming the file into place, and [`Transactional::rollback`] undoes the preparation instead.
+pub trait Transactional {
+    /// The error produced when a phase fails.
+    type Error;
+
+    /// Prepares the change, without making it visible. Called at most once, before committing.
+    fn prepare(&mut self) -> Result<(), Self::Error>;
+
+    /// Makes the prepared change visible.
+    fn commit(self) -> Result<(), Self::Error>;
+
+    /// Undoes the change, whether or not it was prepared.
+    fn rollback(self) -> Result<(), Self::Error>;
+}
+
+#[derive(Debug)]
+struct Pending<T>(T);
+
+impl<T: Transactional> Consume for Pending<T> {
+    #[inline]
+    fn consume(self) {
+        // There is nobody to report a failure to.
+        let _ = self.0.rollback();
+    }
+}
+
+/// A guard which rolls back a [`Transactional`] change when dropped, unless it was committed.
+///
+/// Commit it on its own with [`Transaction::commit`], or together with other transactions with
+/// a [`TxnGroup`].
+#[derive(Debug)]
+#[must_use = "the change is rolled back unless it is committed"]
+pub struct Transaction<T: Transactional> {
+    inner: ConsumeOnDrop<Pending<T>>,
+}
+
+impl<T: Transactional> Transaction<T> {
+    /// Starts a transaction applying the change `value`.
+    #[inline]
+    pub const fn new(value: T) -> Self {
+        Self {
+            inner: ConsumeOnDrop::new(Pending(value)),
+        }
+    }
+
+    /// Prepares and commits the change. If preparation fails, the change is rolled back, and
+    /// the error from preparing it is returned. If preparation panics, the change is rolled
+    /// back while unwinding.
+    pub fn commit(mut x: Self) -> Result<(), T::Error> {
+        match x.prepare() {
+            Ok(()) => Self::into_inner(x).commit(),
+            Err(error) => {
+                let _ = Self::rollback(x);
+                Err(error)
+            }
+        }
+    }
+
+    /// Rolls back the change now, reporting any error.
+    #[inline]
+    pub fn rollback(x: Self) -> Result<(), T::Error> {
+        Self::into_inner(x).rollback()
+    }
+
+    /// Extracts the change without committing or rolling it back.
+    #[inline]
+    pub fn into_inner(x: Self) -> T {
+        ConsumeOnDrop::into_inner(x.inner).0
+    }
+}
+
+impl<T: Transactional> Deref for Transaction<T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        &self.inner.0
+    }
+}
+
+impl<T: Transactional> DerefMut for Transaction<T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.inner.0
+    }
+}
+
+/// A [`Transactional`] change with its error type erased into `E`.
+trait Member<E> {
+    fn prepare(&mut self) -> Result<(), E>;
+    fn commit(self: Box<Self>) -> Result<(), E>;
+    fn rollback(self: Box<Self>) -> Result<(), E>;
+}
+
+impl<T: Transactional, E> Member<E> for T
+where
+    T::Error: Into<E>,
+{
+    #[inline]
+    fn prepare(&mut self) -> Result<(), E> {
+        Transactional::prepare(self).map_err(Into::into)
+    }
+
+    #[inline]
+    fn commit(self: Box<Self>) -> Result<(), E> {
+        Transactional::commit(*self).map_err(Into::into)
+    }
+
+    #[inline]
+    fn rollback(self: Box<Self>) -> Result<(), E> {
+        Transactional::rollback(*self).map_err(Into::into)
+    }
+}
+
+type Members<'a, E> = Vec<Box<dyn Member<E> + 'a>>;
+
+/// A member being rolled back, whose error is collected into `errors` if there is somewhere to
+/// report it.
+struct RollingBack<'a, 'e, E> {
+    member: Box<dyn Member<E> + 'a>,
+    errors: Option<&'e RefCell<Vec<E>>>,
+}
+
+impl<'a, E> RollingBack<'a, '_, E> {
+    #[inline]
+    fn quietly(member: Box<dyn Member<E> + 'a>) -> Self {
+        Self {
+            member,
+            errors: None,
+        }
+    }
+}
+
+impl<E> Consume for RollingBack<'_, '_, E> {
+    #[inline]
+    fn consume(self) {
+        if let (Err(error), Some(errors)) = (self.member.rollback(), self.errors) {
+            errors.borrow_mut().push(error)
+        }
+    }
+}
+
+/// Rolls back `members` in reverse order, collecting the errors in that order. If a rollback
+/// panics, the rest are still rolled back while unwinding.
+fn roll_back_all<E>(members: Members<'_, E>) -> Vec<E> {
+    let errors = RefCell::new(Vec::new());
+    consume_all(members.into_iter().rev().map(|member| RollingBack {
+        member,
+        errors: Some(&errors),
+    }));
+    errors.into_inner()
+}
+
+/// Prepared members which have not been committed yet, and are rolled back in reverse order
+/// if committing is interrupted by a panic.
+struct Committing<'a, E>(vec::IntoIter<Box<dyn Member<E> + 'a>>);
+
+impl<E> Consume for Committing<'_, E> {
+    #[inline]
+    fn consume(self) {
+        consume_all(self.0.rev().map(RollingBack::quietly))
+    }
+}
+
+/// A coordinator which commits several [`Transaction`]s all together, or rolls all of them
+/// back, with two-phase commit.
+///
+/// [`TxnGroup::commit`] prepares every transaction in order. Only if they all succeed does it
+/// commit them, in the same order. Otherwise, every transaction is rolled back, in reverse
+/// order, and the errors are collected into a [`TxnError`]. Dropping the group without
+/// committing it rolls every transaction back, in reverse order, ignoring errors. Errors of
+/// every transaction are converted into the common error type `E`.
+///
+/// If preparing a transaction panics, every transaction is rolled back while unwinding. If
+/// committing one panics, the ones not yet committed are rolled back while unwinding.
+pub struct TxnGroup<'a, E> {
+    members: Members<'a, E>,
+}
+
+impl<'a, E> TxnGroup<'a, E> {
+    /// Builds an empty group.
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            members: Vec::new(),
+        }
+    }
+
+    /// Adds `transaction` to the group, returning its index in the eventual [`TxnError`].
+    pub fn push<T>(&mut self, transaction: Transaction<T>) -> usize
+    where
+        T: Transactional + 'a,
+        T::Error: Into<E>,
+    {
+        self.members
+            .push(Box::new(Transaction::into_inner(transaction)));
+        self.members.len() - 1
+    }
+
+    /// The number of transactions in the group.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    /// Whether the group has no transactions.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+
+    /// Prepares every transaction, then commits them all if every preparation succeeded, or
+    /// rolls them all back otherwise.
+    pub fn commit(mut group: Self) -> Result<(), TxnError<E>> {
+        // Members stay in the group, which rolls them back if preparing one panics.
+        for index in 0..group.members.len() {
+            if let Err(cause) = group.members[index].prepare() {
+                return Err(TxnError::Aborted {
+                    index,
+                    cause,
+                    rollback_errors: roll_back_all(core::mem::take(&mut group.members)),
+                });
+            }
+        }
+        let mut pending =
+            ConsumeOnDrop::new(Committing(core::mem::take(&mut group.members).into_iter()));
+        let mut commit_errors = Vec::new();
+        for (index, member) in pending.0.by_ref().enumerate() {
+            if let Err(error) = member.commit() {
+                commit_errors.push((index, error));
+            }
+        }
+        if commit_errors.is_empty() {
+            Ok(())
+        } else {
+            Err(TxnError::Incomplete { commit_errors })
+        }
+    }
+
+    /// Rolls back every transaction now, in reverse order, and reports the errors in that
+    /// order.
+    pub fn rollback(mut group: Self) -> Result<(), Vec<E>> {
+        let errors = roll_back_all(core::mem::take(&mut group.members));
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+impl<E> Default for TxnGroup<'_, E> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E> Drop for TxnGroup<'_, E> {
+    fn drop(&mut self) {
+        let members = core::mem::take(&mut self.members);
+        consume_all(members.into_iter().rev().map(RollingBack::quietly))
+    }
+}
+
+impl<E> fmt::Debug for TxnGroup<'_, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TxnGroup")
+            .field("len", &self.len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// The failure of [`TxnGroup::commit`].
+#[derive(Debug)]
+pub enum TxnError<E> {
+    /// The transaction at `index` failed to prepare with `cause`, so every transaction was
+    /// rolled back. Errors from rolling back are listed in the order the transactions were
+    /// rolled back, which is the reverse of the order they were added.
+    Aborted {
+        /// The index of the transaction which failed to prepare.
+        index: usize,
+        /// The error from preparing it.
+        cause: E,
+        /// The errors from rolling back the transactions.
+        rollback_errors: Vec<E>,
+    },
+    /// Every transaction was prepared, but the ones listed with their indexes failed to commit.
+    /// The others were committed.
+    Incomplete {
+        /// The indexes of the transactions which failed to commit, with their errors.
+        commit_errors: Vec<(usize, E)>,
+    },
+}
+
+impl<E: fmt::Display> fmt::Display for TxnError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TxnError::Aborted {
+                index,
+                cause,
+                rollback_errors,
+            } => {
+                write!(f, "transaction {} failed to prepare: {}", index, cause)?;
+                for error in rollback_errors {
+                    write!(f, "; rollback failed: {}", error)?;
+                }
+                Ok(())
+            }
+            TxnError::Incomplete { commit_errors } => {
+                f.write_str("transactions were only partially committed")?;
+                for (index, error) in commit_errors {
+                    write!(f, "; transaction {} failed to commit: {}", index, error)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: fmt::Debug + fmt::Display> std::error::Error for TxnError<E> {}
+
+#[cfg(test)]
+mod tests {
+    use super::{Transaction, Transactional, TxnError, TxnGroup};
+    use alloc::vec::Vec;
+    use core::cell::RefCell;
+
+    struct Step<'a> {
+        name: &'static str,
+        fail_prepare: bool,
+        log: &'a RefCell<Vec<&'static str>>,
+    }
+
+    impl Transactional for Step<'_> {
+        type Error = &'static str;
+
+        fn prepare(&mut self) -> Result<(), &'static str> {
+            if self.name == "panic" {
+                panic!("prepare panicked");
+            }
+            if self.fail_prepare {
+                return Err(self.name);
+            }
+            Ok(())
+        }
+
+        fn commit(self) -> Result<(), &'static str> {
+            self.log.borrow_mut().push(self.name);
+            Ok(())
+        }
+
+        fn rollback(self) -> Result<(), &'static str> {
+            self.log.borrow_mut().push("undo");
+            Err(self.name)
+        }
+    }
+
+    #[test]
+    fn all_or_nothing() {
+        let log = RefCell::new(Vec::new());
+        let step = |name, fail_prepare| {
+            Transaction::new(Step {
+                name,
+                fail_prepare,
+                log: &log,
+            })
+        };
+
+        let mut group = TxnGroup::new();
+        group.push(step("file", false));
+        group.push(step("index", false));
+        TxnGroup::<&str>::commit(group).unwrap();
+        assert_eq!(*log.borrow(), ["file", "index"]);
+
+        let mut group = TxnGroup::new();
+        group.push(step("file", false));
+        group.push(step("index", true));
+        group.push(step("peer", false));
+        match TxnGroup::<&str>::commit(group) {
+            Err(TxnError::Aborted {
+                index,
+                cause,
+                rollback_errors,
+            }) => {
+                assert_eq!((index, cause), (1, "index"));
+                assert_eq!(rollback_errors, ["peer", "index", "file"]);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        assert_eq!(log.borrow()[2..], ["undo"; 3]);
+
+        drop(step("dropped", false));
+        assert_eq!(log.borrow().len(), 6);
+    }
+
+    #[test]
+    fn panicking_prepare_rolls_back() {
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+
+        let log = RefCell::new(Vec::new());
+        let step = |name| {
+            Transaction::new(Step {
+                name,
+                fail_prepare: false,
+                log: &log,
+            })
+        };
+
+        let result = catch_unwind(AssertUnwindSafe(|| Transaction::commit(step("panic"))));
+        assert!(result.is_err());
+        assert_eq!(*log.borrow(), ["undo"]);
+
+        let mut group = TxnGroup::new();
+        group.push(step("file"));
+        group.push(step("panic"));
+        group.push(step("peer"));
+        let result = catch_unwind(AssertUnwindSafe(|| TxnGroup::<&str>::commit(group)));
+        assert!(result.is_err());
+        assert_eq!(*log.borrow(), ["undo"; 4]);
+    }
+}
@@ -0,0 +1,112 @@
+use crate::{Consumer, WithConsumer};
+use core::marker::PhantomData;
+use core::ops::{Deref, DerefMut};
+
+/// A [`Consumer`] storing the value it receives in an [`Option`], dropping any previous value.
+#[derive(Debug)]
+pub struct Slot<'a, T>(pub &'a mut Option<T>);
+
+impl<T> Consumer<T> for Slot<'_, T> {
+    #[inline]
+    fn consume(self, other: T) {
+        *self.0 = Some(other)
+    }
+}
+
+/// The [`Consumer`] used by [`TransitionGuard`]. `B` is a parameter only so that the impl below
+/// can name the output of `F`.
+#[derive(Debug)]
+struct Transition<B, F, S> {
+    transition: F,
+    sink: S,
+    _output: PhantomData<fn() -> B>,
+}
+
+impl<A, B, F: FnOnce(A) -> B, S: Consumer<B>> Consumer<A> for Transition<B, F, S> {
+    #[inline]
+    fn consume(self, other: A) {
+        self.sink.consume((self.transition)(other))
+    }
+}
+
+/// A state `A` which must transition to a state `B` before the scope holding it is left.
+///
+/// When the guard is dropped, including while unwinding, the transition `F` is applied to the
+/// current state and the resulting `B` is handed to the [`Consumer<B>`] `S`. A [`Slot`]
+/// works as a simple sink. The transition can also be run explicitly with
+/// [`TransitionGuard::finish`], which returns the `B` directly instead.
+#[derive(Debug)]
+pub struct TransitionGuard<A, B, F: FnOnce(A) -> B, S: Consumer<B>> {
+    inner: WithConsumer<A, Transition<B, F, S>>,
+}
+
+impl<A, B, F: FnOnce(A) -> B, S: Consumer<B>> TransitionGuard<A, B, F, S> {
+    /// Guards `state`, which will be passed through `transition` into `sink`.
+    #[inline]
+    pub const fn new(state: A, transition: F, sink: S) -> Self {
+        Self {
+            inner: WithConsumer::new(
+                state,
+                Transition {
+                    transition,
+                    sink,
+                    _output: PhantomData,
+                },
+            ),
+        }
+    }
+
+    /// Runs the transition now, returning the new state rather than delivering it to the sink.
+    #[inline]
+    pub fn finish(x: Self) -> B {
+        let (state, consumer) = WithConsumer::into_pair(x.inner);
+        (consumer.transition)(state)
+    }
+
+    /// Runs the transition now and delivers the new state to the sink, exactly as dropping `x`
+    /// would.
+    #[inline]
+    pub fn transition(x: Self) {
+        drop(x)
+    }
+}
+
+impl<A, B, F: FnOnce(A) -> B, S: Consumer<B>> Deref for TransitionGuard<A, B, F, S> {
+    type Target = A;
+
+    #[inline]
+    fn deref(&self) -> &A {
+        &self.inner
+    }
+}
+
+impl<A, B, F: FnOnce(A) -> B, S: Consumer<B>> DerefMut for TransitionGuard<A, B, F, S> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut A {
+        &mut self.inner
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Slot, TransitionGuard};
+
+    #[test]
+    fn transitions_on_exit() {
+        struct Open(u32);
+        #[derive(Debug, PartialEq)]
+        struct Closed(u32);
+
+        let mut closed = None;
+        {
+            let mut session =
+                TransitionGuard::new(Open(0), |s: Open| Closed(s.0), Slot(&mut closed));
+            session.0 += 2;
+        }
+        assert_eq!(closed, Some(Closed(2)));
+
+        let session = TransitionGuard::new(Open(5), |s: Open| Closed(s.0), Slot(&mut closed));
+        assert_eq!(TransitionGuard::finish(session), Closed(5));
+        assert_eq!(closed, Some(Closed(2)));
+    }
+}
@@ -0,0 +1,185 @@
+use crate::{Closure, Consume, ConsumeOnDrop};
+use core::fmt;
+use core::ops::{Deref, DerefMut};
+
+/// A fallible counterpart to [`Consume`](crate::Consume), for types whose means of consumption
+/// can fail, such as a connection whose `close` returns a [`Result`].
+pub trait TryConsume {
+    /// The error produced when consumption fails.
+    type Error;
+
+    /// Consumes `self`, reporting whether consumption succeeded.
+    fn try_consume(self) -> Result<(), Self::Error>;
+}
+
+impl<E, F: FnOnce() -> Result<(), E>> TryConsume for Closure<F> {
+    type Error = E;
+
+    #[inline]
+    fn try_consume(self) -> Result<(), E> {
+        (self.0)()
+    }
+}
+
+/// A fallible counterpart to [`Consumer<T>`](crate::Consumer).
+pub trait TryConsumer<T> {
+    /// The error produced when consumption fails.
+    type Error;
+
+    /// Consumes `other`, using up `self` in the process and reporting whether consumption
+    /// succeeded.
+    fn try_consume(self, other: T) -> Result<(), Self::Error>;
+}
+
+impl<T, E, F: FnOnce(T) -> Result<(), E>> TryConsumer<T> for Closure<F> {
+    type Error = E;
+
+    #[inline]
+    fn try_consume(self, other: T) -> Result<(), E> {
+        (self.0)(other)
+    }
+}
+
+/// What a [`TryConsumeOnDrop`] does with the error when consuming its value on drop fails.
+pub trait DropErrorPolicy<E> {
+    /// Handles `error`.
+    fn on_error(self, error: E);
+}
+
+/// A [`DropErrorPolicy`] which ignores errors.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct IgnoreErrors;
+
+impl<E> DropErrorPolicy<E> for IgnoreErrors {
+    #[inline]
+    fn on_error(self, _error: E) {}
+}
+
+/// A [`DropErrorPolicy`] which panics with the error.
+///
+/// With the `std` feature, the error is ignored instead if the guard is dropped while the thread
+/// is already panicking, since panicking again would abort the process.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct PanicOnError;
+
+impl<E: fmt::Debug> DropErrorPolicy<E> for PanicOnError {
+    fn on_error(self, error: E) {
+        #[cfg(feature = "std")]
+        if std::thread::panicking() {
+            return;
+        }
+        panic!("consuming a value on drop failed: {:?}", error)
+    }
+}
+
+/// Passes the error to the closure, e.g. to log it.
+impl<E, F: FnOnce(E)> DropErrorPolicy<E> for Closure<F> {
+    #[inline]
+    fn on_error(self, error: E) {
+        (self.0)(error)
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+struct WithErrorPolicy<T, P> {
+    value: T,
+    policy: P,
+}
+
+impl<T: TryConsume, P: DropErrorPolicy<T::Error>> Consume for WithErrorPolicy<T, P> {
+    #[inline]
+    fn consume(self) {
+        if let Err(error) = self.value.try_consume() {
+            self.policy.on_error(error)
+        }
+    }
+}
+
+/// A guard which consumes a [`TryConsume`] value when dropped, and hands any error to the
+/// [`DropErrorPolicy`] `P`.
+///
+/// Errors on drop are ignored by default. Use [`TryConsumeOnDrop::with_policy`] to panic with
+/// them or forward them to a callback instead, or consume the value with
+/// [`TryConsumeOnDrop::try_consume_now`] to handle the error at the call site.
+#[derive(Clone, Debug, Default)]
+pub struct TryConsumeOnDrop<T: TryConsume, P: DropErrorPolicy<T::Error> = IgnoreErrors> {
+    inner: ConsumeOnDrop<WithErrorPolicy<T, P>>,
+}
+
+impl<T: TryConsume> TryConsumeOnDrop<T> {
+    /// Guards `value`, ignoring any error when it is consumed on drop.
+    #[inline]
+    pub const fn new(value: T) -> Self {
+        Self::with_policy(value, IgnoreErrors)
+    }
+}
+
+impl<T: TryConsume, P: DropErrorPolicy<T::Error>> TryConsumeOnDrop<T, P> {
+    /// Guards `value`, handing any error when it is consumed on drop to `policy`.
+    #[inline]
+    pub const fn with_policy(value: T, policy: P) -> Self {
+        Self {
+            inner: ConsumeOnDrop::new(WithErrorPolicy { value, policy }),
+        }
+    }
+
+    /// Consumes the value now, returning any error to the caller instead of the policy.
+    #[inline]
+    pub fn try_consume_now(x: Self) -> Result<(), T::Error> {
+        Self::into_inner(x).try_consume()
+    }
+
+    /// Unwraps the value without consuming it.
+    #[inline]
+    pub fn into_inner(x: Self) -> T {
+        ConsumeOnDrop::into_inner(x.inner).value
+    }
+}
+
+impl<T: TryConsume, P: DropErrorPolicy<T::Error>> Deref for TryConsumeOnDrop<T, P> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        &self.inner.value
+    }
+}
+
+impl<T: TryConsume, P: DropErrorPolicy<T::Error>> DerefMut for TryConsumeOnDrop<T, P> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.inner.value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PanicOnError, TryConsumeOnDrop};
+    use crate::Closure;
+    use core::cell::Cell;
+
+    #[test]
+    fn drop_time_error_policies() {
+        let close = |ok| Closure(move || if ok { Ok(()) } else { Err("connection reset") });
+
+        drop(TryConsumeOnDrop::new(close(false)));
+        let reported = Cell::new(None);
+        let guard = TryConsumeOnDrop::with_policy(close(false), Closure(|e| reported.set(Some(e))));
+        drop(guard);
+        assert_eq!(reported.get(), Some("connection reset"));
+
+        let guard = TryConsumeOnDrop::with_policy(close(false), PanicOnError);
+        assert_eq!(
+            TryConsumeOnDrop::try_consume_now(guard),
+            Err("connection reset")
+        );
+        drop(TryConsumeOnDrop::with_policy(close(true), PanicOnError));
+        #[cfg(feature = "std")]
+        {
+            let dropped = std::panic::catch_unwind(|| {
+                drop(TryConsumeOnDrop::with_policy(close(false), PanicOnError))
+            });
+            assert!(dropped.is_err());
+        }
+    }
+}
@@ -0,0 +1,81 @@
+use crate::{Consumer, WithConsumer};
+use js_sys::{Function, Reflect, TypeError};
+use wasm_bindgen::{JsCast, JsValue};
+
+/// The default error hook used by [`CallJsMethod`]. It silently ignores the thrown value.
+fn ignore_js_error(_error: JsValue) {}
+
+/// A [`Consumer`] which releases a JavaScript resource by calling a method on it which takes
+/// no arguments, such as `free()` or `close()`, and then drops the reference to it.
+///
+/// Anything which is [`AsRef<JsValue>`] can be consumed, which includes [`JsValue`] itself and
+/// every type imported with `#[wasm_bindgen]`. If the method is missing or throws, the thrown
+/// value is passed to the hook `F`. By default, such errors are ignored.
+///
+/// Note: [`JsValue`] is neither [`Send`] nor [`Sync`], so the method always runs on the JS
+/// thread which created the value.
+#[derive(Debug, Clone, Copy)]
+pub struct CallJsMethod<F = fn(JsValue)> {
+    method: &'static str,
+    on_error: F,
+}
+
+impl CallJsMethod {
+    /// Builds a consumer which calls `method`, ignoring any errors.
+    #[inline]
+    pub const fn new(method: &'static str) -> Self {
+        Self {
+            method,
+            on_error: ignore_js_error,
+        }
+    }
+
+    /// Builds a consumer which calls `free()`, as generated by `wasm-bindgen` for exported
+    /// Rust types.
+    #[inline]
+    pub const fn free() -> Self {
+        Self::new("free")
+    }
+
+    /// Builds a consumer which calls `close()`, as used by streams, sockets, and many
+    /// web APIs.
+    #[inline]
+    pub const fn close() -> Self {
+        Self::new("close")
+    }
+}
+
+impl<F: FnOnce(JsValue)> CallJsMethod<F> {
+    /// Builds a consumer which calls `method`, passing anything it throws to `on_error`.
+    #[inline]
+    pub const fn with_hook(method: &'static str, on_error: F) -> Self {
+        Self { method, on_error }
+    }
+
+    /// The name of the method this consumer calls.
+    #[inline]
+    pub const fn method(&self) -> &'static str {
+        self.method
+    }
+}
+
+impl<T: AsRef<JsValue>, F: FnOnce(JsValue)> Consumer<T> for CallJsMethod<F> {
+    fn consume(self, other: T) {
+        let this = other.as_ref();
+        let result = Reflect::get(this, &JsValue::from_str(self.method)).and_then(|method| {
+            match method.dyn_ref::<Function>() {
+                Some(method) => method.call0(this),
+                None => {
+                    Err(TypeError::new(&alloc::format!("{} is not a function", self.method)).into())
+                }
+            }
+        });
+        if let Err(error) = result {
+            (self.on_error)(error)
+        }
+    }
+}
+
+/// A JavaScript value which is released with a method call when dropped.
+/// See [`CallJsMethod`].
+pub type JsGuard<T = JsValue, F = fn(JsValue)> = WithConsumer<T, CallJsMethod<F>>;
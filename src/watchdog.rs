@@ -0,0 +1,253 @@
+use crate::label::observer::{consume_labeled, observer};
+use crate::{Clock, Consume, ConsumeOnDrop, SystemClock};
+use core::cmp::Reverse;
+use core::fmt;
+use core::ops::{Deref, DerefMut};
+use core::sync::atomic::{AtomicU64, Ordering};
+use core::time::Duration;
+use std::collections::BTreeMap;
+use std::string::{String, ToString};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread::{self, JoinHandle};
+use std::time::Instant;
+use std::vec::Vec;
+
+struct Entry {
+    label: String,
+    since: Instant,
+    reported: bool,
+}
+
+static NEXT_TICKET: AtomicU64 = AtomicU64::new(0);
+static WATCHED: Mutex<BTreeMap<u64, Entry>> = Mutex::new(BTreeMap::new());
+static CLOCK: RwLock<Option<Arc<dyn Clock>>> = RwLock::new(None);
+
+fn clock() -> Option<Arc<dyn Clock>> {
+    CLOCK
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .clone()
+}
+
+fn now() -> Instant {
+    clock().map_or_else(Instant::now, |clock| clock.now())
+}
+
+/// Sets the [`Clock`] which measures how long [`Watched`] values are held, and paces the
+/// thread of [`spawn_watchdog`]. The default is the [`SystemClock`].
+///
+/// Values built earlier keep the construction times read from the previous clock, so this
+/// should be called before any [`Watched`] value is built.
+pub fn set_watchdog_clock(clock: impl Clock + 'static) {
+    *CLOCK
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(Arc::new(clock));
+}
+
+fn watched() -> std::sync::MutexGuard<'static, BTreeMap<u64, Entry>> {
+    WATCHED
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Keeps a [`Watched`] value in the registry for as long as it exists.
+struct Ticket(u64);
+
+impl Ticket {
+    fn register(label: String) -> Self {
+        let id = NEXT_TICKET.fetch_add(1, Ordering::Relaxed);
+        let entry = Entry {
+            label,
+            since: now(),
+            reported: false,
+        };
+        watched().insert(id, entry);
+        Self(id)
+    }
+}
+
+impl Drop for Ticket {
+    fn drop(&mut self) {
+        watched().remove(&self.0);
+    }
+}
+
+/// A labeled value whose construction time is recorded in a program-wide registry, so that
+/// guards which are held for too long can be found while they are still alive.
+///
+/// Drop-based leak detection never notices a guard which is stuck forever in a map. A
+/// [`Watched`] value is listed by [`long_held_guards`] and reported by [`report_long_held`]
+/// until it is consumed or unwrapped. Otherwise, it behaves like a [`Labeled`](crate::Labeled)
+/// value. Build one with [`ConsumeOnDrop::new_watched`].
+pub struct Watched<T, L = &'static str> {
+    value: T,
+    label: L,
+    ticket: Ticket,
+}
+
+impl<T, L: fmt::Display> Watched<T, L> {
+    /// Pairs `value` with `label`, and starts watching it.
+    pub fn new(value: T, label: L) -> Self {
+        let ticket = Ticket::register(label.to_string());
+        Self {
+            value,
+            label,
+            ticket,
+        }
+    }
+}
+
+impl<T, L> Watched<T, L> {
+    /// The label attached to `x`.
+    #[inline]
+    pub fn label(x: &Self) -> &L {
+        &x.label
+    }
+
+    /// How long ago `x` was constructed.
+    pub fn held_for(x: &Self) -> Duration {
+        let now = now();
+        watched().get(&x.ticket.0).map_or(Duration::ZERO, |entry| {
+            now.saturating_duration_since(entry.since)
+        })
+    }
+
+    /// Separates the value from its label, and stops watching it.
+    #[inline]
+    pub fn into_parts(x: Self) -> (T, L) {
+        (x.value, x.label)
+    }
+
+    /// Extracts the value, dropping the label, and stops watching it.
+    #[inline]
+    pub fn into_inner(x: Self) -> T {
+        x.value
+    }
+}
+
+impl<T: Consume, L: fmt::Display> Consume for Watched<T, L> {
+    fn consume(self) {
+        drop(self.ticket);
+        consume_labeled(self.value, &self.label)
+    }
+}
+
+impl<T: fmt::Debug, L: fmt::Debug> fmt::Debug for Watched<T, L> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Watched")
+            .field("value", &self.value)
+            .field("label", &self.label)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T, L> Deref for Watched<T, L> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T, L> DerefMut for Watched<T, L> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
+
+impl<T: Consume> ConsumeOnDrop<T> {
+    /// Wraps a `T` in a [`ConsumeOnDrop`] along with a label, and watches how long it is held.
+    /// See [`Watched`].
+    #[inline]
+    pub fn new_watched<L: fmt::Display>(value: T, label: L) -> ConsumeOnDrop<Watched<T, L>> {
+        ConsumeOnDrop::new(Watched::new(value, label))
+    }
+}
+
+/// Lists the label of every [`Watched`] value which has been alive for at least `threshold`,
+/// along with how long it has been alive, oldest first.
+pub fn long_held_guards(threshold: Duration) -> Vec<(String, Duration)> {
+    let now = now();
+    let mut held: Vec<_> = watched()
+        .values()
+        .map(|entry| {
+            (
+                entry.label.clone(),
+                now.saturating_duration_since(entry.since),
+            )
+        })
+        .filter(|(_, held)| *held >= threshold)
+        .collect();
+    held.sort_by_key(|&(_, held)| Reverse(held));
+    held
+}
+
+/// Reports every [`Watched`] value which has been alive for at least `threshold` to the
+/// [`GuardObserver::held_too_long`](crate::GuardObserver::held_too_long) hook, or on standard
+/// error if no observer is installed. Each value is reported at most once. Returns the number
+/// of values reported by this call.
+pub fn report_long_held(threshold: Duration) -> usize {
+    let now = now();
+    let mut reports = Vec::new();
+    for entry in watched().values_mut() {
+        let held = now.saturating_duration_since(entry.since);
+        if !entry.reported && held >= threshold {
+            entry.reported = true;
+            reports.push((entry.label.clone(), held));
+        }
+    }
+    // The registry is unlocked here, so the observer may construct or drop watched guards.
+    for (label, held) in &reports {
+        match observer() {
+            Some(observer) => observer.held_too_long(label, *held),
+            None => std::eprintln!("guard `{}` has been held for {:?}", label, held),
+        }
+    }
+    reports.len()
+}
+
+/// Spawns a background thread which calls [`report_long_held`] with `threshold` every
+/// `interval`, for the rest of the program.
+pub fn spawn_watchdog(threshold: Duration, interval: Duration) -> JoinHandle<()> {
+    thread::Builder::new()
+        .name("consume_on_drop watchdog".into())
+        .spawn(move || loop {
+            let clock = clock().unwrap_or_else(|| Arc::new(SystemClock));
+            clock.sleep_until(clock.now() + interval);
+            report_long_held(threshold);
+        })
+        .expect("failed to spawn the watchdog thread")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{long_held_guards, Watched};
+    use crate::{Closure, ConsumeOnDrop};
+    use core::time::Duration;
+    use std::thread;
+
+    #[test]
+    fn lists_live_guards() {
+        let mut count = 0;
+        let guard = ConsumeOnDrop::new_watched(Closure(|| count += 1), "watched-lease");
+        thread::sleep(Duration::from_millis(5));
+        assert!(Watched::held_for(&guard) >= Duration::from_millis(5));
+        let listed = |label| {
+            long_held_guards(Duration::from_millis(5))
+                .iter()
+                .any(|(held, _)| held == label)
+        };
+        assert!(listed("watched-lease"));
+        drop(guard);
+        assert_eq!(count, 1);
+        assert!(!listed("watched-lease"));
+
+        let guard = ConsumeOnDrop::new_watched(Closure(|| ()), "unwrapped-lease");
+        Watched::into_inner(ConsumeOnDrop::into_inner(guard));
+        assert!(long_held_guards(Duration::ZERO)
+            .iter()
+            .all(|(held, _)| held != "unwrapped-lease"));
+    }
+}
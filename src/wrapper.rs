@@ -0,0 +1,116 @@
+/// Declares `#[repr(transparent)]` newtypes which implement [`Consume`](crate::Consume) for
+/// foreign types.
+///
+/// [`Consume`](crate::Consume) cannot be implemented directly on a type from another crate, such
+/// as a handle whose destructor is a function taking it by value.
+/// `consume_wrapper!(pub Name(Type) => destroy);` declares `pub struct Name(Type)`, consumed by
+/// calling `destroy` with the wrapped `Type`, where `destroy` is any expression which can be
+/// called that way, such as a path to a function. The newtype has the same layout as `Type`,
+/// dereferences to it, and can be built with `Name::new` and unwrapped without being consumed
+/// with `Name::into_inner`. Attributes, such as doc comments or derives, are applied to the
+/// struct. Several newtypes can be declared at once, separated by semicolons.
+///
+/// ```
+/// use consume_on_drop::{consume_wrapper, ConsumeOnDrop};
+///
+/// mod vk {
+///     pub struct Buffer(pub u64);
+///     pub struct Image(pub u64);
+/// }
+///
+/// fn destroy_buffer(buffer: vk::Buffer) {
+///     println!("destroyed buffer {}", buffer.0)
+/// }
+///
+/// consume_wrapper! {
+///     /// A buffer which is destroyed when consumed.
+///     pub WrappedBuffer(vk::Buffer) => destroy_buffer;
+///     WrappedImage(vk::Image) => |image: vk::Image| println!("destroyed image {}", image.0);
+/// }
+///
+/// let buffer = ConsumeOnDrop::new(WrappedBuffer::new(vk::Buffer(1)));
+/// let raw: &vk::Buffer = &buffer; // through both guard and newtype
+/// assert_eq!(raw.0, 1);
+/// let image = WrappedImage::new(vk::Image(2));
+/// let image: vk::Image = WrappedImage::into_inner(image); // not destroyed
+/// # drop((buffer, image));
+/// ```
+#[macro_export]
+macro_rules! consume_wrapper {
+    ($($(#[$meta:meta])* $vis:vis $name:ident($ty:ty) => $consume:expr);+ $(;)?) => {
+        $(
+            $(#[$meta])*
+            #[repr(transparent)]
+            $vis struct $name($ty);
+
+            impl $name {
+                /// Wraps `value`, so that it can be consumed.
+                #[inline]
+                #[allow(dead_code)]
+                $vis const fn new(value: $ty) -> Self {
+                    Self(value)
+                }
+
+                /// Unwraps the value without consuming it.
+                #[inline]
+                #[allow(dead_code)]
+                $vis fn into_inner(x: Self) -> $ty {
+                    x.0
+                }
+            }
+
+            impl ::core::ops::Deref for $name {
+                type Target = $ty;
+
+                #[inline]
+                fn deref(&self) -> &$ty {
+                    &self.0
+                }
+            }
+
+            impl ::core::ops::DerefMut for $name {
+                #[inline]
+                fn deref_mut(&mut self) -> &mut $ty {
+                    &mut self.0
+                }
+            }
+
+            impl $crate::Consume for $name {
+                #[inline]
+                fn consume(self) {
+                    ($consume)(self.0)
+                }
+            }
+        )+
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ConsumeOnDrop;
+    use core::cell::Cell;
+    use core::mem::size_of;
+
+    std::thread_local! {
+        static DESTROYED: Cell<u32> = const { Cell::new(0) };
+    }
+
+    struct Handle(u32);
+
+    fn destroy(handle: Handle) {
+        DESTROYED.with(|destroyed| destroyed.set(destroyed.get() + handle.0))
+    }
+
+    consume_wrapper!(Wrapped(Handle) => destroy);
+
+    #[test]
+    fn wraps_foreign_types() {
+        assert_eq!(size_of::<ConsumeOnDrop<Wrapped>>(), size_of::<Handle>());
+        let mut wrapped = ConsumeOnDrop::new(Wrapped::new(Handle(1)));
+        let handle: &mut Handle = &mut wrapped;
+        handle.0 += 1;
+        drop(wrapped);
+        Wrapped::into_inner(Wrapped::new(Handle(10)));
+        assert_eq!(DESTROYED.with(Cell::get), 2);
+    }
+}